@@ -1,7 +1,7 @@
 //! Performance benchmarks for critical code paths
 
 use anthropic_rust::{
-    types::{ChatRequest, Message, StopReason, SystemMessage, Usage},
+    types::{ChatRequest, ChatRequestBuilder, Message, StopReason, Usage},
     Client, ContentBlock, MessageParam, Model, Role, Tool,
 };
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
@@ -11,6 +11,7 @@ use std::time::Duration;
 fn create_simple_message() -> Message {
     Message {
         id: "msg_bench_123".to_string(),
+        message_type: Some("message".to_string()),
         role: Role::Assistant,
         content: vec![ContentBlock::text(
             "This is a benchmark message for performance testing.",
@@ -30,6 +31,7 @@ fn create_simple_message() -> Message {
 fn create_complex_message() -> Message {
     Message {
         id: "msg_complex_456".to_string(),
+        message_type: Some("message".to_string()),
         role: Role::Assistant,
         content: vec![
             ContentBlock::text("This is a complex message with multiple content blocks."),
@@ -68,6 +70,7 @@ fn create_large_message(content_blocks: usize) -> Message {
 
     Message {
         id: format!("msg_large_{}", content_blocks),
+        message_type: Some("message".to_string()),
         role: Role::Assistant,
         content,
         model: Model::Claude35Sonnet20241022,
@@ -94,27 +97,26 @@ fn create_chat_request(messages: usize) -> ChatRequest {
         })
         .collect();
 
-    ChatRequest {
-        messages,
-        system: Some(vec![SystemMessage {
-            message_type: "text".to_string(),
-            text: "You are a helpful assistant for benchmarking.".to_string(),
-        }]),
-        tools: Some(vec![Tool::builder("calculator")
-            .description("Perform calculations")
-            .schema_value(serde_json::json!({
-                "type": "object",
-                "properties": {
-                    "operation": {"type": "string"},
-                    "a": {"type": "number"},
-                    "b": {"type": "number"}
-                }
-            }))
-            .build()]),
-        temperature: Some(0.7),
-        top_p: Some(0.9),
-        stop_sequences: Some(vec!["STOP".to_string(), "END".to_string()]),
-    }
+    ChatRequestBuilder::new()
+        .messages(messages)
+        .system("You are a helpful assistant for benchmarking.")
+        .tool(
+            Tool::builder("calculator")
+                .description("Perform calculations")
+                .schema_value(serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "operation": {"type": "string"},
+                        "a": {"type": "number"},
+                        "b": {"type": "number"}
+                    }
+                }))
+                .build(),
+        )
+        .temperature(0.7)
+        .top_p(0.9)
+        .stop_sequences(vec!["STOP".to_string(), "END".to_string()])
+        .build()
 }
 
 // Serialization benchmarks
@@ -409,11 +411,11 @@ fn bench_model_operations(c: &mut Criterion) {
         Model::Claude4Sonnet20250514,
     ];
 
-    group.bench_function("max_tokens_lookup", |b| {
+    group.bench_function("context_window_lookup", |b| {
         b.iter(|| {
             for model in &models {
-                let max_tokens = black_box(model).max_tokens();
-                black_box(max_tokens);
+                let context_window = black_box(model).context_window();
+                black_box(context_window);
             }
         })
     });
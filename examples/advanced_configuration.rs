@@ -87,12 +87,12 @@ async fn main() -> Result<()> {
 
     // Example 2: Custom Retry Configuration
     println!("2️⃣ Custom Retry Configuration");
-    let retry_config = RetryConfig {
-        max_retries: 2,
-        initial_delay: Duration::from_millis(100),
-        max_delay: Duration::from_secs(5),
-        backoff_multiplier: 1.5,
-    };
+    let retry_config = RetryConfig::default()
+        .with_max_retries(2)
+        .with_initial_delay(Duration::from_millis(100))
+        .with_max_delay(Duration::from_secs(5))
+        .with_backoff_multiplier(1.5)
+        .with_overloaded_backoff(Duration::from_secs(2));
 
     // Example 3: Custom Interceptors
     println!("3️⃣ Custom Interceptors Setup");
@@ -10,7 +10,7 @@
 //! For testing purposes, you can set it to a dummy value to see the request structure.
 
 use anthropic_rust::{
-    types::{ChatRequest, ContentBlock, MessageParam, Role},
+    types::{ChatRequestBuilder, ContentBlock},
     Client, Error, Model,
 };
 
@@ -95,20 +95,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Example 4: Chat with model override
     println!("\n4. Testing model override...");
-    let conversation_request = ChatRequest {
-        messages: vec![MessageParam {
-            role: Role::User,
-            content: vec![ContentBlock::text("What's the capital of France?")],
-        }],
-        system: Some(vec![anthropic_rust::types::SystemMessage {
-            message_type: "text".to_string(),
-            text: "Be concise and direct in your responses.".to_string(),
-        }]),
-        tools: None,
-        temperature: Some(0.3), // Lower temperature for factual questions
-        top_p: None,
-        stop_sequences: None,
-    };
+    let conversation_request = ChatRequestBuilder::new()
+        .user_message(ContentBlock::text("What's the capital of France?"))
+        .system("Be concise and direct in your responses.")
+        .temperature(0.3) // Lower temperature for factual questions
+        .build();
 
     match client
         .execute_chat_with_model(Model::Claude3Haiku20240307, conversation_request)
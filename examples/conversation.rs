@@ -9,10 +9,7 @@
 //!
 //! Run with: cargo run --example conversation
 
-use anthropic_rust::{
-    types::{ChatRequest, SystemMessage},
-    Client, ContentBlock, MessageParam, Model, Role,
-};
+use anthropic_rust::{types::ChatRequestBuilder, Client, ContentBlock, MessageParam, Model, Role};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -81,18 +78,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }];
 
     // First exchange
-    let request = ChatRequest {
-        messages: conversation_history.clone(),
-        system: Some(vec![SystemMessage {
-            message_type: "text".to_string(),
-            text: "You are a knowledgeable travel advisor. Provide helpful, practical advice."
-                .to_string(),
-        }]),
-        tools: None,
-        temperature: Some(0.7),
-        top_p: None,
-        stop_sequences: None,
-    };
+    let request = ChatRequestBuilder::new()
+        .messages(conversation_history.clone())
+        .system("You are a knowledgeable travel advisor. Provide helpful, practical advice.")
+        .temperature(0.7)
+        .build();
 
     match client.execute_chat(request).await {
         Ok(response) => {
@@ -115,19 +105,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 )],
             });
 
-            let follow_up_request = ChatRequest {
-                messages: conversation_history.clone(),
-                system: Some(vec![SystemMessage {
-                    message_type: "text".to_string(),
-                    text:
-                        "You are a knowledgeable travel advisor. Provide helpful, practical advice."
-                            .to_string(),
-                }]),
-                tools: None,
-                temperature: Some(0.7),
-                top_p: None,
-                stop_sequences: None,
-            };
+            let follow_up_request = ChatRequestBuilder::new()
+                .messages(conversation_history.clone())
+                .system(
+                    "You are a knowledgeable travel advisor. Provide helpful, practical advice.",
+                )
+                .temperature(0.7)
+                .build();
 
             if let Ok(response2) = client.execute_chat(follow_up_request).await {
                 println!("User: What about the best time to visit for cherry blossoms?");
@@ -233,17 +217,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         });
 
         // Create request with full history
-        let interactive_request = ChatRequest {
-            messages: interactive_history.clone(),
-            system: Some(vec![SystemMessage {
-                message_type: "text".to_string(),
-                text: "You are Claude, a helpful AI assistant. Be conversational and engaging.".to_string(),
-            }]),
-            tools: None,
-            temperature: Some(0.7),
-            top_p: None,
-            stop_sequences: None,
-        };
+        let interactive_request = ChatRequestBuilder::new()
+            .messages(interactive_history.clone())
+            .system("You are Claude, a helpful AI assistant. Be conversational and engaging.")
+            .temperature(0.7)
+            .build();
 
         match client.execute_chat(interactive_request).await {
             Ok(response) => {
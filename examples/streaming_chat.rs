@@ -72,7 +72,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         StreamEvent::ContentBlockDelta { delta, .. } => {
-                            let anthropic_rust::ContentDelta::TextDelta { text } = delta;
+                            let anthropic_rust::ContentDelta::TextDelta { text } = delta else {
+                                continue;
+                            };
                             print!("{}", text);
                             accumulated_text.push_str(&text);
                             token_count += text.split_whitespace().count();
@@ -192,7 +194,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 while let Some(event_result) = stream.next().await {
                     match event_result {
                         Ok(StreamEvent::ContentBlockDelta { delta, .. }) => {
-                            let anthropic_rust::ContentDelta::TextDelta { text } = delta;
+                            let anthropic_rust::ContentDelta::TextDelta { text } = delta else {
+                                continue;
+                            };
                             print!("{}", text);
                             char_count += text.len();
                             io::stdout().flush()?;
@@ -249,7 +253,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             io::stdout().flush()?;
                         }
                         StreamEvent::ContentBlockDelta { delta, .. } => {
-                            let anthropic_rust::ContentDelta::TextDelta { text } = delta;
+                            let anthropic_rust::ContentDelta::TextDelta { text } = delta else {
+                                continue;
+                            };
                             progress_chars += text.len();
                             if progress_chars % 50 == 0 {
                                 print!(".");
@@ -24,6 +24,7 @@ async fn main() -> Result<()> {
         }],
         system: None,
         tools: None,
+        extra: Default::default(),
     };
 
     match client.count_tokens(simple_request).await {
@@ -55,8 +56,9 @@ async fn main() -> Result<()> {
         system: Some(vec![SystemMessage {
             message_type: "text".to_string(),
             text: "You are a helpful geography assistant. Provide accurate and concise information about world geography.".to_string(),
+            cache_control: None,
         }]),
-        tools: None,
+        tools: None, extra: Default::default(), 
     };
 
     match client.count_tokens(conversation_request).await {
@@ -82,7 +84,7 @@ async fn main() -> Result<()> {
             ],
         }],
         system: None,
-        tools: None,
+        tools: None, extra: Default::default(), 
     };
 
     match client.count_tokens(multimodal_request).await {
@@ -132,8 +134,9 @@ async fn main() -> Result<()> {
         system: Some(vec![SystemMessage {
             message_type: "text".to_string(),
             text: "You are a helpful assistant with access to a calculator tool. Use the calculator for any arithmetic operations.".to_string(),
+            cache_control: None,
         }]),
-        tools: Some(vec![calculator_tool]),
+        tools: Some(vec![calculator_tool]), extra: Default::default(), 
     };
 
     match client.count_tokens(tools_request).await {
@@ -159,6 +162,7 @@ async fn main() -> Result<()> {
         }],
         system: None,
         tools: None,
+        extra: Default::default(),
     };
 
     let models = vec![
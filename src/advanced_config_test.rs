@@ -235,6 +235,8 @@ mod tests {
             initial_delay: Duration::from_millis(100),
             max_delay: Duration::from_secs(10),
             backoff_multiplier: 1.5,
+            overloaded_backoff: Duration::from_secs(2),
+            retryable_statuses: None,
         };
 
         let result = ClientBuilder::new()
@@ -316,6 +318,8 @@ mod tests {
             initial_delay: Duration::from_millis(200),
             max_delay: Duration::from_secs(5),
             backoff_multiplier: 1.8,
+            overloaded_backoff: Duration::from_secs(2),
+            retryable_statuses: None,
         };
 
         let logging_interceptor = LoggingInterceptor::new()
@@ -0,0 +1,138 @@
+//! In-memory caches used by the client
+
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+
+use crate::types::{Message, TokenCount};
+
+/// Pluggable store for chat responses
+///
+/// Set with [`ClientBuilder::response_cache`](crate::ClientBuilder::response_cache).
+/// Only deterministic requests (temperature 0, no tools, no extended thinking) are
+/// cached; keys are derived from the full request body, including model and
+/// `max_tokens`.
+pub trait ResponseCache: Send + Sync + std::fmt::Debug {
+    /// Look up a cached response
+    fn get<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Option<Message>> + Send + 'a>>;
+
+    /// Store a successful response
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        message: Message,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+}
+
+/// Least-recently-used cache for token counting results, keyed by request content hash
+#[derive(Debug)]
+pub(crate) struct TokenCountCache {
+    capacity: usize,
+    state: Mutex<LruState>,
+}
+
+#[derive(Debug, Default)]
+struct LruState {
+    entries: HashMap<u64, TokenCount>,
+    order: VecDeque<u64>,
+}
+
+impl LruState {
+    /// Move a key to the most-recently-used position
+    fn touch(&mut self, key: u64) {
+        if let Some(position) = self.order.iter().position(|k| *k == key) {
+            self.order.remove(position);
+        }
+        self.order.push_back(key);
+    }
+}
+
+impl TokenCountCache {
+    /// Create a new cache holding at most `capacity` entries
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(LruState::default()),
+        }
+    }
+
+    /// Look up a cached token count, marking it as recently used
+    pub(crate) fn get(&self, key: u64) -> Option<TokenCount> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let value = state.entries.get(&key).cloned()?;
+        state.touch(key);
+        Some(value)
+    }
+
+    /// Store a token count, evicting the least recently used entry when full
+    pub(crate) fn put(&self, key: u64, value: TokenCount) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if !state.entries.contains_key(&key) && state.entries.len() >= self.capacity {
+            if let Some(oldest) = state.order.pop_front() {
+                state.entries.remove(&oldest);
+            }
+        }
+        state.entries.insert(key, value);
+        state.touch(key);
+    }
+
+    /// Number of cached entries
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entries
+            .len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(input_tokens: u32) -> TokenCount {
+        TokenCount { input_tokens }
+    }
+
+    #[test]
+    fn test_token_count_cache_hit_and_miss() {
+        let cache = TokenCountCache::new(2);
+        assert!(cache.get(1).is_none());
+
+        cache.put(1, count(10));
+        assert_eq!(cache.get(1).unwrap().input_tokens, 10);
+    }
+
+    #[test]
+    fn test_token_count_cache_evicts_least_recently_used() {
+        let cache = TokenCountCache::new(2);
+        cache.put(1, count(10));
+        cache.put(2, count(20));
+
+        // Touch key 1 so key 2 becomes the eviction candidate
+        assert!(cache.get(1).is_some());
+        cache.put(3, count(30));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(1).is_some());
+        assert!(cache.get(2).is_none());
+        assert!(cache.get(3).is_some());
+    }
+
+    #[test]
+    fn test_token_count_cache_zero_capacity() {
+        let cache = TokenCountCache::new(0);
+        cache.put(1, count(10));
+        assert!(cache.get(1).is_none());
+        assert_eq!(cache.len(), 0);
+    }
+}
@@ -4,23 +4,127 @@
 //! The client supports both synchronous and streaming chat requests, with built-in retry
 //! logic and comprehensive error handling.
 
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
-use futures::Stream;
+use futures::{Stream, TryStreamExt};
 use reqwest::{header::HeaderMap, Response, StatusCode};
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 
 use crate::{
+    cache::{ResponseCache, TokenCountCache},
     config::{ClientBuilder, Config},
     error::Error,
-    streaming::MessageStream,
-    types::{ChatRequest, ChatRequestBuilder, CountTokensRequest, Message, Model, TokenCount},
+    rate_limit::{parse_http_date, RateLimitInfo},
+    streaming::{
+        chunked_event_stream, sse_event_stream, usage_stream, MessageStream, StreamOptions,
+        StreamTransport,
+    },
+    types::{
+        ChatRequest, ChatRequestBuilder, CountTokensRequest, CountTokensRequestBuilder,
+        FileMetadata, Message, MessageParam, Model, StopReason, SystemMessage, TokenCount, Usage,
+    },
+    validation::validate_messages_request,
     Result,
 };
 
+/// Maximum number of times [`Client::resume`] resends a paused turn
+pub const MAX_RESUME_ITERATIONS: usize = 10;
+
+/// Counter of requests and streams currently in flight, used by [`Client::drain`]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct InFlight(Arc<AtomicUsize>);
+
+impl InFlight {
+    /// Register an in-flight operation until the returned guard is dropped
+    pub(crate) fn start(&self) -> InFlightGuard {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(self.0.clone())
+    }
+
+    /// Number of operations currently in flight
+    pub(crate) fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Marks one operation as in flight while alive
+pub(crate) struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Per-call options for chat requests
+///
+/// Unset fields fall back to the client configuration.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChatOptions {
+    /// Model override for this request
+    pub model: Option<Model>,
+    /// Timeout override for this request
+    pub timeout: Option<Duration>,
+    /// Headers merged over the client's default headers for this request
+    pub headers: Option<HeaderMap>,
+}
+
+/// Metadata about the HTTP response a chat message was decoded from
+///
+/// Returned by [`Client::execute_chat_with_response`]. Responses served from the
+/// response cache carry the default value: status 200 with no request ID or headers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseMeta {
+    /// HTTP status of the response
+    pub status: StatusCode,
+    /// Request ID to quote when contacting Anthropic support
+    pub request_id: Option<String>,
+    /// Rate-limit headers (`anthropic-ratelimit-*` and `retry-after`)
+    pub rate_limit_headers: HeaderMap,
+    /// Parsed rate-limit headers, if the response carried any
+    pub rate_limit: Option<RateLimitInfo>,
+    /// Fallback model that served the response because the requested model stayed
+    /// unavailable; see [`ClientBuilder::model_fallback`](crate::ClientBuilder::model_fallback)
+    pub fallback_model: Option<Model>,
+}
+
+impl ResponseMeta {
+    /// Capture status, request ID, and rate-limit headers from a response
+    pub(crate) fn from_response(response: &Response) -> Self {
+        let headers = response.headers();
+        let rate_limit_headers = headers
+            .iter()
+            .filter(|(name, _)| {
+                name.as_str().starts_with("anthropic-ratelimit-") || name.as_str() == "retry-after"
+            })
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+
+        Self {
+            status: response.status(),
+            request_id: extract_request_id(headers),
+            rate_limit: RateLimitInfo::from_headers(headers),
+            rate_limit_headers,
+            fallback_model: None,
+        }
+    }
+
+    /// Look up a rate-limit header value, e.g. `anthropic-ratelimit-requests-remaining`
+    pub fn rate_limit_header(&self, name: &str) -> Option<&str> {
+        self.rate_limit_headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    }
+}
+
 /// Main client for interacting with the Anthropic API.
 ///
 /// The `Client` provides a high-level interface for sending messages to Claude models,
@@ -105,12 +209,20 @@ pub struct Client {
 }
 
 /// Retry configuration for HTTP requests
+///
+/// New settings may be added in minor releases, so build it from
+/// [`RetryConfig::default`] and the `with_*` setters rather than a struct literal.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct RetryConfig {
     pub max_retries: u32,
     pub initial_delay: Duration,
     pub max_delay: Duration,
     pub backoff_multiplier: f64,
+    /// Minimum wait after an overloaded (HTTP 529) response
+    pub overloaded_backoff: Duration,
+    /// HTTP statuses to retry; when set, replaces the default 429/5xx check
+    pub retryable_statuses: Option<HashSet<u16>>,
 }
 
 impl Default for RetryConfig {
@@ -120,10 +232,122 @@ impl Default for RetryConfig {
             initial_delay: Duration::from_millis(500),
             max_delay: Duration::from_secs(30),
             backoff_multiplier: 2.0,
+            overloaded_backoff: Duration::from_secs(5),
+            retryable_statuses: None,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// The statuses retried by default: 429 and every 5xx
+    pub fn default_retryable_statuses() -> HashSet<u16> {
+        std::iter::once(429).chain(500..=599).collect()
+    }
+
+    /// Set the maximum number of retries after the initial attempt
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the delay before the first retry
+    pub fn with_initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// Set the upper bound on the delay between retries
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Set the factor the delay grows by after each retry
+    pub fn with_backoff_multiplier(mut self, multiplier: f64) -> Self {
+        self.backoff_multiplier = multiplier;
+        self
+    }
+
+    /// Set the minimum wait after an overloaded (HTTP 529) response
+    pub fn with_overloaded_backoff(mut self, backoff: Duration) -> Self {
+        self.overloaded_backoff = backoff;
+        self
+    }
+
+    /// Set the HTTP statuses to retry, replacing the default 429/5xx check
+    pub fn with_retryable_statuses(mut self, statuses: HashSet<u16>) -> Self {
+        self.retryable_statuses = Some(statuses);
+        self
+    }
+}
+
+/// Asynchronous sleep used between retry attempts
+///
+/// The client uses [`TokioSleeper`] by default; a custom implementation can be
+/// supplied through [`ClientBuilder::sleeper`] to observe or skip retry delays.
+pub trait Sleeper: Send + Sync + std::fmt::Debug {
+    /// Wait for the given duration
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+/// Default sleeper backed by `tokio::time::sleep`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioSleeper;
+
+impl Sleeper for TokioSleeper {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Header carrying the per-request idempotency key
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Source of identifiers generated by the client, such as idempotency keys
+///
+/// Every request is sent with an `idempotency-key` header taken from the generator,
+/// reused across retries of that request. The client uses [`UuidGenerator`] by
+/// default; [`SequentialIdGenerator`] can be supplied through
+/// [`ClientBuilder::id_generator`] to make ids predictable in tests.
+pub trait IdGenerator: Send + Sync + std::fmt::Debug {
+    /// Produce a new identifier
+    fn generate(&self) -> String;
+}
+
+/// Default id generator producing random UUID v4 strings
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UuidGenerator;
+
+impl IdGenerator for UuidGenerator {
+    fn generate(&self) -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+}
+
+/// Deterministic id generator producing `<prefix>-1`, `<prefix>-2`, and so on
+#[derive(Debug, Default)]
+pub struct SequentialIdGenerator {
+    prefix: String,
+    next: AtomicU64,
+}
+
+impl SequentialIdGenerator {
+    /// Create a generator whose ids start with `prefix`
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            next: AtomicU64::new(1),
         }
     }
 }
 
+impl IdGenerator for SequentialIdGenerator {
+    fn generate(&self) -> String {
+        let id = self.next.fetch_add(1, Ordering::Relaxed);
+        format!("{}-{}", self.prefix, id)
+    }
+}
+
 /// Request/response interceptor trait for custom middleware
 pub trait RequestInterceptor: Send + Sync + std::fmt::Debug {
     /// Called before sending a request
@@ -327,15 +551,76 @@ impl RequestMiddleware {
     }
 }
 
+/// Body of an outgoing HTTP request
+#[derive(Debug, Clone)]
+pub(crate) enum RequestBody {
+    Json(Value),
+    /// Single-file multipart upload; the form is rebuilt for every attempt
+    File {
+        bytes: Vec<u8>,
+        filename: String,
+        mime_type: String,
+    },
+}
+
 #[derive(Debug)]
 pub(crate) struct ClientInner {
     pub(crate) http_client: reqwest::Client,
     pub(crate) config: Config,
     pub(crate) retry_config: RetryConfig,
     pub(crate) middleware: RequestMiddleware,
+    pub(crate) token_count_cache: Option<TokenCountCache>,
+    pub(crate) response_cache: Option<Arc<dyn ResponseCache>>,
+    pub(crate) in_flight: InFlight,
+    pub(crate) sleeper: Arc<dyn Sleeper>,
+    pub(crate) id_generator: Arc<dyn IdGenerator>,
+    pub(crate) stream_transport: StreamTransport,
 }
 
 impl ClientInner {
+    /// Decide whether `error`, received with HTTP `status` if any, should be retried
+    ///
+    /// When [`RetryConfig::retryable_statuses`] is set it decides for HTTP errors;
+    /// other failures fall back to [`Error::is_retryable`].
+    fn should_retry(&self, error: &Error, status: Option<StatusCode>) -> bool {
+        let status = status.or(match error {
+            Error::Api { status, .. } => Some(*status),
+            _ => None,
+        });
+
+        match (&self.retry_config.retryable_statuses, status) {
+            (Some(statuses), Some(status)) if !status.is_success() => {
+                statuses.contains(&status.as_u16())
+            }
+            _ => error.is_retryable(),
+        }
+    }
+
+    /// Pick how long to wait before retrying after `error`
+    ///
+    /// Overloaded responses wait at least `overloaded_backoff` and rate-limited ones at
+    /// least the server's `retry_after`; everything else uses the current exponential
+    /// backoff delay.
+    fn retry_wait(&self, error: &Error, delay: Duration) -> Duration {
+        if error.is_overloaded() {
+            std::cmp::max(delay, self.retry_config.overloaded_backoff)
+        } else if let Some(retry_after) = error.retry_after() {
+            std::cmp::max(delay, retry_after)
+        } else {
+            delay
+        }
+    }
+
+    /// Exponential backoff: the delay to use after waiting `delay`
+    fn next_delay(&self, delay: Duration) -> Duration {
+        std::cmp::min(
+            Duration::from_millis(
+                (delay.as_millis() as f64 * self.retry_config.backoff_multiplier) as u64,
+            ),
+            self.retry_config.max_delay,
+        )
+    }
+
     /// Execute an HTTP request with retry logic and error handling
     pub async fn execute_request<T: DeserializeOwned>(
         &self,
@@ -355,23 +640,107 @@ impl ClientInner {
         body: Option<Value>,
         timeout_override: Option<Duration>,
     ) -> Result<T> {
+        self.execute_request_with_body(
+            method,
+            path,
+            body.map(RequestBody::Json),
+            timeout_override,
+            None,
+        )
+        .await
+    }
+
+    /// Execute an HTTP request with any body type, retry logic, and error handling
+    ///
+    /// `headers` are merged over the client's default headers for this request.
+    pub(crate) async fn execute_request_with_body<T: DeserializeOwned>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<RequestBody>,
+        timeout_override: Option<Duration>,
+        headers: Option<&HeaderMap>,
+    ) -> Result<T> {
+        self.execute_with_retry(method, path, body, timeout_override, headers, |response| {
+            self.handle_response::<T>(response)
+        })
+        .await
+    }
+
+    /// Execute a request like [`execute_request_with_body`](Self::execute_request_with_body),
+    /// also returning metadata about the successful response
+    pub(crate) async fn execute_request_with_meta<T: DeserializeOwned>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<RequestBody>,
+        timeout_override: Option<Duration>,
+        headers: Option<&HeaderMap>,
+    ) -> Result<(T, ResponseMeta)> {
+        self.execute_with_retry(method, path, body, timeout_override, headers, |response| {
+            let meta = ResponseMeta::from_response(&response);
+            async move { Ok((self.handle_response::<T>(response).await?, meta)) }
+        })
+        .await
+    }
+
+    /// Execute a request and return the raw response body as text
+    ///
+    /// Used for non-JSON payloads such as JSON Lines; retries like JSON requests.
+    pub(crate) async fn execute_text_request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+    ) -> Result<String> {
+        self.execute_with_retry(method, path, None, None, None, |response| {
+            self.handle_text_response(response)
+        })
+        .await
+    }
+
+    /// Send a request, decoding the response with `handle` and retrying on failure
+    async fn execute_with_retry<T, F, Fut>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<RequestBody>,
+        timeout_override: Option<Duration>,
+        headers: Option<&HeaderMap>,
+        handle: F,
+    ) -> Result<T>
+    where
+        F: Fn(Response) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let _in_flight = self.in_flight.start();
         let url = self
             .config
             .base_url
             .join(path)
             .map_err(|e| Error::Config(format!("Invalid URL path '{}': {}", path, e)))?;
 
+        // One key per logical request, reused by every retry of it
+        let idempotency_key = self.id_generator.generate();
         let mut attempt = 0;
         let mut delay = self.retry_config.initial_delay;
 
         loop {
+            let wait;
             let request_result = self
-                .build_request(method.clone(), &url, body.clone(), timeout_override)
+                .build_request(
+                    method.clone(),
+                    &url,
+                    body.clone(),
+                    timeout_override,
+                    headers,
+                    &idempotency_key,
+                )
                 .await;
 
             match request_result {
                 Ok(response) => {
-                    match self.handle_response::<T>(response).await {
+                    let status = response.status();
+                    match handle(response).await {
                         Ok(result) => return Ok(result),
                         Err(error) => {
                             // Call error interceptors
@@ -379,15 +748,18 @@ impl ClientInner {
                                 interceptor.on_error(&error);
                             }
 
-                            if attempt >= self.retry_config.max_retries || !error.is_retryable() {
+                            if attempt >= self.retry_config.max_retries
+                                || !self.should_retry(&error, Some(status))
+                            {
                                 return Err(error);
                             }
+                            wait = self.retry_wait(&error, delay);
 
                             if self.middleware.log_requests {
                                 eprintln!(
                                     "Request failed (attempt {}), retrying in {:?}: {}",
                                     attempt + 1,
-                                    delay,
+                                    wait,
                                     error
                                 );
                             }
@@ -400,15 +772,17 @@ impl ClientInner {
                         interceptor.on_error(&error);
                     }
 
-                    if attempt >= self.retry_config.max_retries || !error.is_retryable() {
+                    if attempt >= self.retry_config.max_retries || !self.should_retry(&error, None)
+                    {
                         return Err(error);
                     }
+                    wait = self.retry_wait(&error, delay);
 
                     if self.middleware.log_requests {
                         eprintln!(
                             "Request failed (attempt {}), retrying in {:?}: {}",
                             attempt + 1,
-                            delay,
+                            wait,
                             error
                         );
                     }
@@ -416,16 +790,9 @@ impl ClientInner {
             }
 
             // Wait before retrying
-            tokio::time::sleep(delay).await;
-
-            // Exponential backoff
-            delay = std::cmp::min(
-                Duration::from_millis(
-                    (delay.as_millis() as f64 * self.retry_config.backoff_multiplier) as u64,
-                ),
-                self.retry_config.max_delay,
-            );
+            self.sleeper.sleep(wait).await;
 
+            delay = self.next_delay(delay);
             attempt += 1;
         }
     }
@@ -435,25 +802,55 @@ impl ClientInner {
         &self,
         method: reqwest::Method,
         url: &reqwest::Url,
-        body: Option<Value>,
+        body: Option<RequestBody>,
         timeout_override: Option<Duration>,
+        headers: Option<&HeaderMap>,
+        idempotency_key: &str,
     ) -> Result<Response> {
-        let mut request_builder = self.http_client.request(method.clone(), url.clone());
+        let mut request_builder = self
+            .http_client
+            .request(method.clone(), url.clone())
+            .header(IDEMPOTENCY_KEY_HEADER, idempotency_key);
 
         // Apply timeout override if provided
         if let Some(timeout) = timeout_override {
             request_builder = request_builder.timeout(timeout);
         }
 
+        // Send enabled beta features
+        if let Some(betas) = self.config.beta_header() {
+            request_builder = request_builder.header("anthropic-beta", betas);
+        }
+
+        // Per-request headers take precedence over client defaults
+        if let Some(headers) = headers {
+            request_builder = request_builder.headers(headers.clone());
+        }
+
         // Add body if provided
-        if let Some(body) = &body {
-            request_builder = request_builder.json(body);
+        match &body {
+            Some(RequestBody::Json(json)) => {
+                request_builder = request_builder.json(json);
+            }
+            Some(RequestBody::File {
+                bytes,
+                filename,
+                mime_type,
+            }) => {
+                let part = reqwest::multipart::Part::bytes(bytes.clone())
+                    .file_name(filename.clone())
+                    .mime_str(mime_type)
+                    .map_err(|e| {
+                        Error::InvalidRequest(format!("Invalid MIME type '{}': {}", mime_type, e))
+                    })?;
+                request_builder =
+                    request_builder.multipart(reqwest::multipart::Form::new().part("file", part));
+            }
+            None => {}
         }
 
-        // Build the request for interceptors
+        // Build the request once so interceptors see exactly what is sent
         let request = request_builder
-            .try_clone()
-            .ok_or_else(|| Error::Config("Failed to clone request for interceptors".to_string()))?
             .build()
             .map_err(|e| Error::Config(format!("Failed to build request: {}", e)))?;
 
@@ -471,19 +868,27 @@ impl ClientInner {
             }
 
             if self.middleware.log_body {
-                if let Some(body) = &body {
-                    eprintln!(
+                match &body {
+                    Some(RequestBody::Json(json)) => eprintln!(
                         "Request Body: {}",
-                        serde_json::to_string_pretty(body)
+                        serde_json::to_string_pretty(json)
                             .unwrap_or_else(|_| "Invalid JSON".to_string())
-                    );
+                    ),
+                    Some(RequestBody::File {
+                        bytes, filename, ..
+                    }) => eprintln!(
+                        "Request Body: multipart file '{}' ({} bytes)",
+                        filename,
+                        bytes.len()
+                    ),
+                    None => {}
                 }
             }
         }
 
         // Execute the request
         let timeout_duration = timeout_override.unwrap_or(self.config.timeout);
-        let response = request_builder.send().await.map_err(|e| {
+        let response = self.http_client.execute(request).await.map_err(|e| {
             if e.is_timeout() {
                 Error::timeout(timeout_duration, None)
             } else if e.is_connect() {
@@ -514,17 +919,30 @@ impl ClientInner {
     async fn handle_response<T: DeserializeOwned>(&self, response: Response) -> Result<T> {
         let status = response.status();
         let headers = response.headers().clone();
-        let request_id = extract_request_id(&headers);
 
         // Handle successful responses
         if status.is_success() {
-            let response_text = response.text().await.map_err(Error::Http)?;
+            // Reject binary bodies up front instead of decoding them lossily into a
+            // confusing JSON parse error
+            let body = response.bytes().await.map_err(Error::Http)?;
+            let response_text = decode_utf8_body(&body)?;
 
             if self.middleware.log_responses && self.middleware.log_body {
                 eprintln!("Response Body: {}", response_text);
             }
 
-            serde_json::from_str(&response_text).map_err(|e| {
+            let value: Value = serde_json::from_str(response_text).map_err(|e| {
+                Error::InvalidResponse(format!("Failed to parse JSON response: {}", e))
+            })?;
+
+            // Some proxies return error objects with a success status; classify them by
+            // the status the API would have sent
+            if value.get("type").and_then(|t| t.as_str()) == Some("error") {
+                let status = error_type_status(value["error"]["type"].as_str());
+                return self.handle_error_response(status, response_text, Some(&headers));
+            }
+
+            serde_json::from_value(value).map_err(|e| {
                 Error::InvalidResponse(format!("Failed to parse JSON response: {}", e))
             })
         } else {
@@ -535,10 +953,31 @@ impl ClientInner {
                 eprintln!("Error Response Body: {}", response_text);
             }
 
-            self.handle_error_response(status, &response_text, request_id)
+            self.handle_error_response(status, &response_text, Some(&headers))
         }
     }
 
+    /// Handle a response whose successful body is returned as text
+    async fn handle_text_response(&self, response: Response) -> Result<String> {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await.map_err(Error::Http)?;
+
+        if !status.is_success() {
+            let response_text = String::from_utf8_lossy(&body);
+            if self.middleware.log_responses && self.middleware.log_body {
+                eprintln!("Error Response Body: {}", response_text);
+            }
+            return self.handle_error_response(status, &response_text, Some(&headers));
+        }
+
+        let response_text = decode_utf8_body(&body)?;
+        if self.middleware.log_responses && self.middleware.log_body {
+            eprintln!("Response Body: {}", response_text);
+        }
+        Ok(response_text.to_string())
+    }
+
     /// Execute a streaming HTTP request and return a MessageStream
     #[allow(dead_code)]
     pub async fn execute_streaming_request(
@@ -557,32 +996,38 @@ impl ClientInner {
         body: Option<Value>,
         timeout_override: Option<Duration>,
     ) -> Result<MessageStream> {
+        let in_flight = self.in_flight.start();
         let url = self
             .config
             .base_url
             .join(path)
             .map_err(|e| Error::Config(format!("Invalid URL path '{}': {}", path, e)))?;
 
+        let idempotency_key = self.id_generator.generate();
         let mut attempt = 0;
         let mut delay = self.retry_config.initial_delay;
 
         loop {
+            let wait;
             let request_result = self
-                .build_streaming_request(&url, body.clone(), timeout_override)
+                .build_streaming_request(&url, body.clone(), timeout_override, &idempotency_key)
                 .await;
 
             match request_result {
-                Ok(stream) => return Ok(stream),
+                // The stream counts as in flight until it is dropped
+                Ok(stream) => return Ok(stream.holding(in_flight)),
                 Err(error) => {
-                    if attempt >= self.retry_config.max_retries || !error.is_retryable() {
+                    if attempt >= self.retry_config.max_retries || !self.should_retry(&error, None)
+                    {
                         return Err(error);
                     }
+                    wait = self.retry_wait(&error, delay);
 
                     if self.middleware.log_requests {
                         eprintln!(
                             "Streaming request failed (attempt {}), retrying in {:?}: {}",
                             attempt + 1,
-                            delay,
+                            wait,
                             error
                         );
                     }
@@ -590,16 +1035,9 @@ impl ClientInner {
             }
 
             // Wait before retrying
-            tokio::time::sleep(delay).await;
-
-            // Exponential backoff
-            delay = std::cmp::min(
-                Duration::from_millis(
-                    (delay.as_millis() as f64 * self.retry_config.backoff_multiplier) as u64,
-                ),
-                self.retry_config.max_delay,
-            );
+            self.sleeper.sleep(wait).await;
 
+            delay = self.next_delay(delay);
             attempt += 1;
         }
     }
@@ -610,14 +1048,23 @@ impl ClientInner {
         url: &reqwest::Url,
         body: Option<Value>,
         timeout_override: Option<Duration>,
+        idempotency_key: &str,
     ) -> Result<MessageStream> {
-        let mut request_builder = self.http_client.post(url.clone());
+        let mut request_builder = self
+            .http_client
+            .post(url.clone())
+            .header(IDEMPOTENCY_KEY_HEADER, idempotency_key);
 
         // Apply timeout override if provided
         if let Some(timeout) = timeout_override {
             request_builder = request_builder.timeout(timeout);
         }
 
+        // Send enabled beta features
+        if let Some(betas) = self.config.beta_header() {
+            request_builder = request_builder.header("anthropic-beta", betas);
+        }
+
         // Add body if provided
         if let Some(body) = &body {
             request_builder = request_builder.json(body);
@@ -674,7 +1121,7 @@ impl ClientInner {
                 eprintln!("Error Response Body: {}", response_text);
             }
 
-            return self.handle_error_response(status, &response_text, request_id);
+            return self.handle_error_response(status, &response_text, Some(&headers));
         }
 
         // Call after_response interceptors
@@ -695,46 +1142,55 @@ impl ClientInner {
             }
         }
 
-        // For now, return a simple stream that produces a mock event
-        // This will be improved in a future iteration
-        use crate::streaming::{PartialMessage, StreamEvent};
-        use futures::stream;
-
-        let mock_events = vec![
-            Ok(StreamEvent::MessageStart {
-                message: PartialMessage {
-                    id: "mock_msg".to_string(),
-                    role: crate::types::Role::Assistant,
-                    content: vec![],
-                    model: crate::types::Model::Claude35Sonnet20241022,
-                    stop_reason: None,
-                    stop_sequence: None,
-                    usage: crate::types::Usage {
-                        input_tokens: 10,
-                        output_tokens: 0,
-                        cache_creation_input_tokens: None,
-                        cache_read_input_tokens: None,
-                    },
-                },
-            }),
-            Ok(StreamEvent::MessageStop),
-        ];
+        let stream = match self.stream_transport {
+            StreamTransport::Sse => sse_event_stream(response.bytes_stream()),
+            StreamTransport::PollingChunks => chunked_event_stream(response.bytes_stream()),
+        };
+        Ok(stream.with_request_id(request_id))
+    }
 
-        let event_stream = stream::iter(mock_events);
-        let boxed_stream: Pin<
-            Box<dyn Stream<Item = std::result::Result<StreamEvent, Error>> + Send>,
-        > = Box::pin(event_stream);
+    /// Decode one line of batch results into its custom ID and outcome
+    fn parse_batch_result(&self, line: &str) -> Result<(String, Result<Message>)> {
+        let invalid =
+            |reason: &str| Error::InvalidResponse(format!("Invalid batch result line: {}", reason));
+
+        let mut entry: Value = serde_json::from_str(line).map_err(|e| invalid(&e.to_string()))?;
+        let custom_id = entry["custom_id"]
+            .as_str()
+            .ok_or_else(|| invalid("missing custom_id"))?
+            .to_string();
+        let result = entry["result"].take();
+
+        let outcome = match result["type"].as_str() {
+            Some("succeeded") => Ok(serde_json::from_value(result["message"].clone())
+                .map_err(|e| invalid(&e.to_string()))?),
+            Some("errored") => {
+                // The payload is the error body the request would have received over HTTP
+                let error_body = &result["error"];
+                let status = error_type_status(error_body["error"]["type"].as_str());
+                self.handle_error_response(status, &error_body.to_string(), None)
+            }
+            Some(kind @ ("canceled" | "expired")) => Err(Error::InvalidRequest(format!(
+                "Batch request '{}' was {} before it was processed",
+                custom_id, kind
+            ))),
+            _ => return Err(invalid("unknown result type")),
+        };
 
-        Ok(MessageStream::new(boxed_stream))
+        Ok((custom_id, outcome))
     }
 
     /// Handle error responses from the API
+    ///
+    /// `headers` are the response headers, when the body came from an HTTP response.
     fn handle_error_response<T>(
         &self,
         status: StatusCode,
         body: &str,
-        request_id: Option<String>,
+        headers: Option<&HeaderMap>,
     ) -> Result<T> {
+        let request_id = headers.and_then(extract_request_id);
+
         // Try to parse error response as JSON
         let error_info = serde_json::from_str::<Value>(body).ok();
 
@@ -767,10 +1223,17 @@ impl ClientInner {
                 message
             ))),
             StatusCode::TOO_MANY_REQUESTS => {
-                let retry_after = extract_retry_after_duration(body);
-                Err(Error::rate_limit(retry_after, request_id))
+                // The standard header takes precedence over the JSON body
+                let retry_after = headers
+                    .and_then(extract_retry_after_header)
+                    .or_else(|| extract_retry_after_duration(body));
+                Err(Error::RateLimit {
+                    retry_after,
+                    request_id,
+                    info: headers.and_then(RateLimitInfo::from_headers),
+                })
             }
-            StatusCode::BAD_REQUEST => Err(Error::InvalidRequest(message)),
+            StatusCode::BAD_REQUEST => Err(Error::bad_request(message, error_type.as_deref())),
             StatusCode::NOT_FOUND => Err(Error::InvalidRequest(format!(
                 "Resource not found: {}",
                 message
@@ -987,22 +1450,192 @@ impl Client {
         request: ChatRequest,
         timeout: Option<Duration>,
     ) -> Result<Message> {
+        let options = ChatOptions {
+            model: Some(model),
+            timeout,
+            headers: None,
+        };
+        self.execute_chat_with(options, request).await
+    }
+
+    /// Execute a chat request with a bundle of per-call options.
+    ///
+    /// Headers in [`ChatOptions::headers`] are merged over the client's default headers
+    /// for this request only, replacing defaults with the same name.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use anthropic_rust::{ChatOptions, Client, ContentBlock, Model};
+    /// use reqwest::header::{HeaderMap, HeaderValue};
+    ///
+    /// # async fn example() -> anthropic_rust::Result<()> {
+    /// let client = Client::new(Model::Claude35Sonnet20241022)?;
+    /// let request = client.chat_builder()
+    ///     .user_message(ContentBlock::text("Hello!"))
+    ///     .build();
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert("x-tenant-id", HeaderValue::from_static("tenant-42"));
+    /// let options = ChatOptions {
+    ///     headers: Some(headers),
+    ///     ..Default::default()
+    /// };
+    /// let response = client.execute_chat_with(options, request).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn execute_chat_with(
+        &self,
+        options: ChatOptions,
+        request: ChatRequest,
+    ) -> Result<Message> {
+        self.execute_chat_with_meta(options, request, true)
+            .await
+            .map(|(message, _)| message)
+    }
+
+    /// Execute a chat request, also returning the response's request ID, rate-limit
+    /// headers, and status.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use anthropic_rust::{Client, ContentBlock, Model};
+    ///
+    /// # async fn example() -> anthropic_rust::Result<()> {
+    /// let client = Client::new(Model::Claude35Sonnet20241022)?;
+    /// let request = client.chat_builder()
+    ///     .user_message(ContentBlock::text("Hello!"))
+    ///     .build();
+    ///
+    /// let (message, meta) = client.execute_chat_with_response(request).await?;
+    /// println!("{} (request-id: {:?})", message.id, meta.request_id);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn execute_chat_with_response(
+        &self,
+        request: ChatRequest,
+    ) -> Result<(Message, ResponseMeta)> {
+        self.execute_chat_with_meta(ChatOptions::default(), request, true)
+            .await
+    }
+
+    /// Execute a chat request, consulting the response cache only when `use_cache` is set
+    async fn execute_chat_with_meta(
+        &self,
+        options: ChatOptions,
+        mut request: ChatRequest,
+        use_cache: bool,
+    ) -> Result<(Message, ResponseMeta)> {
+        let model = options
+            .model
+            .unwrap_or_else(|| self.inner.config.model.clone());
+        let max_tokens = self.inner.config.max_tokens_for(&model, false);
+        request.validate(&model, max_tokens)?;
+        self.apply_default_system(&mut request.system);
+
         // Create the request body with model and max_tokens
         let mut body = serde_json::to_value(&request)?;
 
         // Add model and max_tokens to the request
         body["model"] = serde_json::to_value(&model)?;
-        body["max_tokens"] = serde_json::to_value(self.inner.config.max_tokens)?;
+        body["max_tokens"] = serde_json::to_value(max_tokens)?;
 
-        // Execute the request with optional timeout override
-        self.inner
-            .execute_request_with_timeout(
-                reqwest::Method::POST,
-                "/v1/messages",
-                Some(body),
-                timeout,
-            )
-            .await
+        if self.inner.config.validate_requests {
+            validate_messages_request(&body)?;
+        }
+
+        // Deterministic requests may be answered from the response cache
+        let cache_key = match &self.inner.response_cache {
+            Some(cache) if use_cache && request.is_deterministic() => {
+                let key = response_cache_key(&body);
+                if let Some(message) = cache.get(&key).await {
+                    return Ok((message, ResponseMeta::default()));
+                }
+                Some(key)
+            }
+            _ => None,
+        };
+
+        if self.inner.config.precheck_tokens {
+            let token_count = self
+                .count_tokens_as_sent(&model, CountTokensRequest::from(request.clone()))
+                .await?;
+            let context_window = model.context_window();
+            if token_count.input_tokens.saturating_add(max_tokens) > context_window {
+                return Err(Error::InvalidRequest(format!(
+                    "Request needs {} input tokens plus {} max_tokens, exceeding the {}-token context window",
+                    token_count.input_tokens, max_tokens, context_window
+                )));
+            }
+        }
+
+        let mut fallbacks = self.inner.config.model_fallback.iter();
+        let mut fallback_model = None;
+        let (message, mut meta): (Message, ResponseMeta) = loop {
+            // Execute the request with optional timeout and header overrides
+            let result = self
+                .inner
+                .execute_request_with_meta(
+                    reqwest::Method::POST,
+                    "/v1/messages",
+                    Some(RequestBody::Json(body.clone())),
+                    options.timeout,
+                    options.headers.as_ref(),
+                )
+                .await;
+
+            // Downgrade to the next fallback model once retries are exhausted
+            match result {
+                Err(error) if error.is_overloaded() || error.is_rate_limit_error() => {
+                    // Skip fallbacks the request is invalid for, e.g. a thinking budget
+                    // above their output limit
+                    let Some((next, next_max_tokens)) = fallbacks.by_ref().find_map(|next| {
+                        let max_tokens = self.inner.config.max_tokens_for(next, false);
+                        request
+                            .validate(next, max_tokens)
+                            .is_ok()
+                            .then_some((next, max_tokens))
+                    }) else {
+                        return Err(error);
+                    };
+                    if self.inner.middleware.log_requests {
+                        eprintln!(
+                            "Model {} unavailable, falling back to {}: {}",
+                            body["model"],
+                            serde_json::to_value(next)?,
+                            error
+                        );
+                    }
+                    body["model"] = serde_json::to_value(next)?;
+                    body["max_tokens"] = serde_json::to_value(next_max_tokens)?;
+                    fallback_model = Some(next.clone());
+                }
+                result => break result?,
+            }
+        };
+
+        if self.inner.config.error_on_empty_response
+            && message.content.is_empty()
+            && message.stop_reason == Some(StopReason::EndTurn)
+        {
+            return Err(Error::InvalidResponse(format!(
+                "Message {} ended its turn without any content",
+                message.id
+            )));
+        }
+
+        // A downgraded response must not answer later requests for the original model
+        if let (Some(cache), Some(key), None) =
+            (&self.inner.response_cache, cache_key, &fallback_model)
+        {
+            cache.put(&key, message.clone()).await;
+        }
+
+        meta.fallback_model = fallback_model;
+        Ok((message, meta))
     }
 
     /// Execute a chat request with timeout override using the client's default model.
@@ -1044,6 +1677,111 @@ impl Client {
             .await
     }
 
+    /// Generate several candidate completions for the same request.
+    ///
+    /// The API returns a single message per request, so this issues
+    /// [`ChatRequest::candidate_count`] requests in parallel (one when unset) and
+    /// collects the results in order. When the request sets a `seed`, each candidate is
+    /// sent with that seed plus its index; otherwise the request is sent unchanged.
+    /// Candidates bypass the response cache. Fails if any request fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use anthropic_rust::{Client, Model, ContentBlock};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Client::new(Model::Claude35Sonnet20241022)?;
+    ///
+    ///     let request = client.chat_builder()
+    ///         .user_message(ContentBlock::text("Suggest a name for a cat"))
+    ///         .temperature(1.0)
+    ///         .candidate_count(3)
+    ///         .build();
+    ///
+    ///     for candidate in client.execute_chat_candidates(request).await? {
+    ///         println!("{:?}", candidate.content);
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn execute_chat_candidates(&self, request: ChatRequest) -> Result<Vec<Message>> {
+        let count = request.candidate_count.unwrap_or(1);
+        if count == 0 {
+            return Err(Error::InvalidRequest(
+                "candidate_count must be at least 1".to_string(),
+            ));
+        }
+
+        let requests = (0..count).map(|index| {
+            let mut candidate = request.clone();
+            candidate.seed = request.seed.map(|seed| seed.wrapping_add(u64::from(index)));
+            async move {
+                self.execute_chat_with_meta(ChatOptions::default(), candidate, false)
+                    .await
+                    .map(|(message, _)| message)
+            }
+        });
+        futures::future::try_join_all(requests).await
+    }
+
+    /// Continue a turn that stopped with [`StopReason::PauseTurn`].
+    ///
+    /// Server-side tools can pause long-running turns. This sends `request` with the paused
+    /// assistant content as its final turn, repeating while the API keeps pausing, up to
+    /// [`MAX_RESUME_ITERATIONS`] times; each pause extends that single assistant turn. The
+    /// returned message carries the id, stop reason, and usage of the last response, with
+    /// the content of the whole turn. If `previous` did not pause it is returned unchanged;
+    /// if the turn is still paused after the limit, the stop reason stays `PauseTurn`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use anthropic_rust::{Client, Model, ContentBlock};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Client::new(Model::Claude35Sonnet20241022)?;
+    ///
+    ///     let request = client.chat_builder()
+    ///         .user_message(ContentBlock::text("Research this topic"))
+    ///         .build();
+    ///
+    ///     let response = client.execute_chat(request.clone()).await?;
+    ///     let response = client.resume(response, request).await?;
+    ///     println!("Final stop reason: {:?}", response.stop_reason);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn resume(&self, previous: Message, mut request: ChatRequest) -> Result<Message> {
+        let mut message = previous;
+        let mut turn = Vec::new();
+
+        for iteration in 0..MAX_RESUME_ITERATIONS {
+            if message.stop_reason != Some(StopReason::PauseTurn) {
+                break;
+            }
+
+            // Extend the trailing assistant turn instead of adding a consecutive one
+            turn.append(&mut message.content);
+            if iteration > 0 {
+                request.messages.pop();
+            }
+            request.messages.push(MessageParam {
+                role: message.role.clone(),
+                content: turn.clone(),
+            });
+            message = self.execute_chat(request.clone()).await?;
+        }
+
+        turn.append(&mut message.content);
+        message.content = turn;
+        Ok(message)
+    }
+
     /// Stream a chat request using the client's configured model and max_tokens.
     ///
     /// This method enables real-time streaming of Claude's response, allowing you to
@@ -1181,18 +1919,130 @@ impl Client {
         request: ChatRequest,
         timeout: Option<Duration>,
     ) -> Result<MessageStream> {
+        let options = StreamOptions {
+            model: Some(model),
+            timeout,
+            idle_timeout: None,
+            include_usage: self.inner.config.stream_include_usage,
+        };
+        self.stream_chat_with(options, request).await
+    }
+
+    /// Stream a chat request with a bundle of per-call options.
+    ///
+    /// Consolidates the model, timeout, idle timeout, and usage overrides of the
+    /// `stream_chat_with_*` variants. When `idle_timeout` is set, the stream ends with
+    /// [`Error::Timeout`] if no event arrives within that duration.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use anthropic_rust::{Client, ContentBlock, Model, StreamOptions};
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> anthropic_rust::Result<()> {
+    /// let client = Client::new(Model::Claude35Sonnet20241022)?;
+    /// let request = client.chat_builder()
+    ///     .user_message(ContentBlock::text("Tell me a story"))
+    ///     .build();
+    ///
+    /// let options = StreamOptions {
+    ///     model: Some(Model::Claude3Haiku20240307),
+    ///     idle_timeout: Some(Duration::from_secs(10)),
+    ///     ..Default::default()
+    /// };
+    /// let stream = client.stream_chat_with(options, request).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn stream_chat_with(
+        &self,
+        options: StreamOptions,
+        mut request: ChatRequest,
+    ) -> Result<MessageStream> {
+        let model = options
+            .model
+            .unwrap_or_else(|| self.inner.config.model.clone());
+        let max_tokens = self.inner.config.max_tokens_for(&model, true);
+        request.validate(&model, max_tokens)?;
+        self.apply_default_system(&mut request.system);
+
         // Create the request body with model, max_tokens, and stream=true
         let mut body = serde_json::to_value(&request)?;
 
         // Add model and max_tokens to the request
         body["model"] = serde_json::to_value(&model)?;
-        body["max_tokens"] = serde_json::to_value(self.inner.config.max_tokens)?;
+        body["max_tokens"] = serde_json::to_value(max_tokens)?;
         body["stream"] = serde_json::Value::Bool(true);
 
+        if self.inner.config.validate_requests {
+            validate_messages_request(&body)?;
+        }
+
         // Execute the streaming request with optional timeout override
-        self.inner
-            .execute_streaming_request_with_timeout("/v1/messages", Some(body), timeout)
-            .await
+        let stream = self
+            .inner
+            .execute_streaming_request_with_timeout(
+                "/v1/messages",
+                Some(body.clone()),
+                options.timeout,
+            )
+            .await?;
+
+        let stream = if self.inner.config.retry_streams {
+            self.restart_on_retryable_error(stream, body, options.timeout)
+        } else {
+            stream
+        };
+
+        let stream = if options.include_usage {
+            stream
+        } else {
+            stream.without_usage()
+        };
+        Ok(match options.idle_timeout {
+            Some(idle_timeout) => stream.with_idle_timeout(idle_timeout),
+            None => stream,
+        })
+    }
+
+    /// Re-issue `body` when `stream` fails mid-way with a retryable error, following
+    /// the client's retry configuration
+    fn restart_on_retryable_error(
+        &self,
+        stream: MessageStream,
+        body: Value,
+        timeout: Option<Duration>,
+    ) -> MessageStream {
+        let inner = self.inner.clone();
+        let mut attempt = 0;
+        let mut delay = inner.retry_config.initial_delay;
+
+        stream.with_restarts(move |error| {
+            if attempt >= inner.retry_config.max_retries || !inner.should_retry(error, None) {
+                return None;
+            }
+            let wait = inner.retry_wait(error, delay);
+            if inner.middleware.log_requests {
+                eprintln!(
+                    "Stream failed (attempt {}), restarting in {:?}: {}",
+                    attempt + 1,
+                    wait,
+                    error
+                );
+            }
+            delay = inner.next_delay(delay);
+            attempt += 1;
+
+            let inner = inner.clone();
+            let body = body.clone();
+            Some(async move {
+                inner.sleeper.sleep(wait).await;
+                inner
+                    .execute_streaming_request_with_timeout("/v1/messages", Some(body), timeout)
+                    .await
+            })
+        })
     }
 
     /// Stream a chat request with timeout override using the client's default model.
@@ -1237,6 +2087,37 @@ impl Client {
             .await
     }
 
+    /// Stream a chat request, yielding only the cumulative token usage.
+    ///
+    /// A [`Usage`] is yielded after each `message_delta` event and all content is
+    /// discarded, which keeps cost dashboards and monitors lightweight. Errors from
+    /// starting the request or from the stream are yielded as items.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use anthropic_rust::{Client, ContentBlock, Model};
+    /// use futures::StreamExt;
+    ///
+    /// # async fn example() -> anthropic_rust::Result<()> {
+    /// let client = Client::new(Model::Claude35Sonnet20241022)?;
+    /// let request = client.chat_builder()
+    ///     .user_message(ContentBlock::text("Write a haiku"))
+    ///     .build();
+    ///
+    /// let mut usage = Box::pin(client.stream_usage(request));
+    /// while let Some(usage) = usage.next().await {
+    ///     println!("Output tokens so far: {}", usage?.output_tokens);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream_usage(&self, request: ChatRequest) -> impl Stream<Item = Result<Usage>> + Send {
+        let client = self.clone();
+        futures::stream::once(async move { client.stream_chat(request).await.map(usage_stream) })
+            .try_flatten()
+    }
+
     /// Count tokens in a request without sending it to Claude.
     ///
     /// This method allows you to estimate token usage before making an actual request,
@@ -1248,7 +2129,12 @@ impl Client {
     ///
     /// # Returns
     ///
-    /// Returns a `TokenCount` with the estimated input token count.
+    /// Returns a `TokenCount` with the estimated input token count. When the client was
+    /// built with [`ClientBuilder::token_count_cache`], identical requests are served
+    /// from an in-memory cache instead of calling the API again. When beta features are
+    /// enabled, the request goes to the beta endpoint and carries the `anthropic-beta` header.
+    /// A [`ClientBuilder::default_system`] prompt is included just as in
+    /// [`execute_chat`](Self::execute_chat), so the count matches what a chat request sends.
     ///
     /// # Examples
     ///
@@ -1268,6 +2154,7 @@ impl Client {
     ///         ],
     ///         system: None,
     ///         tools: None,
+    ///         extra: Default::default(),
     ///     };
     ///
     ///     let token_count = client.count_tokens(request).await?;
@@ -1276,23 +2163,260 @@ impl Client {
     ///     Ok(())
     /// }
     /// ```
-    pub async fn count_tokens(&self, request: CountTokensRequest) -> Result<TokenCount> {
+    pub async fn count_tokens(&self, mut request: CountTokensRequest) -> Result<TokenCount> {
+        // Count exactly what execute_chat would send
+        self.apply_default_system(&mut request.system);
+        self.count_tokens_as_sent(&self.inner.config.model, request)
+            .await
+    }
+
+    /// Count tokens with `model` for a request that already carries the default system
+    /// prompt
+    async fn count_tokens_as_sent(
+        &self,
+        model: &Model,
+        request: CountTokensRequest,
+    ) -> Result<TokenCount> {
+        // Serve repeated requests from the cache when enabled; counts differ per model
+        let mut hasher = DefaultHasher::new();
+        (model.as_str(), request.content_hash()).hash(&mut hasher);
+        let cache_key = hasher.finish();
+        if let Some(cache) = &self.inner.token_count_cache {
+            if let Some(token_count) = cache.get(cache_key) {
+                return Ok(token_count);
+            }
+        }
+
         // Create the request body with model
         let mut body = serde_json::to_value(&request)?;
 
         // Add model to the request
-        body["model"] = serde_json::to_value(&self.inner.config.model)?;
+        body["model"] = serde_json::to_value(model)?;
+
+        // The count_tokens endpoint never streams; drop a stray flag rather than
+        // letting the API reject the request
+        if let Some(object) = body.as_object_mut() {
+            object.remove("stream");
+        }
+
+        // Beta features are only honoured on the beta variant of the endpoint
+        let path = if self.inner.config.beta_features.is_empty() {
+            "/v1/messages/count_tokens"
+        } else {
+            "/v1/messages/count_tokens?beta=true"
+        };
 
         // Execute the request
+        let token_count: TokenCount = self
+            .inner
+            .execute_request(reqwest::Method::POST, path, Some(body))
+            .await?;
+
+        if let Some(cache) = &self.inner.token_count_cache {
+            cache.put(cache_key, token_count.clone());
+        }
+
+        Ok(token_count)
+    }
+
+    /// Place the configured default system prompt before the request's own system prompt
+    fn apply_default_system(&self, system: &mut Option<Vec<SystemMessage>>) {
+        if let Some(default_system) = &self.inner.config.default_system {
+            system.get_or_insert_with(Vec::new).insert(
+                0,
+                SystemMessage {
+                    message_type: "text".to_string(),
+                    text: default_system.clone(),
+                    cache_control: None,
+                },
+            );
+        }
+    }
+
+    /// Generate an identifier with the client's [`IdGenerator`].
+    ///
+    /// Useful for idempotency keys and correlation ids that should be predictable in
+    /// tests; see [`ClientBuilder::id_generator`].
+    pub fn generate_id(&self) -> String {
+        self.inner.id_generator.generate()
+    }
+
+    /// Wait for in-flight requests and streams to finish, for graceful shutdown.
+    ///
+    /// Streams count as in flight until they are dropped. Returns [`Error::Timeout`] if
+    /// any remain after `timeout`. Waiting uses the client's [`Sleeper`], polling every
+    /// 10ms.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use anthropic_rust::{Client, Model};
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> anthropic_rust::Result<()> {
+    /// let client = Client::new(Model::Claude35Sonnet20241022)?;
+    /// // ... serve traffic, then on shutdown:
+    /// client.drain(Duration::from_secs(30)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn drain(&self, timeout: Duration) -> Result<()> {
+        const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+        let mut waited = Duration::ZERO;
+        while self.inner.in_flight.count() > 0 {
+            if waited >= timeout {
+                return Err(Error::timeout(timeout, None));
+            }
+            self.inner.sleeper.sleep(POLL_INTERVAL).await;
+            waited += POLL_INTERVAL;
+        }
+        Ok(())
+    }
+
+    /// Compute the largest safe `max_tokens` for a request.
+    ///
+    /// Counts the request's input tokens with [`count_tokens`](Self::count_tokens) and
+    /// subtracts them from the configured model's context window, clamping the result
+    /// to the model's maximum output tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use anthropic_rust::{Client, Model, ContentBlock};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Client::new(Model::Claude35Sonnet20241022)?;
+    ///
+    ///     let request = client.chat_builder()
+    ///         .user_message(ContentBlock::text("Summarize this long document..."))
+    ///         .build();
+    ///
+    ///     let budget = client.remaining_output_budget(&request).await?;
+    ///     println!("Up to {} output tokens fit", budget);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn remaining_output_budget(&self, request: &ChatRequest) -> Result<u32> {
+        let token_count = self
+            .count_tokens(CountTokensRequest::from(request.clone()))
+            .await?;
+
+        let model = &self.inner.config.model;
+        Ok(model
+            .context_window()
+            .saturating_sub(token_count.input_tokens)
+            .min(model.max_output_tokens()))
+    }
+
+    /// Upload a file with the Files API.
+    ///
+    /// The returned id can be referenced from content blocks with
+    /// [`ContentBlock::image_file`](crate::ContentBlock::image_file) or
+    /// [`ContentBlock::document_file`](crate::ContentBlock::document_file).
+    /// The Files API is in beta; enable it with
+    /// [`ClientBuilder::beta_feature`]`("files-api-2025-04-14")`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use anthropic_rust::{Client, ContentBlock};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Client::builder()
+    ///         .beta_feature("files-api-2025-04-14")
+    ///         .build()?;
+    ///
+    ///     let bytes = std::fs::read("report.pdf")?;
+    ///     let file = client.upload_file(bytes, "report.pdf", "application/pdf").await?;
+    ///     let block = ContentBlock::document_file(file.id);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn upload_file(
+        &self,
+        bytes: Vec<u8>,
+        filename: &str,
+        mime: &str,
+    ) -> Result<FileMetadata> {
+        let body = RequestBody::File {
+            bytes,
+            filename: filename.to_string(),
+            mime_type: mime.to_string(),
+        };
+
+        self.inner
+            .execute_request_with_body(reqwest::Method::POST, "/v1/files", Some(body), None, None)
+            .await
+    }
+
+    /// Get the metadata of an uploaded file.
+    pub async fn get_file(&self, file_id: &str) -> Result<FileMetadata> {
         self.inner
             .execute_request(
-                reqwest::Method::POST,
-                "/v1/messages/count_tokens",
-                Some(body),
+                reqwest::Method::GET,
+                &format!("/v1/files/{}", file_id),
+                None,
             )
             .await
     }
 
+    /// Delete an uploaded file.
+    pub async fn delete_file(&self, file_id: &str) -> Result<()> {
+        let _: Value = self
+            .inner
+            .execute_request(
+                reqwest::Method::DELETE,
+                &format!("/v1/files/{}", file_id),
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Download and decode the results of a completed message batch.
+    ///
+    /// Returns one `(custom_id, result)` pair per line of the batch's JSON Lines results,
+    /// in the order the API returns them. Failed entries are decoded into the same
+    /// [`Error`] the request would have produced on its own; canceled and expired entries
+    /// are reported as `Error::InvalidRequest`. The outer result fails if the download
+    /// fails or a line cannot be parsed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use anthropic_rust::{Client, Model};
+    ///
+    /// # async fn example() -> anthropic_rust::Result<()> {
+    /// let client = Client::new(Model::Claude35Sonnet20241022)?;
+    /// for (custom_id, result) in client.batch_results("msgbatch_123").await? {
+    ///     match result {
+    ///         Ok(message) => println!("{}: {:?}", custom_id, message.content),
+    ///         Err(error) => eprintln!("{} failed: {}", custom_id, error),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn batch_results(&self, batch_id: &str) -> Result<Vec<(String, Result<Message>)>> {
+        let body = self
+            .inner
+            .execute_text_request(
+                reqwest::Method::GET,
+                &format!("/v1/messages/batches/{}/results", batch_id),
+            )
+            .await?;
+
+        body.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| self.inner.parse_batch_result(line))
+            .collect()
+    }
+
     /// Create a new chat request builder.
     ///
     /// The builder provides a fluent API for constructing chat requests with
@@ -1324,6 +2448,32 @@ impl Client {
         ChatRequestBuilder::new()
     }
 
+    /// Create a new token counting request builder.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use anthropic_rust::{Client, Model, ContentBlock};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Client::new(Model::Claude35Sonnet20241022)?;
+    ///
+    ///     let request = client.count_tokens_builder()
+    ///         .system("You are a helpful assistant.")
+    ///         .user_message(ContentBlock::text("Hello!"))
+    ///         .build();
+    ///
+    ///     let count = client.count_tokens(request).await?;
+    ///     println!("Input tokens: {}", count.input_tokens);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn count_tokens_builder(&self) -> CountTokensRequestBuilder {
+        CountTokensRequestBuilder::new()
+    }
+
     /// Get the client's default model.
     ///
     /// Returns the model that will be used for requests when no model override is specified.
@@ -1348,8 +2498,8 @@ impl Client {
 
     /// Get the client's default max_tokens setting.
     ///
-    /// Returns the maximum number of tokens that will be used for response generation
-    /// when no override is specified.
+    /// Returns the maximum number of tokens that will be used for non-streaming response
+    /// generation with the default model when no override is specified.
     ///
     /// # Examples
     ///
@@ -1366,10 +2516,44 @@ impl Client {
     /// }
     /// ```
     pub fn default_max_tokens(&self) -> u32 {
-        self.inner.config.max_tokens
+        self.inner
+            .config
+            .max_tokens_for(&self.inner.config.model, false)
     }
 }
 
+/// HTTP status the API sends with an error body of the given `error.type`
+fn error_type_status(error_type: Option<&str>) -> StatusCode {
+    match error_type {
+        Some("invalid_request_error") => StatusCode::BAD_REQUEST,
+        Some("authentication_error") => StatusCode::UNAUTHORIZED,
+        Some("permission_error") => StatusCode::FORBIDDEN,
+        Some("not_found_error") => StatusCode::NOT_FOUND,
+        Some("request_too_large") => StatusCode::PAYLOAD_TOO_LARGE,
+        Some("rate_limit_error") => StatusCode::TOO_MANY_REQUESTS,
+        Some("overloaded_error") => StatusCode::from_u16(529).expect("529 is a valid status code"),
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Decode a successful response body, reporting binary bodies distinctly from bad JSON
+fn decode_utf8_body(body: &[u8]) -> Result<&str> {
+    std::str::from_utf8(body).map_err(|e| {
+        Error::InvalidResponse(format!(
+            "Response body was not valid UTF-8 ({} bytes, invalid at byte {})",
+            body.len(),
+            e.valid_up_to()
+        ))
+    })
+}
+
+/// Cache key for a chat request body, covering model and `max_tokens`
+fn response_cache_key(body: &Value) -> String {
+    let mut hasher = DefaultHasher::new();
+    body.to_string().hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
 /// Extract request ID from response headers
 pub(crate) fn extract_request_id(headers: &HeaderMap) -> Option<String> {
     headers
@@ -1379,6 +2563,30 @@ pub(crate) fn extract_request_id(headers: &HeaderMap) -> Option<String> {
         .map(|s| s.to_string())
 }
 
+/// Longest server-requested retry delay honoured; larger values are clamped to it
+pub(crate) const MAX_RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Extract the delay from a `Retry-After` header, given in whole seconds or as an HTTP
+/// date
+///
+/// Dates in the past yield a zero delay; delays beyond [`MAX_RETRY_AFTER`] are clamped.
+pub(crate) fn extract_retry_after_header(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get("retry-after")?.to_str().ok()?.trim();
+
+    // RFC 9110 delta-seconds are a non-negative integer; saturate instead of overflowing
+    if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+        let seconds = value.parse::<u64>().unwrap_or(u64::MAX);
+        return Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER));
+    }
+
+    let date = parse_http_date(value)?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO)
+            .min(MAX_RETRY_AFTER),
+    )
+}
+
 /// Extract retry-after duration from error response
 pub(crate) fn extract_retry_after_duration(body: &str) -> Option<Duration> {
     // Try to parse JSON and look for retry_after field
@@ -1388,12 +2596,10 @@ pub(crate) fn extract_retry_after_duration(body: &str) -> Option<Duration> {
             .and_then(|e| e.get("retry_after"))
             .and_then(|r| r.as_f64())
         {
-            return Some(Duration::from_secs_f64(retry_after));
+            return (retry_after >= 0.0)
+                .then(|| Duration::from_secs_f64(retry_after.min(MAX_RETRY_AFTER.as_secs_f64())));
         }
     }
 
     None
 }
-
-// SSE parsing will be implemented in a future iteration
-// For now, we use a mock implementation for testing
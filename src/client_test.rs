@@ -33,12 +33,16 @@
 #[cfg(all(test, not(miri)))]
 mod network_tests {
     use serde_json::json;
+    use std::sync::Arc;
     use std::time::Duration;
 
     use crate::{
-        client::{ClientInner, RequestMiddleware, RetryConfig},
+        client::{
+            ClientInner, InFlight, RequestMiddleware, RetryConfig, TokioSleeper, UuidGenerator,
+        },
         config::Config,
         error::Error,
+        streaming::StreamTransport,
         types::Model,
     };
 
@@ -50,6 +54,15 @@ mod network_tests {
             max_retries: 2,
             model: Model::Claude35Sonnet20241022,
             max_tokens: 1000,
+            auto_max_tokens: false,
+            default_system: None,
+            beta_features: Vec::new(),
+            stream_include_usage: true,
+            validate_requests: false,
+            model_fallback: Vec::new(),
+            precheck_tokens: false,
+            error_on_empty_response: false,
+            retry_streams: false,
         };
 
         let http_client = reqwest::Client::builder()
@@ -62,6 +75,12 @@ mod network_tests {
             config,
             retry_config: RetryConfig::default(),
             middleware: RequestMiddleware::default(),
+            token_count_cache: None,
+            response_cache: None,
+            in_flight: InFlight::default(),
+            sleeper: Arc::new(TokioSleeper),
+            id_generator: Arc::new(UuidGenerator),
+            stream_transport: StreamTransport::default(),
         }
     }
 
@@ -123,6 +142,15 @@ mod network_tests {
             max_retries: 0,                    // No retries to speed up test
             model: Model::Claude35Sonnet20241022,
             max_tokens: 1000,
+            auto_max_tokens: false,
+            default_system: None,
+            beta_features: Vec::new(),
+            stream_include_usage: true,
+            validate_requests: false,
+            model_fallback: Vec::new(),
+            precheck_tokens: false,
+            error_on_empty_response: false,
+            retry_streams: false,
         };
 
         let http_client = reqwest::Client::builder()
@@ -138,6 +166,12 @@ mod network_tests {
                 ..RetryConfig::default()
             },
             middleware: RequestMiddleware::default(),
+            token_count_cache: None,
+            response_cache: None,
+            in_flight: InFlight::default(),
+            sleeper: Arc::new(TokioSleeper),
+            id_generator: Arc::new(UuidGenerator),
+            stream_transport: StreamTransport::default(),
         };
 
         // Use httpbin's /delay endpoint which will likely timeout
@@ -201,6 +235,15 @@ mod network_tests {
             max_retries: 0,
             model: Model::Claude35Sonnet20241022,
             max_tokens: 1000,
+            auto_max_tokens: false,
+            default_system: None,
+            beta_features: Vec::new(),
+            stream_include_usage: true,
+            validate_requests: false,
+            model_fallback: Vec::new(),
+            precheck_tokens: false,
+            error_on_empty_response: false,
+            retry_streams: false,
         };
 
         let http_client = reqwest::Client::builder()
@@ -213,6 +256,12 @@ mod network_tests {
             config,
             retry_config: RetryConfig::default(),
             middleware: RequestMiddleware::default().with_full_logging(),
+            token_count_cache: None,
+            response_cache: None,
+            in_flight: InFlight::default(),
+            sleeper: Arc::new(TokioSleeper),
+            id_generator: Arc::new(UuidGenerator),
+            stream_transport: StreamTransport::default(),
         };
 
         // This test mainly verifies that logging doesn't crash
@@ -234,6 +283,15 @@ mod network_tests {
             max_retries: 2,
             model: Model::Claude35Sonnet20241022,
             max_tokens: 1000,
+            auto_max_tokens: false,
+            default_system: None,
+            beta_features: Vec::new(),
+            stream_include_usage: true,
+            validate_requests: false,
+            model_fallback: Vec::new(),
+            precheck_tokens: false,
+            error_on_empty_response: false,
+            retry_streams: false,
         };
 
         let http_client = reqwest::Client::builder()
@@ -249,8 +307,16 @@ mod network_tests {
                 initial_delay: Duration::from_millis(10), // Fast retries for testing
                 max_delay: Duration::from_millis(100),
                 backoff_multiplier: 2.0,
+                overloaded_backoff: Duration::from_millis(50),
+                retryable_statuses: None,
             },
             middleware: RequestMiddleware::default().with_request_logging(),
+            token_count_cache: None,
+            response_cache: None,
+            in_flight: InFlight::default(),
+            sleeper: Arc::new(TokioSleeper),
+            id_generator: Arc::new(UuidGenerator),
+            stream_transport: StreamTransport::default(),
         };
 
         // Use a 500 error which should be retried
@@ -410,6 +476,53 @@ mod unit_tests {
         let no_retry_after = r#"{"error": {"message": "rate limited"}}"#;
         let duration = extract_retry_after_duration(no_retry_after);
         assert_eq!(duration, None);
+
+        let huge = r#"{"error": {"retry_after": 1e30}}"#;
+        assert_eq!(
+            extract_retry_after_duration(huge),
+            Some(crate::client::MAX_RETRY_AFTER)
+        );
+        let negative = r#"{"error": {"retry_after": -1}}"#;
+        assert_eq!(extract_retry_after_duration(negative), None);
+    }
+
+    #[test]
+    fn test_extract_retry_after_header() {
+        use crate::client::extract_retry_after_header;
+        use reqwest::header::{HeaderMap, HeaderValue};
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(extract_retry_after_header(&headers), None);
+
+        headers.insert("retry-after", HeaderValue::from_static("30"));
+        assert_eq!(
+            extract_retry_after_header(&headers),
+            Some(Duration::from_secs(30))
+        );
+
+        // Dates in the past mean "retry now"
+        headers.insert(
+            "retry-after",
+            HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT"),
+        );
+        assert_eq!(extract_retry_after_header(&headers), Some(Duration::ZERO));
+
+        headers.insert("retry-after", HeaderValue::from_static("soon"));
+        assert_eq!(extract_retry_after_header(&headers), None);
+
+        // Huge or malformed values must not panic; large delays are clamped
+        headers.insert("retry-after", HeaderValue::from_static("1e30"));
+        assert_eq!(extract_retry_after_header(&headers), None);
+        headers.insert(
+            "retry-after",
+            HeaderValue::from_static("99999999999999999999999"),
+        );
+        assert_eq!(
+            extract_retry_after_header(&headers),
+            Some(crate::client::MAX_RETRY_AFTER)
+        );
+        headers.insert("retry-after", HeaderValue::from_static("1.5"));
+        assert_eq!(extract_retry_after_header(&headers), None);
     }
 
     #[test]
@@ -464,12 +577,12 @@ mod unit_tests {
         let client = Client::builder()
             .api_key("sk-ant-api03-test-key")
             .model(Model::Claude3Opus20240229)
-            .max_tokens(8192)
+            .max_tokens(4000)
             .build()
             .expect("Client should build with custom config");
 
         assert_eq!(client.default_model(), Model::Claude3Opus20240229);
-        assert_eq!(client.default_max_tokens(), 8192);
+        assert_eq!(client.default_max_tokens(), 4000);
     }
 
     #[test]
@@ -522,11 +635,13 @@ mod unit_tests {
             system: Some(vec![SystemMessage {
                 message_type: "text".to_string(),
                 text: "Be helpful".to_string(),
+                cache_control: None,
             }]),
             tools: None,
             temperature: Some(0.7),
             top_p: Some(0.9),
             stop_sequences: Some(vec!["STOP".to_string()]),
+            ..Default::default()
         };
 
         // Test that the request can be serialized (this is what execute_chat does internally)
@@ -558,6 +673,7 @@ mod unit_tests {
             temperature: None,
             top_p: None,
             stop_sequences: None,
+            ..Default::default()
         };
 
         // Simulate what execute_chat_with_model does
@@ -598,8 +714,10 @@ mod unit_tests {
             system: Some(vec![SystemMessage {
                 message_type: "text".to_string(),
                 text: "You are a helpful assistant.".to_string(),
+                cache_control: None,
             }]),
             tools: None,
+            extra: Default::default(),
         };
 
         // This will fail because httpbin doesn't implement the Anthropic API,
@@ -645,8 +763,10 @@ mod unit_tests {
             system: Some(vec![SystemMessage {
                 message_type: "text".to_string(),
                 text: "Be helpful and concise.".to_string(),
+                cache_control: None,
             }]),
             tools: None,
+            extra: Default::default(),
         };
 
         // Test that the request can be serialized
@@ -685,6 +805,7 @@ mod unit_tests {
             }],
             system: None,
             tools: None,
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_value(&request).expect("Should serialize");
@@ -722,6 +843,7 @@ mod unit_tests {
             }],
             system: None,
             tools: None,
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_value(&request).expect("Should serialize");
@@ -779,6 +901,7 @@ mod unit_tests {
             }],
             system: None,
             tools: Some(vec![tool]),
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_value(&request).expect("Should serialize");
@@ -823,6 +946,7 @@ mod unit_tests {
             }],
             system: None,
             tools: None,
+            extra: Default::default(),
         };
 
         // Test that count_tokens uses the client's configured model
@@ -888,7 +1012,7 @@ mod unit_tests {
     fn test_count_tokens_request_builder_pattern() {
         use crate::types::{ContentBlock, CountTokensRequest, MessageParam, Role, SystemMessage};
 
-        // Test building a CountTokensRequest manually (no builder pattern exists yet)
+        // Test building a CountTokensRequest manually
         let messages = vec![
             MessageParam {
                 role: Role::User,
@@ -903,12 +1027,14 @@ mod unit_tests {
         let system = Some(vec![SystemMessage {
             message_type: "text".to_string(),
             text: "System prompt".to_string(),
+            cache_control: None,
         }]);
 
         let request = CountTokensRequest {
             messages,
             system,
             tools: None,
+            extra: Default::default(),
         };
 
         // Verify the structure
@@ -918,6 +1044,35 @@ mod unit_tests {
         assert!(request.tools.is_none());
     }
 
+    #[test]
+    fn test_count_tokens_builder() {
+        use crate::tools::Tool;
+        use crate::types::{ContentBlock, Role};
+
+        let client = crate::Client::builder()
+            .api_key("sk-ant-api03-test-key")
+            .build()
+            .expect("Client should build successfully");
+        let request = client
+            .count_tokens_builder()
+            .system("System prompt")
+            .user_message(ContentBlock::text("First message"))
+            .assistant_message(ContentBlock::text("Response message"))
+            .message_with_content(
+                Role::User,
+                vec![ContentBlock::text("a"), ContentBlock::text("b")],
+            )
+            .tool(Tool::builder("search").build())
+            .tools(vec![Tool::builder("fetch").build()])
+            .build();
+
+        assert_eq!(request.messages.len(), 3);
+        assert_eq!(request.messages[1].role, Role::Assistant);
+        assert_eq!(request.messages[2].content.len(), 2);
+        assert_eq!(request.system.as_ref().unwrap()[0].text, "System prompt");
+        assert_eq!(request.tools.as_ref().unwrap().len(), 2);
+    }
+
     #[test]
     fn test_mock_http_client_response_parsing() {
         use crate::mock::MockResponseBuilder;
@@ -1216,11 +1371,13 @@ mod unit_tests {
             system: Some(vec![SystemMessage {
                 message_type: "text".to_string(),
                 text: "Be helpful".to_string(),
+                cache_control: None,
             }]),
             tools: None,
             temperature: Some(0.7), // This field won't be in CountTokensRequest
             top_p: Some(0.9),       // This field won't be in CountTokensRequest
             stop_sequences: Some(vec!["STOP".to_string()]), // This field won't be in CountTokensRequest
+            ..Default::default()
         };
 
         // Use the From trait implementation
@@ -1253,6 +1410,7 @@ mod unit_tests {
             temperature: Some(0.5),
             top_p: Some(0.8),
             stop_sequences: None,
+            ..Default::default()
         };
 
         // Convert using From trait
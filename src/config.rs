@@ -1,21 +1,30 @@
 //! Configuration and builder patterns for the Anthropic client
 
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use url::Url;
 
 use std::sync::Arc;
 
 use crate::{
+    cache::{ResponseCache, TokenCountCache},
     client::{
-        Client, ClientInner, LoggingInterceptor, RequestInterceptor, RequestMiddleware, RetryConfig,
+        Client, ClientInner, IdGenerator, InFlight, LoggingInterceptor, RequestInterceptor,
+        RequestMiddleware, RetryConfig, Sleeper, TokioSleeper, UuidGenerator,
     },
     error::Error,
+    streaming::StreamTransport,
     types::Model,
     Result,
 };
 
 /// Configuration for the Anthropic client
+///
+/// New settings may be added in minor releases; configure clients through
+/// [`ClientBuilder`] rather than constructing this struct directly.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct Config {
     pub api_key: String,
     pub base_url: Url,
@@ -23,6 +32,32 @@ pub struct Config {
     pub max_retries: u32,
     pub model: Model,
     pub max_tokens: u32,
+    /// Use each model's maximum output tokens instead of `max_tokens`
+    ///
+    /// Only set when `max_tokens` was not chosen explicitly.
+    pub auto_max_tokens: bool,
+    /// System prompt placed before any request-level system prompt
+    pub default_system: Option<String>,
+    /// Beta feature flags sent in the `anthropic-beta` header
+    pub beta_features: Vec<String>,
+    /// Keep the usage reported in streaming `message_delta` events
+    ///
+    /// The API always reports usage; when false it is stripped client-side.
+    pub stream_include_usage: bool,
+    /// Validate request bodies against the embedded schema before sending
+    ///
+    /// Enabled by default in debug builds.
+    pub validate_requests: bool,
+    /// Models tried in order when a chat request stays overloaded or rate limited
+    pub model_fallback: Vec<Model>,
+    /// Count input tokens before each chat request and reject requests that cannot
+    /// fit in the model's context window
+    pub precheck_tokens: bool,
+    /// Fail with `Error::InvalidResponse` when a chat response ends its turn with no
+    /// content
+    pub error_on_empty_response: bool,
+    /// Re-issue a streaming request when its stream fails mid-way with a retryable error
+    pub retry_streams: bool,
 }
 
 impl Default for Config {
@@ -36,11 +71,46 @@ impl Default for Config {
             max_retries: 3,
             model: Model::Claude35Sonnet20241022,
             max_tokens: 4096,
+            auto_max_tokens: false,
+            default_system: None,
+            beta_features: Vec::new(),
+            stream_include_usage: true,
+            validate_requests: cfg!(debug_assertions),
+            model_fallback: Vec::new(),
+            precheck_tokens: false,
+            error_on_empty_response: false,
+            retry_streams: false,
         }
     }
 }
 
 impl Config {
+    /// Effective `max_tokens` for requests sent to `model`
+    pub(crate) fn max_tokens_for(&self, model: &Model, streaming: bool) -> u32 {
+        if !self.auto_max_tokens {
+            return self.max_tokens;
+        }
+
+        // Non-streaming requests to slower families are capped to avoid HTTP timeouts
+        let family = model.family();
+        if streaming || !family.streaming_recommended() {
+            model.max_output_tokens()
+        } else {
+            model
+                .max_output_tokens()
+                .min(family.recommended_max_tokens())
+        }
+    }
+
+    /// Value of the `anthropic-beta` header, if any beta features are enabled
+    pub(crate) fn beta_header(&self) -> Option<String> {
+        if self.beta_features.is_empty() {
+            None
+        } else {
+            Some(self.beta_features.join(","))
+        }
+    }
+
     /// Validate the configuration parameters
     pub fn validate(&self) -> Result<()> {
         // Validate API key
@@ -62,11 +132,11 @@ impl Config {
             ));
         }
 
-        // Validate max_tokens against model limits
-        let model_max_tokens = self.model.max_tokens();
+        // Validate max_tokens against the model's output limit
+        let model_max_tokens = self.model.max_output_tokens();
         if self.max_tokens > model_max_tokens {
             return Err(Error::Config(format!(
-                "max_tokens ({}) exceeds model limit ({}) for {:?}",
+                "max_tokens ({}) exceeds model limit ({} output tokens) for {:?}",
                 self.max_tokens, model_max_tokens, self.model
             )));
         }
@@ -93,14 +163,31 @@ impl Config {
 #[derive(Debug, Default)]
 pub struct ClientBuilder {
     api_key: Option<String>,
+    api_key_file: Option<PathBuf>,
     base_url: Option<Url>,
     timeout: Option<Duration>,
     max_retries: Option<u32>,
     http_client: Option<reqwest::Client>,
+    redirect_policy: Option<reqwest::redirect::Policy>,
     model: Option<Model>,
     max_tokens: Option<u32>,
+    auto_max_tokens: Option<bool>,
+    default_system: Option<String>,
     retry_config: Option<RetryConfig>,
+    retryable_statuses: Option<HashSet<u16>>,
     middleware: Option<RequestMiddleware>,
+    token_count_cache: Option<usize>,
+    response_cache: Option<Arc<dyn ResponseCache>>,
+    beta_features: Vec<String>,
+    stream_include_usage: Option<bool>,
+    validate_requests: Option<bool>,
+    model_fallback: Vec<Model>,
+    precheck_tokens: Option<bool>,
+    error_on_empty_response: Option<bool>,
+    retry_streams: Option<bool>,
+    sleeper: Option<Arc<dyn Sleeper>>,
+    id_generator: Option<Arc<dyn IdGenerator>>,
+    stream_transport: StreamTransport,
 }
 
 impl ClientBuilder {
@@ -115,6 +202,16 @@ impl ClientBuilder {
         self
     }
 
+    /// Read the API key from a file when the client is built
+    ///
+    /// Useful for secret-file deployments such as Docker secrets or mounted Kubernetes
+    /// secrets. Surrounding whitespace is trimmed. An explicit [`api_key`](Self::api_key)
+    /// takes precedence over the file, and the file takes precedence over environment variables.
+    pub fn api_key_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.api_key_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
     /// Set the base URL
     pub fn base_url(mut self, url: impl TryInto<Url>) -> Result<Self> {
         self.base_url = Some(
@@ -142,24 +239,91 @@ impl ClientBuilder {
         self
     }
 
+    /// Set how HTTP redirects are handled (defaults to not following them)
+    ///
+    /// The Anthropic API never redirects, and following a redirect from a misbehaving
+    /// proxy could send the API key to an unexpected host, so redirect responses are
+    /// returned as errors by default. Ignored when a custom
+    /// [`http_client`](Self::http_client) is supplied.
+    pub fn redirect_policy(mut self, policy: reqwest::redirect::Policy) -> Self {
+        self.redirect_policy = Some(policy);
+        self
+    }
+
     /// Set the default model
     pub fn model(mut self, model: Model) -> Self {
         self.model = Some(model);
         self
     }
 
-    /// Set the default max tokens
+    /// Set the default max tokens (defaults to 4096)
+    ///
+    /// This caps the output of each response and must not exceed
+    /// [`Model::max_output_tokens`] for the model a request is sent to. Use
+    /// [`auto_max_tokens`](Self::auto_max_tokens) to size it per model instead.
     pub fn max_tokens(mut self, tokens: u32) -> Self {
         self.max_tokens = Some(tokens);
         self
     }
 
+    /// Set a system prompt sent with every chat and token counting request
+    ///
+    /// It is placed before any system prompt on the request itself.
+    pub fn default_system(mut self, system: impl Into<String>) -> Self {
+        self.default_system = Some(system.into());
+        self
+    }
+
+    /// Set models to fall back to when a chat request stays overloaded or rate limited
+    ///
+    /// After retries on one model are exhausted with an overloaded (529) or rate limit
+    /// (429) error, the request is sent again with the next model in the chain, skipping
+    /// models the request fails [`ChatRequest::validate`](crate::ChatRequest::validate)
+    /// for. [`ResponseMeta::fallback_model`](crate::ResponseMeta::fallback_model) reports
+    /// which fallback served the response; downgraded responses are never stored in the
+    /// response cache.
+    pub fn model_fallback(mut self, models: Vec<Model>) -> Self {
+        self.model_fallback = models;
+        self
+    }
+
     /// Set custom retry configuration
     pub fn retry_config(mut self, config: RetryConfig) -> Self {
         self.retry_config = Some(config);
         self
     }
 
+    /// Enable a beta feature, sent in the `anthropic-beta` header on every request
+    pub fn beta_feature(mut self, feature: impl Into<String>) -> Self {
+        let feature = feature.into();
+        if !self.beta_features.contains(&feature) {
+            self.beta_features.push(feature);
+        }
+        self
+    }
+
+    /// Enable several beta features at once
+    pub fn beta_features<I, S>(self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        features
+            .into_iter()
+            .fold(self, |builder, feature| builder.beta_feature(feature))
+    }
+
+    /// Also retry requests that fail with the given HTTP status
+    ///
+    /// The status is added to the default retryable set (429 and 5xx), or to
+    /// [`RetryConfig::retryable_statuses`] when a custom retry configuration sets one.
+    pub fn retry_on_status(mut self, status: u16) -> Self {
+        self.retryable_statuses
+            .get_or_insert_with(HashSet::new)
+            .insert(status);
+        self
+    }
+
     /// Set request middleware for logging and debugging
     pub fn middleware(mut self, middleware: RequestMiddleware) -> Self {
         self.middleware = Some(middleware);
@@ -187,11 +351,114 @@ impl ClientBuilder {
         self
     }
 
+    /// Enable an in-memory LRU cache for token counting results
+    ///
+    /// Identical `count_tokens` requests are answered from the cache instead of the API.
+    /// `capacity` is the maximum number of distinct requests kept.
+    pub fn token_count_cache(mut self, capacity: usize) -> Self {
+        self.token_count_cache = Some(capacity);
+        self
+    }
+
+    /// Serve repeated deterministic chat requests from `cache`
+    ///
+    /// See [`ResponseCache`] for which requests are cached.
+    pub fn response_cache(mut self, cache: Arc<dyn ResponseCache>) -> Self {
+        self.response_cache = Some(cache);
+        self
+    }
+
+    /// Set the sleeper used to wait between retry attempts
+    pub fn sleeper(mut self, sleeper: Arc<dyn Sleeper>) -> Self {
+        self.sleeper = Some(sleeper);
+        self
+    }
+
+    /// Set the generator used for client-generated ids (defaults to [`UuidGenerator`])
+    pub fn id_generator(mut self, generator: Arc<dyn IdGenerator>) -> Self {
+        self.id_generator = Some(generator);
+        self
+    }
+
+    /// Set whether streaming `message_delta` events keep their usage report (defaults to true)
+    ///
+    /// Nothing extra is sent to the API, which always reports usage.
+    pub fn stream_include_usage(mut self, include: bool) -> Self {
+        self.stream_include_usage = Some(include);
+        self
+    }
+
+    /// Set whether request bodies are schema-validated before sending
+    ///
+    /// Defaults to enabled in debug builds and disabled in release builds.
+    pub fn validate_requests(mut self, validate: bool) -> Self {
+        self.validate_requests = Some(validate);
+        self
+    }
+
+    /// Set whether chat requests are token-counted before being sent (defaults to false)
+    ///
+    /// When enabled, [`Client::execute_chat`](crate::Client::execute_chat) first calls
+    /// `count_tokens` and fails with `Error::InvalidRequest` if the input tokens plus
+    /// `max_tokens` exceed the model's context window, before any generation is billed.
+    /// This costs one extra round trip per request.
+    pub fn precheck_tokens(mut self, precheck: bool) -> Self {
+        self.precheck_tokens = Some(precheck);
+        self
+    }
+
+    /// Set whether an empty `end_turn` response is an error (defaults to false)
+    ///
+    /// The API occasionally returns a message with no content blocks and an
+    /// `end_turn` stop reason. When enabled, [`Client::execute_chat`](crate::Client::execute_chat)
+    /// returns `Error::InvalidResponse` for such messages instead of handing them back.
+    pub fn error_on_empty_response(mut self, error: bool) -> Self {
+        self.error_on_empty_response = Some(error);
+        self
+    }
+
+    /// Restart streams that fail mid-way with a retryable error (defaults to false)
+    ///
+    /// When the server sends an error event such as `overloaded_error` after the stream
+    /// has started, the request is re-issued under the client's [`RetryConfig`](crate::RetryConfig),
+    /// waiting through its sleeper between attempts. Events from the new attempt follow
+    /// on the same stream, starting with a fresh `message_start` that supersedes the
+    /// partial message; [`MessageAccumulator`](crate::MessageAccumulator) handles this
+    /// automatically. Byte callbacks only observe the first attempt.
+    pub fn retry_streams(mut self, retry: bool) -> Self {
+        self.retry_streams = Some(retry);
+        self
+    }
+
+    /// Default `max_tokens` to each model's output limit (defaults to false)
+    ///
+    /// When enabled and [`max_tokens`](Self::max_tokens) is not set, requests use
+    /// [`Model::max_output_tokens`] for the model they are sent to instead of a flat
+    /// 4096. Non-streaming requests to families where
+    /// [`ModelFamily::streaming_recommended`](crate::ModelFamily::streaming_recommended)
+    /// are capped at the family's recommended `max_tokens` to avoid timeouts. Longer
+    /// responses can cost more, so this is opt-in. An explicit `max_tokens` always wins.
+    pub fn auto_max_tokens(mut self, auto: bool) -> Self {
+        self.auto_max_tokens = Some(auto);
+        self
+    }
+
+    /// Set how streaming responses are read (defaults to [`StreamTransport::Sse`])
+    pub fn stream_transport(mut self, transport: StreamTransport) -> Self {
+        self.stream_transport = transport;
+        self
+    }
+
     /// Build the client
     pub fn build(self) -> Result<Client> {
-        // Set API key from builder or environment variables
+        // Set API key from builder, key file, or environment variables
+        let api_key_from_file = match (&self.api_key, &self.api_key_file) {
+            (None, Some(path)) => Some(read_api_key_file(path)?),
+            _ => None,
+        };
         let api_key = self
             .api_key
+            .or(api_key_from_file)
             .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok())
             .or_else(|| std::env::var("CLAUDE_API_KEY").ok()) // Alternative env var
             .ok_or_else(|| {
@@ -230,6 +497,25 @@ impl ClientBuilder {
         if let Some(max_tokens) = self.max_tokens {
             config.max_tokens = max_tokens;
         }
+        config.auto_max_tokens = self.auto_max_tokens.unwrap_or(false) && self.max_tokens.is_none();
+        config.beta_features = self.beta_features;
+        config.model_fallback = self.model_fallback;
+        config.default_system = self.default_system;
+        if let Some(include) = self.stream_include_usage {
+            config.stream_include_usage = include;
+        }
+        if let Some(validate) = self.validate_requests {
+            config.validate_requests = validate;
+        }
+        if let Some(precheck) = self.precheck_tokens {
+            config.precheck_tokens = precheck;
+        }
+        if let Some(error) = self.error_on_empty_response {
+            config.error_on_empty_response = error;
+        }
+        if let Some(retry) = self.retry_streams {
+            config.retry_streams = retry;
+        }
 
         // Validate the configuration
         config.validate()?;
@@ -238,6 +524,10 @@ impl ClientBuilder {
         let http_client = self.http_client.unwrap_or_else(|| {
             let mut builder = reqwest::Client::builder()
                 .timeout(config.timeout)
+                .redirect(
+                    self.redirect_policy
+                        .unwrap_or_else(reqwest::redirect::Policy::none),
+                )
                 .user_agent(format!("anthropic-rust-sdk/{}", env!("CARGO_PKG_VERSION")));
 
             // Add default headers
@@ -263,7 +553,7 @@ impl ClientBuilder {
 
         // Handle retry configuration - if retry_config is explicitly set, use it
         // Otherwise, create one from max_retries if set
-        let retry_config = if let Some(retry_config) = self.retry_config {
+        let mut retry_config = if let Some(retry_config) = self.retry_config {
             retry_config
         } else {
             let mut default_retry = RetryConfig::default();
@@ -273,17 +563,51 @@ impl ClientBuilder {
             default_retry
         };
 
+        // Extra retryable statuses extend the configured (or default) set
+        if let Some(statuses) = self.retryable_statuses {
+            retry_config
+                .retryable_statuses
+                .get_or_insert_with(RetryConfig::default_retryable_statuses)
+                .extend(statuses);
+        }
+
         let inner = ClientInner {
             http_client,
             config,
             retry_config,
             middleware: self.middleware.unwrap_or_default(),
+            token_count_cache: self.token_count_cache.map(TokenCountCache::new),
+            response_cache: self.response_cache,
+            in_flight: InFlight::default(),
+            sleeper: self.sleeper.unwrap_or_else(|| Arc::new(TokioSleeper)),
+            id_generator: self.id_generator.unwrap_or_else(|| Arc::new(UuidGenerator)),
+            stream_transport: self.stream_transport,
         };
 
         Ok(Client::from_inner(inner))
     }
 }
 
+/// Read and trim an API key from a secret file
+fn read_api_key_file(path: &Path) -> Result<String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        Error::Config(format!(
+            "Failed to read API key file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    let key = contents.trim();
+    if key.is_empty() {
+        return Err(Error::Config(format!(
+            "API key file {} is empty",
+            path.display()
+        )));
+    }
+    Ok(key.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -378,7 +702,7 @@ mod tests {
         let config = Config {
             api_key: "sk-ant-api03-test-key".to_string(),
             model: Model::Claude3Haiku20240307,
-            max_tokens: 300_000, // Exceeds model limit of 200_000
+            max_tokens: 8_192, // Exceeds output limit of 4_096
             ..Config::default()
         };
 
@@ -585,6 +909,26 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_client_builder_beta_features() {
+        let client = ClientBuilder::new()
+            .api_key("sk-ant-api03-test-key")
+            .beta_feature("pdfs-2024-09-25")
+            .beta_features(["token-counting-2024-11-01", "pdfs-2024-09-25"])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            client.inner.config.beta_features,
+            vec!["pdfs-2024-09-25", "token-counting-2024-11-01"]
+        );
+        assert_eq!(
+            client.inner.config.beta_header().as_deref(),
+            Some("pdfs-2024-09-25,token-counting-2024-11-01")
+        );
+        assert_eq!(Config::default().beta_header(), None);
+    }
+
     #[test]
     fn test_client_builder_build_validation_failure() {
         let result = ClientBuilder::new()
@@ -613,6 +957,80 @@ mod tests {
             .contains("exceeds model limit"));
     }
 
+    #[test]
+    fn test_client_builder_auto_max_tokens() {
+        let build = |builder: ClientBuilder| {
+            builder
+                .api_key("sk-ant-api03-test-key")
+                .model(Model::Claude4Sonnet20250514)
+                .build()
+                .unwrap()
+        };
+
+        let fixed = build(ClientBuilder::new());
+        assert_eq!(fixed.default_max_tokens(), 4096);
+
+        // Streaming uses the full output limit; non-streaming Sonnet requests are capped
+        let auto = build(ClientBuilder::new().auto_max_tokens(true));
+        let config = &auto.inner.config;
+        assert_eq!(
+            config.max_tokens_for(&Model::Claude4Sonnet20250514, true),
+            64_000
+        );
+        assert_eq!(auto.default_max_tokens(), 8_192);
+        assert_eq!(
+            config.max_tokens_for(&Model::Claude3Haiku20240307, false),
+            4_096
+        );
+
+        // An explicit max_tokens wins over auto mode
+        let overridden = build(ClientBuilder::new().auto_max_tokens(true).max_tokens(1000));
+        assert_eq!(overridden.default_max_tokens(), 1000);
+        assert_eq!(
+            overridden
+                .inner
+                .config
+                .max_tokens_for(&Model::Claude4Sonnet20250514, true),
+            1000
+        );
+    }
+
+    #[test]
+    fn test_client_builder_id_generator() {
+        let client = ClientBuilder::new()
+            .api_key("sk-ant-api03-test-key")
+            .id_generator(Arc::new(crate::SequentialIdGenerator::new("req")))
+            .build()
+            .unwrap();
+        assert_eq!(client.generate_id(), "req-1");
+        assert_eq!(client.generate_id(), "req-2");
+        // Clones share the client's generator
+        assert_eq!(client.clone().generate_id(), "req-3");
+
+        let client = ClientBuilder::new()
+            .api_key("sk-ant-api03-test-key")
+            .build()
+            .unwrap();
+        let id = client.generate_id();
+        assert_eq!(id.len(), 36);
+        assert_ne!(id, client.generate_id());
+    }
+
+    #[test]
+    fn test_client_builder_redirect_policy() {
+        let result = ClientBuilder::new()
+            .api_key("sk-ant-api03-test-key")
+            .redirect_policy(reqwest::redirect::Policy::none())
+            .build();
+        assert!(result.is_ok());
+
+        let result = ClientBuilder::new()
+            .api_key("sk-ant-api03-test-key")
+            .redirect_policy(reqwest::redirect::Policy::limited(3))
+            .build();
+        assert!(result.is_ok());
+    }
+
     #[test]
     fn test_client_builder_build_with_all_options() {
         let result = ClientBuilder::new()
@@ -653,7 +1071,7 @@ mod tests {
             let config = Config {
                 api_key: "sk-ant-api03-test-key".to_string(),
                 model: model.clone(),
-                max_tokens: model.max_tokens(), // Use model's max tokens
+                max_tokens: model.max_output_tokens(), // Use model's output limit
                 ..Config::default()
             };
 
@@ -701,6 +1119,8 @@ mod tests {
             initial_delay: Duration::from_millis(100),
             max_delay: Duration::from_secs(10),
             backoff_multiplier: 1.5,
+            overloaded_backoff: Duration::from_secs(2),
+            retryable_statuses: None,
         };
 
         let client = ClientBuilder::new()
@@ -192,7 +192,7 @@ mod tests {
         let client = ClientBuilder::new()
             .api_key("sk-ant-api03-test-key")
             .model(Model::Claude3Opus20240229)
-            .max_tokens(8000)
+            .max_tokens(4000)
             .timeout(Duration::from_secs(45))
             .max_retries(2)
             .base_url("https://custom.anthropic.com")
@@ -205,7 +205,7 @@ mod tests {
         let config = &client.inner.config;
         assert_eq!(config.api_key, "sk-ant-api03-test-key");
         assert_eq!(config.model, Model::Claude3Opus20240229);
-        assert_eq!(config.max_tokens, 8000);
+        assert_eq!(config.max_tokens, 4000);
         assert_eq!(config.timeout, Duration::from_secs(45));
         assert_eq!(config.max_retries, 2);
         assert_eq!(config.base_url.as_str(), "https://custom.anthropic.com/");
@@ -268,12 +268,20 @@ mod tests {
         let client = ClientBuilder::new()
             .api_key("sk-ant-api03-test-key")
             .model(Model::Claude3Haiku20240307)
-            .max_tokens(200_000) // At model limit
+            .max_tokens(4_096) // At model output limit
             .build();
 
         assert!(client.is_ok());
         let client = client.unwrap();
-        assert_eq!(client.inner.config.max_tokens, 200_000);
+        assert_eq!(client.inner.config.max_tokens, 4_096);
+
+        // The context window is not a valid output limit
+        let result = ClientBuilder::new()
+            .api_key("sk-ant-api03-test-key")
+            .model(Model::Claude3Haiku20240307)
+            .max_tokens(200_000)
+            .build();
+        assert!(result.is_err());
     }
 
     #[test]
@@ -285,6 +293,15 @@ mod tests {
             max_retries: 3,
             model: Model::Claude35Sonnet20241022,
             max_tokens: 1000,
+            auto_max_tokens: false,
+            default_system: None,
+            beta_features: Vec::new(),
+            stream_include_usage: true,
+            validate_requests: false,
+            model_fallback: Vec::new(),
+            precheck_tokens: false,
+            error_on_empty_response: false,
+            retry_streams: false,
         };
 
         let cloned = config.clone();
@@ -305,6 +322,15 @@ mod tests {
             max_retries: 3,
             model: Model::Claude35Sonnet20241022,
             max_tokens: 4096,
+            auto_max_tokens: false,
+            default_system: None,
+            beta_features: Vec::new(),
+            stream_include_usage: true,
+            validate_requests: false,
+            model_fallback: Vec::new(),
+            precheck_tokens: false,
+            error_on_empty_response: false,
+            retry_streams: false,
         };
 
         let debug_str = format!("{:?}", config);
@@ -374,12 +400,12 @@ mod tests {
         let client = Client::builder()
             .api_key("sk-ant-api03-test-key")
             .model(Model::Claude3Opus20240229)
-            .max_tokens(8000)
+            .max_tokens(4000)
             .build()
             .unwrap();
 
         assert_eq!(client.default_model(), Model::Claude3Opus20240229);
-        assert_eq!(client.default_max_tokens(), 8000);
+        assert_eq!(client.default_max_tokens(), 4000);
     }
 
     #[test]
@@ -456,4 +482,58 @@ mod tests {
             _ => panic!("Expected Config error"),
         }
     }
+
+    #[test]
+    fn test_client_builder_api_key_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("anthropic_api_key");
+        std::fs::write(&path, "  sk-ant-api03-file-key\n").unwrap();
+
+        let client = ClientBuilder::new().api_key_file(&path).build().unwrap();
+        assert_eq!(client.inner.config.api_key, "sk-ant-api03-file-key");
+    }
+
+    #[test]
+    fn test_client_builder_api_key_file_errors() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let missing = ClientBuilder::new()
+            .api_key_file(dir.path().join("missing"))
+            .build();
+        match missing.unwrap_err() {
+            Error::Config(msg) => assert!(msg.contains("Failed to read API key file")),
+            other => panic!("Expected Config error, got {:?}", other),
+        }
+
+        let empty_path = dir.path().join("empty");
+        std::fs::write(&empty_path, " \n").unwrap();
+        let empty = ClientBuilder::new().api_key_file(&empty_path).build();
+        match empty.unwrap_err() {
+            Error::Config(msg) => assert!(msg.contains("is empty")),
+            other => panic!("Expected Config error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_client_builder_api_key_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("anthropic_api_key");
+        std::fs::write(&path, "sk-ant-api03-file-key").unwrap();
+        std::env::set_var("ANTHROPIC_API_KEY", "sk-ant-api03-env-key");
+
+        // Explicit key wins over the file, and the file is not read at all
+        let explicit = ClientBuilder::new()
+            .api_key("sk-ant-api03-explicit-key")
+            .api_key_file(dir.path().join("missing"))
+            .build()
+            .unwrap();
+        assert_eq!(explicit.inner.config.api_key, "sk-ant-api03-explicit-key");
+
+        // File wins over the environment
+        let from_file = ClientBuilder::new().api_key_file(&path).build().unwrap();
+        assert_eq!(from_file.inner.config.api_key, "sk-ant-api03-file-key");
+
+        // Clean up
+        std::env::remove_var("ANTHROPIC_API_KEY");
+    }
 }
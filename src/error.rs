@@ -3,8 +3,13 @@
 use std::time::Duration;
 use thiserror::Error;
 
+use crate::rate_limit::RateLimitInfo;
+
 /// Main error type for the Anthropic SDK
+///
+/// New variants may be added in minor releases, so matches need a wildcard arm.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum Error {
     /// HTTP request failed
     #[error("HTTP request failed: {0}")]
@@ -28,9 +33,12 @@ pub enum Error {
         Some(duration) => format!(", retry after {:?}", duration),
         None => String::new(),
     })]
+    #[non_exhaustive]
     RateLimit {
         retry_after: Option<Duration>,
         request_id: Option<String>,
+        /// Rate-limit headers from the rejected response
+        info: Option<RateLimitInfo>,
     },
 
     /// JSON serialization/deserialization error
@@ -64,6 +72,14 @@ pub enum Error {
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
 
+    /// The prompt does not fit in the model's context window
+    #[error("Context length exceeded: {message}")]
+    ContextLengthExceeded {
+        /// Prompt size in tokens, when the API reports it
+        tokens: Option<u32>,
+        message: String,
+    },
+
     /// Server returned invalid response format
     #[error("Invalid response format: {0}")]
     InvalidResponse(String),
@@ -81,6 +97,23 @@ pub enum Error {
     Content(String),
 }
 
+/// Extract the prompt size from a message of the form "... N tokens > M maximum"
+///
+/// Returns `None` unless the message contains that pattern.
+fn prompt_tokens(message: &str) -> Option<u32> {
+    message.match_indices(" tokens > ").find_map(|(index, _)| {
+        let before = &message[..index];
+        let start = before
+            .rfind(|c: char| !c.is_ascii_digit())
+            .map_or(0, |i| i + 1);
+        let limit = &message[index + " tokens > ".len()..];
+        let limit_digits = limit.bytes().take_while(u8::is_ascii_digit).count();
+        (limit_digits > 0)
+            .then(|| before[start..].parse().ok())
+            .flatten()
+    })
+}
+
 /// Error categories for easier error handling
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ErrorCategory {
@@ -102,6 +135,19 @@ pub enum ErrorCategory {
     Stream,
 }
 
+/// Structured, end-user appropriate guidance for handling an [`Error`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorSuggestion {
+    /// Short description of what went wrong
+    pub summary: String,
+    /// What the caller can do about it
+    pub actionable: String,
+    /// Whether retrying the same request may succeed
+    pub retryable: bool,
+    /// Delay requested by the server before retrying, if any
+    pub retry_after: Option<Duration>,
+}
+
 impl Error {
     /// Create a new API error
     pub fn api(
@@ -123,6 +169,7 @@ impl Error {
         Self::RateLimit {
             retry_after,
             request_id,
+            info: None,
         }
     }
 
@@ -155,6 +202,7 @@ impl Error {
             Error::Authentication(_)
             | Error::Config(_)
             | Error::InvalidRequest(_)
+            | Error::ContextLengthExceeded { .. }
             | Error::Serialization(_)
             | Error::Url(_)
             | Error::InvalidResponse(_)
@@ -174,6 +222,47 @@ impl Error {
         }
     }
 
+    /// Attach a request ID to errors that carry one but do not have it yet
+    ///
+    /// `Stream` errors have no request ID field, so the ID is appended to their message.
+    pub(crate) fn with_request_id(self, id: Option<&str>) -> Self {
+        let Some(id) = id else {
+            return self;
+        };
+
+        match self {
+            Error::Api {
+                status,
+                message,
+                error_type,
+                request_id: None,
+            } => Error::Api {
+                status,
+                message,
+                error_type,
+                request_id: Some(id.to_string()),
+            },
+            Error::RateLimit {
+                retry_after,
+                request_id: None,
+                info,
+            } => Error::RateLimit {
+                retry_after,
+                request_id: Some(id.to_string()),
+                info,
+            },
+            Error::Timeout {
+                timeout,
+                request_id: None,
+            } => Error::Timeout {
+                timeout,
+                request_id: Some(id.to_string()),
+            },
+            Error::Stream(message) => Error::Stream(format!("{} (request_id: {})", message, id)),
+            other => other,
+        }
+    }
+
     /// Get the error category
     pub fn category(&self) -> ErrorCategory {
         match self {
@@ -181,7 +270,9 @@ impl Error {
             Error::Authentication(_) => ErrorCategory::Auth,
             Error::RateLimit { .. } => ErrorCategory::RateLimit,
             Error::Config(_) => ErrorCategory::Config,
-            Error::InvalidRequest(_) | Error::Url(_) => ErrorCategory::Request,
+            Error::InvalidRequest(_) | Error::ContextLengthExceeded { .. } | Error::Url(_) => {
+                ErrorCategory::Request
+            }
             Error::Api { status, .. } => {
                 if status.is_client_error() {
                     if *status == reqwest::StatusCode::UNAUTHORIZED
@@ -213,6 +304,7 @@ impl Error {
             Error::Authentication(_)
             | Error::Config(_)
             | Error::InvalidRequest(_)
+            | Error::ContextLengthExceeded { .. }
             | Error::Url(_) => true,
             _ => false,
         }
@@ -241,6 +333,57 @@ impl Error {
         matches!(self.category(), ErrorCategory::RateLimit)
     }
 
+    /// Check if the prompt exceeded the model's context window
+    pub fn is_context_length_error(&self) -> bool {
+        matches!(self, Error::ContextLengthExceeded { .. })
+    }
+
+    /// Build the error for a 400 response, detecting prompts that are too long
+    pub(crate) fn bad_request(message: String, error_type: Option<&str>) -> Self {
+        let lowercase = message.to_lowercase();
+        let too_long = lowercase.contains("prompt is too long")
+            || lowercase.contains("context window")
+            || lowercase.contains("context length");
+
+        if error_type.is_none_or(|t| t == "invalid_request_error") && too_long {
+            let tokens = prompt_tokens(&message);
+            Error::ContextLengthExceeded { tokens, message }
+        } else {
+            Error::InvalidRequest(message)
+        }
+    }
+
+    /// Check if the error reports an overloaded API (HTTP 529 or `overloaded_error`)
+    pub fn is_overloaded(&self) -> bool {
+        match self {
+            Error::Api {
+                status, error_type, ..
+            } => status.as_u16() == 529 || error_type.as_deref() == Some("overloaded_error"),
+            _ => false,
+        }
+    }
+
+    /// Get the delay requested by the server before retrying, if any
+    ///
+    /// Unlike [`retry_delay`](Self::retry_delay), this never falls back to a heuristic:
+    /// it only returns a delay the API actually specified. Rate limit errors take it from
+    /// the `Retry-After` header, in seconds or as an HTTP date, falling back to a
+    /// `retry_after` field in the error body; every other error returns `None`.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::RateLimit { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Get the rate-limit headers reported alongside a rate limit error
+    pub fn rate_limit_info(&self) -> Option<&RateLimitInfo> {
+        match self {
+            Error::RateLimit { info, .. } => info.as_ref(),
+            _ => None,
+        }
+    }
+
     /// Get retry delay suggestion for retryable errors
     pub fn retry_delay(&self) -> Option<Duration> {
         match self {
@@ -255,6 +398,83 @@ impl Error {
         }
     }
 
+    /// Get structured guidance suitable for surfacing to end users
+    ///
+    /// Unlike [`user_message`](Self::user_message), the text does not refer to test
+    /// execution and is split into a summary and a suggested action.
+    pub fn suggestion(&self) -> ErrorSuggestion {
+        let (summary, actionable) = match self {
+            Error::Authentication(_) => (
+                "Authentication failed",
+                "Check that the API key is valid and has not been revoked.",
+            ),
+            Error::Api { status, .. } if *status == reqwest::StatusCode::UNAUTHORIZED => (
+                "Authentication failed",
+                "Check that the API key is valid and has not been revoked.",
+            ),
+            Error::Api { status, .. } if *status == reqwest::StatusCode::FORBIDDEN => (
+                "Permission denied",
+                "Check that the API key has access to the requested resource.",
+            ),
+            Error::RateLimit { .. } => (
+                "Rate limit exceeded",
+                "Wait before retrying, or reduce the request rate.",
+            ),
+            _ if self.is_overloaded() => (
+                "The API is temporarily overloaded",
+                "Retry later with exponential backoff.",
+            ),
+            Error::Api { status, .. } if status.is_server_error() => (
+                "The API returned a server error",
+                "Retry the request; contact support with the request ID if it persists.",
+            ),
+            Error::ContextLengthExceeded { .. } => (
+                "The prompt is too long for the model's context window",
+                "Shorten the conversation or its attachments, or lower max_tokens.",
+            ),
+            Error::Api { .. } | Error::InvalidRequest(_) => (
+                "The request was rejected",
+                "Check the request parameters against the API documentation.",
+            ),
+            Error::Timeout { .. } => (
+                "The request timed out",
+                "Retry the request, or increase the client timeout for long generations.",
+            ),
+            Error::Http(_) | Error::Network(_) => (
+                "Could not reach the API",
+                "Check network connectivity and the configured base URL.",
+            ),
+            Error::Config(_) | Error::Url(_) => (
+                "The client is misconfigured",
+                "Review the client configuration, such as the API key, base URL, and timeouts.",
+            ),
+            Error::Model(_) => (
+                "The requested model is unavailable",
+                "Choose a different model or check the model name.",
+            ),
+            Error::Stream(_) => ("The response stream was interrupted", "Retry the request."),
+            Error::Serialization(_) | Error::InvalidResponse(_) => (
+                "The response could not be processed",
+                "Retry the request; report the request ID if it keeps failing.",
+            ),
+            Error::Tool(_) => (
+                "A tool definition or call was invalid",
+                "Check the tool's name, input schema, and the input passed to it.",
+            ),
+            Error::Content(_) => (
+                "Message content could not be processed",
+                "Check the format and encoding of images and documents.",
+            ),
+        };
+
+        ErrorSuggestion {
+            summary: summary.to_string(),
+            actionable: actionable.to_string(),
+            retryable: self.is_retryable(),
+            retry_after: self.retry_after(),
+        }
+    }
+
     /// Get a user-friendly error message with context about test execution
     pub fn user_message(&self) -> String {
         match self {
@@ -336,6 +556,7 @@ impl Error {
             Error::RateLimit {
                 retry_after,
                 request_id,
+                ..
             } => {
                 let id_info = request_id
                     .as_ref()
@@ -379,6 +600,12 @@ impl Error {
                     msg
                 )
             }
+            Error::ContextLengthExceeded { message, .. } => {
+                format!(
+                    "Context length exceeded: {}. This is a unit test error - reduce the prompt size or max_tokens.",
+                    message
+                )
+            }
             Error::InvalidResponse(msg) => {
                 format!(
                     "Invalid response format: {}. This could be a unit test error (mock response format) or integration test error (unexpected API response).",
@@ -497,6 +724,7 @@ mod tests {
             Error::RateLimit {
                 retry_after: Some(duration),
                 request_id,
+                info: None,
             } => {
                 assert_eq!(duration, Duration::from_secs(60));
                 assert_eq!(request_id, Some("req_456".to_string()));
@@ -649,6 +877,41 @@ mod tests {
         assert!(!network_error.is_server_error());
     }
 
+    #[test]
+    fn test_bad_request_detects_context_length() {
+        let error = Error::bad_request(
+            "prompt is too long: 208310 tokens > 200000 maximum".to_string(),
+            Some("invalid_request_error"),
+        );
+        assert!(error.is_context_length_error());
+        assert!(matches!(
+            error,
+            Error::ContextLengthExceeded {
+                tokens: Some(208310),
+                ..
+            }
+        ));
+        assert!(error.is_client_error());
+        assert!(!error.is_retryable());
+        assert_eq!(error.category(), ErrorCategory::Request);
+
+        let error = Error::bad_request(
+            "Input exceeds the context window".to_string(),
+            Some("invalid_request_error"),
+        );
+        assert!(matches!(
+            error,
+            Error::ContextLengthExceeded { tokens: None, .. }
+        ));
+
+        let error = Error::bad_request(
+            "messages: field required".to_string(),
+            Some("invalid_request_error"),
+        );
+        assert!(!error.is_context_length_error());
+        assert!(matches!(error, Error::InvalidRequest(_)));
+    }
+
     #[test]
     fn test_specific_error_type_detection() {
         assert!(Error::Network("test".to_string()).is_network_error());
@@ -685,6 +948,33 @@ mod tests {
         assert_eq!(auth_error.retry_delay(), None);
     }
 
+    #[test]
+    fn test_with_request_id() {
+        let api = Error::api(StatusCode::INTERNAL_SERVER_ERROR, "Error", None, None)
+            .with_request_id(Some("req_1"));
+        assert_eq!(api.request_id(), Some("req_1"));
+
+        // An existing request ID is kept
+        let rate_limit =
+            Error::rate_limit(None, Some("req_0".to_string())).with_request_id(Some("req_1"));
+        assert_eq!(rate_limit.request_id(), Some("req_0"));
+
+        let stream =
+            Error::Stream("Stream error: Bad input".to_string()).with_request_id(Some("req_1"));
+        assert!(stream.to_string().contains("(request_id: req_1)"));
+    }
+
+    #[test]
+    fn test_retry_after_is_server_specified_only() {
+        let rate_limit = Error::rate_limit(Some(Duration::from_secs(60)), None);
+        assert_eq!(rate_limit.retry_after(), Some(Duration::from_secs(60)));
+        assert_eq!(Error::rate_limit(None, None).retry_after(), None);
+
+        let server_error = Error::api(StatusCode::INTERNAL_SERVER_ERROR, "Error", None, None);
+        assert_eq!(server_error.retry_after(), None);
+        assert_eq!(server_error.retry_delay(), Some(Duration::from_secs(1)));
+    }
+
     #[test]
     fn test_error_display() {
         let api_error = Error::api(
@@ -34,6 +34,7 @@ mod tests {
         let rate_limit_error = Error::RateLimit {
             retry_after: Some(Duration::from_secs(60)),
             request_id: Some("req-456".to_string()),
+            info: None,
         };
         assert_eq!(
             rate_limit_error.to_string(),
@@ -90,6 +91,7 @@ mod tests {
         let rate_limit_error = Error::RateLimit {
             retry_after: Some(Duration::from_secs(60)),
             request_id: Some("req-123".to_string()),
+            info: None,
         };
         assert!(rate_limit_error.is_rate_limit_error());
         assert!(rate_limit_error.is_retryable());
@@ -156,6 +158,7 @@ mod tests {
         let rate_limit_error = Error::RateLimit {
             retry_after: Some(Duration::from_secs(60)),
             request_id: Some("req-456".to_string()),
+            info: None,
         };
         assert_eq!(rate_limit_error.request_id(), Some("req-456"));
 
@@ -311,6 +314,7 @@ mod tests {
         let rate_limit_error = Error::RateLimit {
             retry_after: None,
             request_id: Some("req-123".to_string()),
+            info: None,
         };
 
         assert_eq!(rate_limit_error.to_string(), "Rate limit exceeded");
@@ -319,6 +323,35 @@ mod tests {
         // Note: retry_after method doesn't exist in current implementation
     }
 
+    #[test]
+    fn test_overloaded_detection() {
+        let status_529 = Error::api(
+            reqwest::StatusCode::from_u16(529).unwrap(),
+            "Overloaded".to_string(),
+            None,
+            None,
+        );
+        assert!(status_529.is_overloaded());
+        assert!(status_529.is_retryable());
+
+        let typed = Error::api(
+            reqwest::StatusCode::SERVICE_UNAVAILABLE,
+            "Overloaded".to_string(),
+            Some("overloaded_error".to_string()),
+            None,
+        );
+        assert!(typed.is_overloaded());
+
+        let generic = Error::api(
+            reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            "Internal error".to_string(),
+            Some("api_error".to_string()),
+            None,
+        );
+        assert!(!generic.is_overloaded());
+        assert!(!Error::Network("down".to_string()).is_overloaded());
+    }
+
     #[test]
     fn test_timeout_error_formatting() {
         let timeout_1s = Error::Timeout {
@@ -340,6 +373,29 @@ mod tests {
         assert!(timeout_ms.to_string().contains("500ms") || timeout_ms.to_string().contains("0.5"));
     }
 
+    #[test]
+    fn test_bad_request_context_length_tokens() {
+        let tokens = |message: &str| match Error::bad_request(message.to_string(), None) {
+            Error::ContextLengthExceeded { tokens, .. } => tokens,
+            other => panic!("Expected ContextLengthExceeded, got: {:?}", other),
+        };
+
+        assert_eq!(
+            tokens("prompt is too long: 210000 tokens > 200000 maximum"),
+            Some(210000)
+        );
+        // Earlier numbers in the message are not mistaken for the prompt size
+        assert_eq!(
+            tokens("Request 42 failed: prompt is too long: 210000 tokens > 200000 maximum"),
+            Some(210000)
+        );
+        assert_eq!(
+            tokens("input length and max_tokens 4096 exceed context length"),
+            None
+        );
+        assert_eq!(tokens("prompt is too long"), None);
+    }
+
     #[test]
     fn test_error_send_sync() {
         // Verify that Error implements Send + Sync
@@ -361,4 +417,32 @@ mod tests {
         // Test source chain (should be None for our simple errors)
         assert!(std_error.source().is_none());
     }
+
+    #[test]
+    fn test_error_suggestion_auth() {
+        let suggestion = Error::Authentication("Invalid API key".to_string()).suggestion();
+        assert_eq!(suggestion.summary, "Authentication failed");
+        assert!(suggestion.actionable.contains("API key"));
+        assert!(!suggestion.retryable);
+        assert_eq!(suggestion.retry_after, None);
+    }
+
+    #[test]
+    fn test_error_suggestion_rate_limit() {
+        let suggestion =
+            Error::rate_limit(Some(Duration::from_secs(30)), Some("req_1".to_string()))
+                .suggestion();
+        assert_eq!(suggestion.summary, "Rate limit exceeded");
+        assert!(suggestion.retryable);
+        assert_eq!(suggestion.retry_after, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_error_suggestion_config() {
+        let suggestion = Error::Config("Timeout must be positive".to_string()).suggestion();
+        assert_eq!(suggestion.summary, "The client is misconfigured");
+        assert!(!suggestion.retryable);
+        assert_eq!(suggestion.retry_after, None);
+        assert!(!suggestion.actionable.contains("test"));
+    }
 }
@@ -22,13 +22,19 @@
 //! }
 //! ```
 
+pub mod cache;
 pub mod client;
 pub mod config;
 pub mod error;
 pub mod multimodal;
+pub mod rate_limit;
 pub mod streaming;
+pub mod template;
+#[cfg(feature = "token-estimator")]
+pub mod token_estimator;
 pub mod tools;
 pub mod types;
+pub mod validation;
 
 // Mock infrastructure for unit tests
 #[cfg(test)]
@@ -52,21 +58,32 @@ mod client_test;
 mod streaming_test;
 
 // Re-export commonly used types for convenience
-pub use client::{Client, LoggingInterceptor, RequestInterceptor, RequestMiddleware, RetryConfig};
+pub use cache::ResponseCache;
+pub use client::{
+    ChatOptions, Client, IdGenerator, LoggingInterceptor, RequestInterceptor, RequestMiddleware,
+    ResponseMeta, RetryConfig, SequentialIdGenerator, Sleeper, TokioSleeper, UuidGenerator,
+};
 pub use config::{ClientBuilder, Config};
-pub use error::Error;
+pub use error::{Error, ErrorSuggestion};
 pub use streaming::{
-    ContentDelta, MessageAccumulator, MessageDelta, MessageStream, PartialMessage, StreamEvent,
+    BroadcastEvent, ContentDelta, MessageAccumulator, MessageDelta, MessageStream, PartialMessage,
+    StreamBroadcast, StreamEvent, StreamOptions, StreamTransport,
 };
-pub use tools::{Tool, ToolBuilder};
+pub use template::PromptTemplate;
+#[cfg(feature = "token-estimator")]
+pub use token_estimator::TokenEstimator;
+pub use tools::{ParamType, Tool, ToolBuilder};
 pub use types::{
-    ChatRequest, ChatRequestBuilder, ContentBlock, CountTokensRequest, DocumentMediaType,
-    DocumentSource, ImageMediaType, ImageSource, Message, MessageParam, Model, Role, StopReason,
-    SystemMessage, TokenCount, Usage,
+    CacheControl, ChatRequest, ChatRequestBuilder, ContentBlock, Conversation, CountTokensRequest,
+    CountTokensRequestBuilder, DocumentMediaType, DocumentSource, FileMetadata, ImageMediaType,
+    ImageSource, Message, MessageParam, Model, ModelFamily, OutputFormat, RequestMetadata, Role,
+    Sampling, StopReason, SystemMessage, ThinkingConfig, TokenCount, ToolCall, ToolChoice, Usage,
+    UsageDelta,
 };
 
 // Re-export multimodal utilities for convenience
 pub use multimodal::{validate_url, Base64Utils, DocumentUtils, ImageUtils, MimeUtils};
+pub use rate_limit::RateLimitInfo;
 
 /// Result type alias for the SDK
 pub type Result<T> = std::result::Result<T, Error>;
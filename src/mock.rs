@@ -62,6 +62,21 @@
 //! client.mock(Method::POST, "/v1/messages", error_response);
 //! ```
 //!
+//! ## Record and Replay
+//!
+//! ```rust
+//! use anthropic_rust::mock::{MockHttpClient, MockResponse};
+//! use reqwest::{header::HeaderMap, Method, StatusCode};
+//!
+//! // Capture a response once (or use `MockResponse::record` on a live response)...
+//! let recorded = MockResponse::from_recorded(StatusCode::OK, HeaderMap::new(), r#"{"id": "msg_1"}"#);
+//! let fixture = recorded.to_fixture().unwrap();
+//!
+//! // ...and replay it from the saved fixture in later runs
+//! let client = MockHttpClient::new();
+//! client.mock(Method::POST, "/v1/messages", MockResponse::from_fixture(&fixture).unwrap());
+//! ```
+//!
 //! ## Pre-configured Clients
 //!
 //! ```rust
@@ -112,7 +127,11 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use reqwest::{header::HeaderMap, Method, StatusCode, Url};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Method, StatusCode, Url,
+};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::{error::Error, Result};
@@ -238,7 +257,6 @@ impl MockResponse {
 
     /// Add a header to the response
     pub fn with_header(mut self, name: &str, value: &str) -> Self {
-        use reqwest::header::HeaderName;
         self.headers.insert(
             HeaderName::from_bytes(name.as_bytes()).expect("Invalid header name"),
             value.parse().expect("Invalid header value"),
@@ -256,6 +274,86 @@ impl MockResponse {
         self.delay = Some(delay);
         self
     }
+
+    /// Create a mock response from a captured status, headers, and raw body
+    ///
+    /// Bodies that are not valid JSON are kept as a JSON string.
+    pub fn from_recorded(status: StatusCode, headers: HeaderMap, body: &str) -> Self {
+        let body = serde_json::from_str(body).unwrap_or_else(|_| Value::String(body.to_string()));
+        Self {
+            status,
+            headers,
+            body,
+            delay: None,
+        }
+    }
+
+    /// Capture a real HTTP response for later replay
+    ///
+    /// Fails with `Error::InvalidResponse` if the body is not valid UTF-8, since it could
+    /// not be replayed faithfully.
+    pub async fn record(response: reqwest::Response) -> Result<Self> {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await.map_err(Error::Http)?;
+        let body = std::str::from_utf8(&body).map_err(|e| {
+            Error::InvalidResponse(format!("Recorded response body is not valid UTF-8: {}", e))
+        })?;
+        Ok(Self::from_recorded(status, headers, body))
+    }
+
+    /// Serialize the response as a JSON fixture suitable for saving to disk
+    ///
+    /// Repeated headers are kept in order. Fails with `Error::InvalidResponse` if a
+    /// header value is not visible ASCII, since it could not be stored losslessly.
+    pub fn to_fixture(&self) -> Result<String> {
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let value = value.to_str().map_err(|_| {
+                    Error::InvalidResponse(format!(
+                        "Header '{}' has a non-ASCII value and cannot be stored in a fixture",
+                        name
+                    ))
+                })?;
+                Ok((name.to_string(), value.to_string()))
+            })
+            .collect::<Result<_>>()?;
+        let fixture = RecordedResponse {
+            status: self.status.as_u16(),
+            headers,
+            body: self.body.clone(),
+        };
+        Ok(serde_json::to_string_pretty(&fixture)?)
+    }
+
+    /// Reconstruct a response from a fixture produced by [`MockResponse::to_fixture`]
+    pub fn from_fixture(fixture: &str) -> Result<Self> {
+        let recorded: RecordedResponse = serde_json::from_str(fixture)?;
+        let status = StatusCode::from_u16(recorded.status)
+            .map_err(|e| Error::InvalidResponse(format!("Invalid status in fixture: {}", e)))?;
+
+        let mut response = Self::new(status, recorded.body);
+        for (name, value) in &recorded.headers {
+            let invalid = |e: &dyn std::fmt::Display| {
+                Error::InvalidResponse(format!("Invalid header '{}' in fixture: {}", name, e))
+            };
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(&e))?;
+            let value = HeaderValue::from_str(value).map_err(|e| invalid(&e))?;
+            response.headers.append(name, value);
+        }
+        Ok(response)
+    }
+}
+
+/// On-disk form of a recorded response
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    /// Header name and value pairs, in response order
+    headers: Vec<(String, String)>,
+    body: Value,
 }
 
 /// Mock HTTP client that can be configured to return specific responses
@@ -684,6 +782,17 @@ impl TestConfig {
         }
     }
 
+    /// Apply this configuration's seed to a chat request when running deterministically
+    pub fn seed_request(
+        &self,
+        builder: crate::types::ChatRequestBuilder,
+    ) -> crate::types::ChatRequestBuilder {
+        match self.random_seed {
+            Some(seed) if self.deterministic => builder.seed(seed),
+            _ => builder,
+        }
+    }
+
     /// Get timeout with jitter for non-deterministic configs
     pub fn effective_timeout(&self) -> Duration {
         if self.deterministic {
@@ -1170,6 +1279,46 @@ mod tests {
     use reqwest::Method;
     use serde_json::json;
 
+    #[tokio::test]
+    async fn test_recorded_response_replay() {
+        let mut headers = HeaderMap::new();
+        headers.insert("request-id", "req_recorded".parse().unwrap());
+        headers.append("set-cookie", "a=1".parse().unwrap());
+        headers.append("set-cookie", "b=2".parse().unwrap());
+        let body = json!({
+            "input_tokens": 42
+        });
+        let recorded = MockResponse::from_recorded(StatusCode::OK, headers, &body.to_string());
+
+        let fixture = recorded.to_fixture().unwrap();
+        let replayed = MockResponse::from_fixture(&fixture).unwrap();
+        assert_eq!(replayed.status, recorded.status);
+        assert_eq!(replayed.headers, recorded.headers);
+        assert_eq!(replayed.body, recorded.body);
+
+        let client = MockHttpClient::new();
+        client.mock(Method::POST, "/v1/messages/count_tokens", replayed);
+        let url = Url::parse("https://api.anthropic.com/v1/messages/count_tokens").unwrap();
+        let count: crate::types::TokenCount = client
+            .execute_request(Method::POST, &url, None, None)
+            .await
+            .unwrap();
+        assert_eq!(count.input_tokens, 42);
+
+        // Non-JSON bodies survive as strings
+        let text = MockResponse::from_recorded(StatusCode::BAD_GATEWAY, HeaderMap::new(), "oops");
+        assert_eq!(text.body, Value::String("oops".to_string()));
+
+        // Values that cannot be stored losslessly are rejected rather than dropped
+        let mut opaque = HeaderMap::new();
+        opaque.insert("x-opaque", HeaderValue::from_bytes(b"caf\xe9").unwrap());
+        let opaque = MockResponse::from_recorded(StatusCode::OK, opaque, "{}");
+        assert!(matches!(
+            opaque.to_fixture(),
+            Err(Error::InvalidResponse(_))
+        ));
+    }
+
     #[test]
     fn test_mock_response_builders() {
         // Test successful response
@@ -1434,6 +1583,19 @@ mod tests {
         assert!(effective_timeout >= base_timeout);
     }
 
+    #[test]
+    fn test_test_config_seed_request() {
+        let builder = crate::types::ChatRequestBuilder::new;
+
+        let seeded = TestConfig::for_miri().seed_request(builder()).build();
+        assert_eq!(seeded.seed, Some(42));
+
+        let unseeded = TestConfig::for_integration()
+            .seed_request(builder())
+            .build();
+        assert_eq!(unseeded.seed, None);
+    }
+
     #[test]
     fn test_test_client_builder_from_config() {
         let mock_config = TestConfig::for_miri();
@@ -11,6 +11,9 @@ use crate::{
     Result,
 };
 
+/// Error message for SVG images, which the API does not accept
+const SVG_NOT_SUPPORTED: &str = "SVG is not supported; rasterize to PNG";
+
 /// Utilities for handling image content
 pub struct ImageUtils;
 
@@ -42,6 +45,7 @@ impl ImageUtils {
                 e
             ))
         })?;
+        MimeUtils::reject_svg(&data)?;
 
         // Validate file size (max 20MB for images)
         const MAX_IMAGE_SIZE: usize = 20 * 1024 * 1024;
@@ -59,6 +63,8 @@ impl ImageUtils {
 
     /// Create an image content block from raw bytes
     pub fn from_bytes(data: &[u8], media_type: ImageMediaType) -> Result<ContentBlock> {
+        MimeUtils::reject_svg(data)?;
+
         // Validate file size
         const MAX_IMAGE_SIZE: usize = 20 * 1024 * 1024;
         if data.len() > MAX_IMAGE_SIZE {
@@ -78,6 +84,7 @@ impl ImageUtils {
         let validated_url = validate_url(url.as_ref())?;
         Ok(ContentBlock::Image {
             source: ImageSource::Url { url: validated_url },
+            cache_control: None,
         })
     }
 
@@ -99,6 +106,7 @@ impl ImageUtils {
             "png" => Ok(ImageMediaType::Png),
             "gif" => Ok(ImageMediaType::Gif),
             "webp" => Ok(ImageMediaType::WebP),
+            "svg" | "svgz" => Err(Error::Content(SVG_NOT_SUPPORTED.to_string())),
             _ => Err(Error::Config(format!(
                 "Unsupported image format '{}' for file: {}",
                 extension,
@@ -118,6 +126,9 @@ impl ImageUtils {
             (mime::IMAGE, mime::PNG) => Ok(ImageMediaType::Png),
             (mime::IMAGE, mime::GIF) => Ok(ImageMediaType::Gif),
             (mime::IMAGE, subtype) if subtype == "webp" => Ok(ImageMediaType::WebP),
+            (mime::IMAGE, subtype) if subtype == "svg" => {
+                Err(Error::Content(SVG_NOT_SUPPORTED.to_string()))
+            }
             _ => Err(Error::Config(format!(
                 "Unsupported image MIME type: {}",
                 mime_str
@@ -162,14 +173,28 @@ impl ImageUtils {
     }
 }
 
+/// Maximum PDF size accepted by the API
+const MAX_PDF_SIZE: usize = 32 * 1024 * 1024;
+
+/// Maximum number of PDF pages accepted by the API
+const MAX_PDF_PAGES: usize = 100;
+
 /// Utilities for handling document content
 pub struct DocumentUtils;
 
 impl DocumentUtils {
     /// Create a document content block from a file path
     pub async fn from_file(path: impl AsRef<Path>) -> Result<ContentBlock> {
-        let path = path.as_ref();
+        Self::load_file(path.as_ref(), false).await
+    }
 
+    /// Create a document content block from a file path, checking PDFs against the
+    /// API's document limits with [`DocumentUtils::validate_pdf`]
+    pub async fn from_file_validated(path: impl AsRef<Path>) -> Result<ContentBlock> {
+        Self::load_file(path.as_ref(), true).await
+    }
+
+    async fn load_file(path: &Path, validate_pdf: bool) -> Result<ContentBlock> {
         // Validate file exists and is readable
         if !path.exists() {
             return Err(Error::Config(format!(
@@ -206,6 +231,9 @@ impl DocumentUtils {
 
         // Validate document format
         Self::validate_document_format(&data, &media_type)?;
+        if validate_pdf && media_type == DocumentMediaType::Pdf {
+            Self::validate_pdf(&data)?;
+        }
 
         let encoded = general_purpose::STANDARD.encode(&data);
         Ok(ContentBlock::Document {
@@ -213,11 +241,29 @@ impl DocumentUtils {
                 media_type,
                 data: encoded,
             },
+            cache_control: None,
         })
     }
 
     /// Create a document content block from raw bytes
     pub fn from_bytes(data: &[u8], media_type: DocumentMediaType) -> Result<ContentBlock> {
+        Self::encode_bytes(data, media_type, false)
+    }
+
+    /// Create a document content block from raw bytes, checking PDFs against the API's
+    /// document limits with [`DocumentUtils::validate_pdf`]
+    pub fn from_bytes_validated(
+        data: &[u8],
+        media_type: DocumentMediaType,
+    ) -> Result<ContentBlock> {
+        Self::encode_bytes(data, media_type, true)
+    }
+
+    fn encode_bytes(
+        data: &[u8],
+        media_type: DocumentMediaType,
+        validate_pdf: bool,
+    ) -> Result<ContentBlock> {
         // Validate file size
         const MAX_DOCUMENT_SIZE: usize = 32 * 1024 * 1024;
         if data.len() > MAX_DOCUMENT_SIZE {
@@ -230,6 +276,9 @@ impl DocumentUtils {
 
         // Validate document format
         Self::validate_document_format(data, &media_type)?;
+        if validate_pdf && media_type == DocumentMediaType::Pdf {
+            Self::validate_pdf(data)?;
+        }
 
         let encoded = general_purpose::STANDARD.encode(data);
         Ok(ContentBlock::Document {
@@ -237,6 +286,7 @@ impl DocumentUtils {
                 media_type,
                 data: encoded,
             },
+            cache_control: None,
         })
     }
 
@@ -245,6 +295,7 @@ impl DocumentUtils {
         let validated_url = validate_url(url.as_ref())?;
         Ok(ContentBlock::Document {
             source: DocumentSource::Url { url: validated_url },
+            cache_control: None,
         })
     }
 
@@ -288,6 +339,66 @@ impl DocumentUtils {
         }
     }
 
+    /// Validate a PDF against the API's document limits
+    ///
+    /// Checks the size (at most 32MB) and the page count (at most 100 pages), returning
+    /// `Error::Content` naming the exceeded limit. Pages are counted by scanning for
+    /// `/Type /Page` objects; PDFs that keep their page objects in compressed object
+    /// streams cannot be counted this way and only have their size checked.
+    pub fn validate_pdf(bytes: &[u8]) -> Result<()> {
+        if !bytes.starts_with(b"%PDF") {
+            return Err(Error::Content(
+                "Document is not a PDF (missing %PDF header)".to_string(),
+            ));
+        }
+
+        if bytes.len() > MAX_PDF_SIZE {
+            return Err(Error::Content(format!(
+                "PDF exceeds the size limit: {} bytes (max: {} bytes)",
+                bytes.len(),
+                MAX_PDF_SIZE
+            )));
+        }
+
+        let pages = Self::pdf_page_count(bytes);
+        if pages > MAX_PDF_PAGES {
+            return Err(Error::Content(format!(
+                "PDF exceeds the page limit: {} pages (max: {} pages)",
+                pages, MAX_PDF_PAGES
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Count `/Type /Page` objects, skipping the `/Type /Pages` tree nodes
+    fn pdf_page_count(bytes: &[u8]) -> usize {
+        const TYPE_KEY: &[u8] = b"/Type";
+
+        let mut count = 0;
+        let mut position = 0;
+        while let Some(offset) = bytes[position..]
+            .windows(TYPE_KEY.len())
+            .position(|window| window == TYPE_KEY)
+        {
+            position += offset + TYPE_KEY.len();
+            let rest = &bytes[position..];
+            let value_start = rest
+                .iter()
+                .position(|byte| !byte.is_ascii_whitespace())
+                .unwrap_or(rest.len());
+            let value = &rest[value_start..];
+            if value.starts_with(b"/Page")
+                && !value
+                    .get(b"/Page".len())
+                    .is_some_and(|byte| byte.is_ascii_alphanumeric())
+            {
+                count += 1;
+            }
+        }
+        count
+    }
+
     /// Validate document data format by checking magic bytes
     pub fn validate_document_format(data: &[u8], expected_type: &DocumentMediaType) -> Result<()> {
         if data.is_empty() {
@@ -409,6 +520,24 @@ impl MimeUtils {
     pub fn is_supported_document_mime(mime_str: &str) -> bool {
         DocumentUtils::detect_media_type_from_mime(mime_str).is_ok()
     }
+
+    /// Check whether data looks like an SVG document
+    pub fn is_svg(data: &[u8]) -> bool {
+        let head = String::from_utf8_lossy(&data[..data.len().min(1024)]).to_ascii_lowercase();
+        let head = head.trim_start_matches('\u{feff}').trim_start();
+
+        head.starts_with("<svg")
+            || ((head.starts_with("<?xml") || head.starts_with("<!doctype svg"))
+                && head.contains("<svg"))
+    }
+
+    /// Return a descriptive error if data is an SVG document
+    pub fn reject_svg(data: &[u8]) -> Result<()> {
+        if Self::is_svg(data) {
+            return Err(Error::Content(SVG_NOT_SUPPORTED.to_string()));
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -583,6 +712,7 @@ mod tests {
         match content_block {
             ContentBlock::Image {
                 source: ImageSource::Base64 { media_type, data },
+                ..
             } => {
                 assert_eq!(media_type, ImageMediaType::Jpeg);
                 assert!(!data.is_empty());
@@ -591,6 +721,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_image_svg_rejected() {
+        let svg = br#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg"></svg>"#;
+        assert!(MimeUtils::is_svg(svg));
+        assert!(MimeUtils::is_svg(b"  <svg width=\"10\"></svg>"));
+        assert!(!MimeUtils::is_svg(&[0x89, 0x50, 0x4E, 0x47]));
+
+        match ImageUtils::from_bytes(svg, ImageMediaType::Png) {
+            Err(Error::Content(message)) => {
+                assert_eq!(message, "SVG is not supported; rasterize to PNG")
+            }
+            other => panic!("Expected SVG content error, got {:?}", other),
+        }
+
+        assert!(matches!(
+            ImageUtils::detect_media_type(Path::new("logo.svg")),
+            Err(Error::Content(_))
+        ));
+        assert!(matches!(
+            ImageUtils::detect_media_type_from_mime("image/svg+xml"),
+            Err(Error::Content(_))
+        ));
+    }
+
     #[test]
     fn test_document_from_bytes() {
         // Create valid PDF data
@@ -600,6 +754,7 @@ mod tests {
         match content_block {
             ContentBlock::Document {
                 source: DocumentSource::Base64 { media_type, data },
+                ..
             } => {
                 assert_eq!(media_type, DocumentMediaType::Pdf);
                 assert!(!data.is_empty());
@@ -608,6 +763,50 @@ mod tests {
         }
     }
 
+    /// Build a minimal PDF with `pages` page objects
+    fn synthetic_pdf(pages: usize) -> Vec<u8> {
+        let mut pdf = b"%PDF-1.4\n1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n".to_vec();
+        pdf.extend_from_slice(
+            format!("2 0 obj\n<< /Type /Pages /Count {} >>\nendobj\n", pages).as_bytes(),
+        );
+        for page in 0..pages {
+            pdf.extend_from_slice(
+                format!(
+                    "{} 0 obj\n<< /Type/Page /Parent 2 0 R >>\nendobj\n",
+                    page + 3
+                )
+                .as_bytes(),
+            );
+        }
+        pdf.extend_from_slice(b"%%EOF");
+        pdf
+    }
+
+    #[test]
+    fn test_validate_pdf_limits() {
+        let small = synthetic_pdf(3);
+        assert_eq!(DocumentUtils::pdf_page_count(&small), 3);
+        assert!(DocumentUtils::validate_pdf(&small).is_ok());
+        assert!(DocumentUtils::from_bytes_validated(&small, DocumentMediaType::Pdf).is_ok());
+
+        let oversized = synthetic_pdf(101);
+        match DocumentUtils::validate_pdf(&oversized) {
+            Err(Error::Content(message)) => assert!(message.contains("page limit")),
+            other => panic!("Expected page limit error, got {:?}", other),
+        }
+        assert!(matches!(
+            DocumentUtils::from_bytes_validated(&oversized, DocumentMediaType::Pdf),
+            Err(Error::Content(_))
+        ));
+        // The plain constructor leaves limits to the API
+        assert!(DocumentUtils::from_bytes(&oversized, DocumentMediaType::Pdf).is_ok());
+
+        assert!(matches!(
+            DocumentUtils::validate_pdf(b"not a pdf"),
+            Err(Error::Content(_))
+        ));
+    }
+
     #[test]
     fn test_image_from_url() {
         let url = "https://example.com/image.jpg";
@@ -616,6 +815,7 @@ mod tests {
         match content_block {
             ContentBlock::Image {
                 source: ImageSource::Url { url: parsed_url },
+                ..
             } => {
                 assert_eq!(parsed_url.as_str(), url);
             }
@@ -631,6 +831,7 @@ mod tests {
         match content_block {
             ContentBlock::Document {
                 source: DocumentSource::Url { url: parsed_url },
+                ..
             } => {
                 assert_eq!(parsed_url.as_str(), url);
             }
@@ -708,6 +909,7 @@ mod tests {
         match content_block {
             ContentBlock::Image {
                 source: ImageSource::Base64 { media_type, .. },
+                ..
             } => {
                 assert_eq!(media_type, ImageMediaType::Png);
             }
@@ -730,6 +932,7 @@ mod tests {
         match doc_content_block {
             ContentBlock::Document {
                 source: DocumentSource::Base64 { media_type, .. },
+                ..
             } => {
                 assert_eq!(media_type, DocumentMediaType::Pdf);
             }
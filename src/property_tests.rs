@@ -49,13 +49,14 @@ mod tests {
 
     prop_compose! {
         fn arb_stop_reason()(
-            reason_idx in 0..4usize
+            reason_idx in 0..5usize
         ) -> StopReason {
             match reason_idx {
                 0 => StopReason::EndTurn,
                 1 => StopReason::MaxTokens,
                 2 => StopReason::StopSequence,
                 3 => StopReason::ToolUse,
+                4 => StopReason::PauseTurn,
                 _ => StopReason::EndTurn,
             }
         }
@@ -99,14 +100,14 @@ mod tests {
             block_type in 0..3usize,
         ) -> ContentBlock {
             match block_type {
-                0 => ContentBlock::Text { text, citations: None },
-                1 => ContentBlock::Image { source: image_source },
+                0 => ContentBlock::Text { text, citations: None, cache_control: None },
+                1 => ContentBlock::Image { source: image_source, cache_control: None },
                 2 => ContentBlock::ToolUse {
                     id: tool_id,
                     name: tool_name,
                     input: serde_json::json!({"test": "value"}),
                 },
-                _ => ContentBlock::Text { text, citations: None },
+                _ => ContentBlock::Text { text, citations: None, cache_control: None },
             }
         }
     }
@@ -127,6 +128,7 @@ mod tests {
             SystemMessage {
                 message_type: "text".to_string(),
                 text,
+                cache_control: None,
             }
         }
     }
@@ -145,6 +147,7 @@ mod tests {
                         "param": {"type": "string"}
                     }
                 }),
+                cache_control: None,
             }
         }
     }
@@ -165,6 +168,7 @@ mod tests {
                 temperature,
                 top_p,
                 stop_sequences,
+                ..Default::default()
             }
         }
     }
@@ -181,6 +185,7 @@ mod tests {
         ) -> Message {
             Message {
                 id,
+                message_type: Some("message".to_string()),
                 role,
                 content,
                 model,
@@ -297,6 +302,7 @@ mod tests {
                 temperature: None,
                 top_p: None,
                 stop_sequences: None,
+                ..Default::default()
             };
 
             let json = serde_json::to_value(&request).unwrap();
@@ -323,6 +329,10 @@ mod tests {
                 ContentBlock::ToolUse { .. } => prop_assert_eq!(type_field, "tool_use"),
                 ContentBlock::ToolResult { .. } => prop_assert_eq!(type_field, "tool_result"),
                 ContentBlock::Document { .. } => prop_assert_eq!(type_field, "document"),
+                ContentBlock::Thinking { .. } => prop_assert_eq!(type_field, "thinking"),
+                ContentBlock::RedactedThinking { .. } => {
+                    prop_assert_eq!(type_field, "redacted_thinking")
+                }
             }
         }
 
@@ -334,6 +344,7 @@ mod tests {
             match source {
                 ImageSource::Base64 { .. } => prop_assert_eq!(type_field, "base64"),
                 ImageSource::Url { .. } => prop_assert_eq!(type_field, "url"),
+                ImageSource::File { .. } => prop_assert_eq!(type_field, "file"),
             }
         }
 
@@ -367,6 +378,7 @@ mod tests {
                 temperature: None,
                 top_p: None,
                 stop_sequences: Some(vec![]), // Empty stop sequences
+                ..Default::default()
             };
 
             let json = serde_json::to_value(&request).unwrap();
@@ -417,6 +429,7 @@ mod tests {
                 temperature: Some(temperature),
                 top_p: Some(top_p),
                 stop_sequences: None,
+                ..Default::default()
             };
 
             // Should be able to serialize any float values
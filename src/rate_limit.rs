@@ -0,0 +1,206 @@
+//! Rate-limit information reported in API response headers
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use reqwest::header::HeaderMap;
+
+/// Parsed `anthropic-ratelimit-*` response headers
+///
+/// Available on successful responses through
+/// [`ResponseMeta::rate_limit`](crate::ResponseMeta::rate_limit) and on rate limit
+/// errors through [`Error::rate_limit_info`](crate::Error::rate_limit_info). Headers that
+/// are missing or malformed are left as `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimitInfo {
+    /// Maximum number of requests allowed in the current window
+    pub requests_limit: Option<u32>,
+    /// Requests remaining before the limit is hit
+    pub requests_remaining: Option<u32>,
+    /// When the request limit is fully replenished
+    pub requests_reset: Option<SystemTime>,
+    /// Maximum number of tokens allowed in the current window
+    pub tokens_limit: Option<u32>,
+    /// Tokens remaining before the limit is hit
+    pub tokens_remaining: Option<u32>,
+    /// When the token limit is fully replenished
+    pub tokens_reset: Option<SystemTime>,
+}
+
+impl RateLimitInfo {
+    /// Parse rate-limit headers, returning `None` when the response carries none
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| {
+            headers
+                .get(format!("anthropic-ratelimit-{}", name))
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+        };
+        let count = |name: &str| header(name).and_then(|value| value.parse().ok());
+        let reset = |name: &str| header(name).and_then(parse_rfc3339);
+
+        let info = Self {
+            requests_limit: count("requests-limit"),
+            requests_remaining: count("requests-remaining"),
+            requests_reset: reset("requests-reset"),
+            tokens_limit: count("tokens-limit"),
+            tokens_remaining: count("tokens-remaining"),
+            tokens_reset: reset("tokens-reset"),
+        };
+        (info != Self::default()).then_some(info)
+    }
+}
+
+/// Parse an RFC 3339 timestamp such as `2024-06-01T12:00:30Z`
+///
+/// Fractional seconds are truncated.
+pub(crate) fn parse_rfc3339(value: &str) -> Option<SystemTime> {
+    let (date, time) = value.split_once(['T', 't', ' '])?;
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+
+    // Split the UTC offset from the time of day
+    let (clock, offset_secs) = match time.strip_suffix(['Z', 'z']) {
+        Some(clock) => (clock, 0),
+        None => {
+            let (clock, offset) = time.split_at(time.rfind(['+', '-'])?);
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let offset = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+            (clock, sign * offset)
+        }
+    };
+
+    let clock = clock.split('.').next()?;
+    let mut clock_parts = clock.splitn(3, ':');
+    let hour: u32 = clock_parts.next()?.parse().ok()?;
+    let minute: u32 = clock_parts.next()?.parse().ok()?;
+    let second: u32 = clock_parts.next()?.parse().ok()?;
+
+    unix_time(year, month, day, hour, minute, second, offset_secs)
+}
+
+/// Parse an HTTP date in the IMF-fixdate format, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+pub(crate) fn parse_http_date(value: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let (_weekday, rest) = value.split_once(", ")?;
+    let mut parts = rest.split_whitespace();
+    let day: u32 = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|name| *name == month)? as u32 + 1;
+    let year: i64 = parts.next()?.parse().ok()?;
+
+    let mut clock_parts = parts.next()?.splitn(3, ':');
+    let hour: u32 = clock_parts.next()?.parse().ok()?;
+    let minute: u32 = clock_parts.next()?.parse().ok()?;
+    let second: u32 = clock_parts.next()?.parse().ok()?;
+
+    if parts.next()? != "GMT" {
+        return None;
+    }
+
+    unix_time(year, month, day, hour, minute, second, 0)
+}
+
+/// Convert a calendar date and time with a UTC offset in seconds to a `SystemTime`
+pub(crate) fn unix_time(
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    offset_secs: i64,
+) -> Option<SystemTime> {
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    let secs = days_from_civil(year, month, day) * 86_400
+        + i64::from(hour * 3600 + minute * 60 + second)
+        - offset_secs;
+    u64::try_from(secs)
+        .ok()
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_parse_rfc3339() {
+        let at = |secs| Some(UNIX_EPOCH + Duration::from_secs(secs));
+
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), at(0));
+        assert_eq!(parse_rfc3339("2024-06-01T12:00:30Z"), at(1_717_243_230));
+        assert_eq!(parse_rfc3339("2024-06-01T12:00:30.750Z"), at(1_717_243_230));
+        assert_eq!(
+            parse_rfc3339("2024-06-01T14:00:30+02:00"),
+            at(1_717_243_230)
+        );
+        assert_eq!(parse_rfc3339("2024-13-01T00:00:00Z"), None);
+        assert_eq!(parse_rfc3339("soon"), None);
+    }
+
+    #[test]
+    fn test_parse_http_date() {
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(784_111_777))
+        );
+        assert_eq!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("120"), None);
+    }
+
+    #[test]
+    fn test_from_headers() {
+        assert_eq!(RateLimitInfo::from_headers(&HeaderMap::new()), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "anthropic-ratelimit-requests-remaining",
+            HeaderValue::from_static("49"),
+        );
+        headers.insert(
+            "anthropic-ratelimit-requests-reset",
+            HeaderValue::from_static("1970-01-01T00:01:00Z"),
+        );
+        headers.insert(
+            "anthropic-ratelimit-tokens-remaining",
+            HeaderValue::from_static("not-a-number"),
+        );
+
+        let info = RateLimitInfo::from_headers(&headers).unwrap();
+        assert_eq!(info.requests_remaining, Some(49));
+        assert_eq!(
+            info.requests_reset,
+            Some(UNIX_EPOCH + Duration::from_secs(60))
+        );
+        assert_eq!(info.tokens_remaining, None);
+        assert_eq!(info.requests_limit, None);
+    }
+}
@@ -1,39 +1,830 @@
 //! Streaming support for the Anthropic API
 
+use std::collections::HashMap;
+use std::io::BufRead;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     error::Error,
-    types::{ContentBlock, Message, Usage},
+    types::{ContentBlock, Message, Model, Usage},
 };
 
+/// Per-call options for streaming chat requests
+///
+/// Unset fields fall back to the client configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamOptions {
+    /// Model override for this request
+    pub model: Option<Model>,
+    /// Timeout for establishing the stream
+    pub timeout: Option<Duration>,
+    /// Maximum time to wait between consecutive stream events
+    pub idle_timeout: Option<Duration>,
+    /// Keep the usage reported in `message_delta` events
+    ///
+    /// The API always reports usage; when false it is stripped client-side.
+    pub include_usage: bool,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            model: None,
+            timeout: None,
+            idle_timeout: None,
+            include_usage: true,
+        }
+    }
+}
+
+/// Callback receiving the cumulative number of response bytes read
+type ByteCallback = Box<dyn FnMut(usize) + Send>;
+
+/// Callback receiving each raw response chunk before it is parsed
+type RawCallback = Box<dyn FnMut(&[u8]) + Send>;
+
+/// Shared slots for byte-level callbacks, filled in after the reader is created
+#[derive(Clone, Default)]
+pub(crate) struct ByteProgress {
+    callback: Arc<Mutex<Option<ByteCallback>>>,
+    raw: Arc<Mutex<Option<RawCallback>>>,
+}
+
+impl ByteProgress {
+    fn set(&self, callback: ByteCallback) {
+        *self.callback.lock().unwrap_or_else(|e| e.into_inner()) = Some(callback);
+    }
+
+    fn set_raw(&self, callback: RawCallback) {
+        *self.raw.lock().unwrap_or_else(|e| e.into_inner()) = Some(callback);
+    }
+
+    /// Pass a raw chunk to the registered tap, if any
+    pub(crate) fn tap(&self, bytes: &[u8]) {
+        if let Some(callback) = self.raw.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            callback(bytes);
+        }
+    }
+
+    /// Report the cumulative byte count to the registered callback, if any
+    pub(crate) fn report(&self, total: usize) {
+        if let Some(callback) = self
+            .callback
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+        {
+            callback(total);
+        }
+    }
+}
+
 /// Stream of message events
 pub struct MessageStream {
     inner: Pin<Box<dyn Stream<Item = Result<StreamEvent, Error>> + Send>>,
+    progress: Option<ByteProgress>,
+    request_id: Option<String>,
+    /// Message state for [`MessageStream::snapshot`], tracked only once enabled
+    state: Option<StreamState>,
 }
 
 impl MessageStream {
     /// Create a new MessageStream from a stream of events
     pub fn new(stream: Pin<Box<dyn Stream<Item = Result<StreamEvent, Error>> + Send>>) -> Self {
-        Self { inner: stream }
+        Self {
+            inner: stream,
+            progress: None,
+            request_id: None,
+            state: None,
+        }
+    }
+
+    /// Create a MessageStream whose byte reader reports to `progress`
+    pub(crate) fn with_progress(
+        stream: Pin<Box<dyn Stream<Item = Result<StreamEvent, Error>> + Send>>,
+        progress: ByteProgress,
+    ) -> Self {
+        Self {
+            inner: stream,
+            progress: Some(progress),
+            request_id: None,
+            state: None,
+        }
+    }
+
+    /// Register a callback invoked with the cumulative byte count as response chunks arrive
+    ///
+    /// Useful for driving a download progress bar on large responses. Streams built from
+    /// already-parsed events with [`MessageStream::new`] have no byte reader, so the
+    /// callback is never invoked for them.
+    pub fn on_bytes(self, callback: impl FnMut(usize) + Send + 'static) -> Self {
+        if let Some(progress) = &self.progress {
+            progress.set(Box::new(callback));
+        }
+        self
+    }
+
+    /// Register a callback invoked with each raw response chunk before it is parsed
+    ///
+    /// Intended for debugging parse issues, for example by appending the SSE feed to a
+    /// file. Chunks arrive in order, exactly as read from the connection. Like
+    /// [`on_bytes`](Self::on_bytes), streams created with [`MessageStream::new`] have no
+    /// byte reader and never invoke the callback.
+    pub fn tap_raw(self, callback: impl FnMut(&[u8]) + Send + 'static) -> Self {
+        if let Some(progress) = &self.progress {
+            progress.set_raw(Box::new(callback));
+        }
+        self
+    }
+
+    /// Copy the raw response bytes to `writer` while still yielding parsed events
+    ///
+    /// Bytes read since the previous event are written and flushed before each event is
+    /// yielded, so the sink never lags the consumer by more than one event. A write
+    /// failure ends the stream with `Error::Stream`. This uses the same raw tap as
+    /// [`tap_raw`](Self::tap_raw) and replaces any callback registered there; streams
+    /// created with [`MessageStream::new`] have no byte reader and persist nothing.
+    pub fn persist_to<W>(self, writer: W) -> Self
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        use tokio::io::AsyncWriteExt;
+
+        let pending = Arc::new(Mutex::new(Vec::new()));
+        let sink = pending.clone();
+        let mut this = self.tap_raw(move |bytes| {
+            sink.lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .extend_from_slice(bytes)
+        });
+
+        let events = std::mem::replace(&mut this.inner, Box::pin(futures::stream::empty()));
+        this.inner = Box::pin(futures::stream::unfold(
+            Some((events, writer, pending)),
+            |state| async move {
+                let (mut events, mut writer, pending) = state?;
+                let event = events.next().await;
+
+                let bytes = std::mem::take(&mut *pending.lock().unwrap_or_else(|e| e.into_inner()));
+                if !bytes.is_empty() {
+                    let written = match writer.write_all(&bytes).await {
+                        Ok(()) => writer.flush().await,
+                        Err(error) => Err(error),
+                    };
+                    if let Err(error) = written {
+                        let error = Error::Stream(format!(
+                            "Failed to persist stream transcript: {}",
+                            error
+                        ));
+                        return Some((Err(error), None));
+                    }
+                }
+
+                event.map(|event| (event, Some((events, writer, pending))))
+            },
+        ));
+        this
+    }
+
+    /// Register a callback invoked with the block index and each `input_json_delta`
+    /// fragment as tool call arguments stream in
+    ///
+    /// Fragments are raw, usually incomplete JSON; concatenating them per index yields
+    /// the tool's full input.
+    pub fn on_tool_input_delta(
+        mut self,
+        mut callback: impl FnMut(usize, &str) + Send + 'static,
+    ) -> Self {
+        self.inner = Box::pin(self.inner.inspect(move |event| {
+            if let Ok(StreamEvent::ContentBlockDelta {
+                index,
+                delta: ContentDelta::InputJsonDelta { partial_json },
+            }) = event
+            {
+                callback(*index, partial_json);
+            }
+        }));
+        self
+    }
+
+    /// End the stream with `Error::Timeout` if no event arrives within `idle_timeout`
+    pub(crate) fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        let progress = self.progress.clone();
+        let state = self.state.take();
+        let request_id = self.request_id.clone();
+        let timeout_request_id = request_id.clone();
+        let timed = futures::stream::unfold(Some(self), move |state| {
+            let request_id = timeout_request_id.clone();
+            async move {
+                let mut stream = state?;
+                match tokio::time::timeout(idle_timeout, stream.next()).await {
+                    Ok(Some(event)) => Some((event, Some(stream))),
+                    Ok(None) => None,
+                    Err(_) => Some((Err(Error::timeout(idle_timeout, request_id)), None)),
+                }
+            }
+        });
+
+        Self {
+            inner: Box::pin(timed),
+            progress,
+            request_id,
+            state,
+        }
+    }
+
+    /// Tag the stream with the request ID of the response it reads, attaching it to
+    /// every error the stream yields
+    pub(crate) fn with_request_id(mut self, request_id: Option<String>) -> Self {
+        let Some(id) = request_id else {
+            return self;
+        };
+
+        let tagged_id = id.clone();
+        self.inner = Box::pin(
+            self.inner
+                .map(move |event| event.map_err(|error| error.with_request_id(Some(&tagged_id)))),
+        );
+        self.request_id = Some(id);
+        self
+    }
+
+    /// Re-issue the stream when an event fails with an error
+    ///
+    /// `restart` receives each error and returns `None` to pass it through, or a future
+    /// re-issuing the request whose stream replaces the failed one. Byte callbacks and
+    /// the request ID stay those of the first attempt.
+    pub(crate) fn with_restarts<F, Fut>(mut self, restart: F) -> Self
+    where
+        F: FnMut(&Error) -> Option<Fut> + Send + 'static,
+        Fut: std::future::Future<Output = Result<MessageStream, Error>> + Send + 'static,
+    {
+        let events = std::mem::replace(&mut self.inner, Box::pin(futures::stream::empty()));
+        self.inner = Box::pin(futures::stream::unfold(
+            Some((events, restart)),
+            |state| async move {
+                let (mut events, mut restart) = state?;
+                loop {
+                    match events.next().await? {
+                        Err(error) => match restart(&error) {
+                            Some(next) => match next.await {
+                                Ok(next) => events = Box::pin(next),
+                                Err(error) => return Some((Err(error), None)),
+                            },
+                            None => return Some((Err(error), Some((events, restart)))),
+                        },
+                        event => return Some((event, Some((events, restart)))),
+                    }
+                }
+            },
+        ));
+        self
+    }
+
+    /// Strip the usage report from `message_delta` events
+    pub(crate) fn without_usage(mut self) -> Self {
+        self.inner = Box::pin(self.inner.map(|event| {
+            event.map(|event| match event {
+                StreamEvent::MessageDelta { mut delta } => {
+                    delta.usage = None;
+                    StreamEvent::MessageDelta { delta }
+                }
+                event => event,
+            })
+        }));
+        self
+    }
+
+    /// Keep `value` alive for as long as the stream exists
+    pub(crate) fn holding<T: Send + 'static>(mut self, value: T) -> Self {
+        self.inner = Box::pin(self.inner.map(move |event| {
+            let _ = &value;
+            event
+        }));
+        self
+    }
+
+    /// Get the request ID of the response backing this stream, if known
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Track the message state needed by [`MessageStream::snapshot`]
+    ///
+    /// Tracking copies every event into an accumulator as it is polled, so it is off
+    /// by default. Enable it before polling; events seen earlier are not included.
+    pub fn with_snapshot(mut self) -> Self {
+        self.state.get_or_insert_with(StreamState::default);
+        self
+    }
+
+    /// Get the message as accumulated from the events polled so far
+    ///
+    /// Includes the content blocks built up to this point, the latest usage, and the
+    /// stop reason once a `message_delta` has reported it. Returns `None` until the
+    /// `message_start` event has been received, or if tracking was not enabled with
+    /// [`MessageStream::with_snapshot`].
+    pub fn snapshot(&self) -> Option<PartialMessage> {
+        let state = self.state.as_ref()?;
+        let message = state.message.as_ref()?;
+        Some(PartialMessage {
+            id: message.id.clone(),
+            role: message.role.clone(),
+            content: state.content_blocks.clone(),
+            model: message.model.clone(),
+            stop_reason: message.stop_reason.clone(),
+            stop_sequence: message.stop_sequence.clone(),
+            usage: message.usage.clone(),
+        })
     }
 
     /// Create a message accumulator to reconstruct the full message from stream events
     pub fn accumulate(self) -> MessageAccumulator {
         MessageAccumulator::new(self)
     }
+
+    /// Prepare to forward the stream's events to many consumers over a broadcast channel
+    ///
+    /// Create receivers with [`StreamBroadcast::subscribe`], then call
+    /// [`StreamBroadcast::start`] to begin forwarding; every receiver created before
+    /// `start` sees the full event sequence. Errors are forwarded as `Err` so subscribers
+    /// can tell them apart from a clean finish, which closes the channel. A receiver that
+    /// falls more than `capacity` events behind gets `RecvError::Lagged` and skips the
+    /// oldest events.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use anthropic_rust::{Client, ContentBlock, Model};
+    ///
+    /// # async fn example() -> anthropic_rust::Result<()> {
+    /// let client = Client::new(Model::Claude35Sonnet20241022)?;
+    /// let request = client.chat_builder()
+    ///     .user_message(ContentBlock::text("Hello!"))
+    ///     .build();
+    ///
+    /// let broadcast = client.stream_chat(request).await?.into_broadcast(64);
+    /// let mut first = broadcast.subscribe();
+    /// let mut second = broadcast.subscribe();
+    /// broadcast.start();
+    ///
+    /// tokio::spawn(async move {
+    ///     while let Ok(event) = second.recv().await {
+    ///         println!("second subscriber: {:?}", event);
+    ///     }
+    /// });
+    /// while let Ok(event) = first.recv().await {
+    ///     match event {
+    ///         Ok(event) => println!("first subscriber: {:?}", event),
+    ///         Err(error) => eprintln!("stream failed: {}", error),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_broadcast(self, capacity: usize) -> StreamBroadcast {
+        let (sender, _) = tokio::sync::broadcast::channel(capacity);
+        StreamBroadcast {
+            stream: self,
+            sender,
+        }
+    }
+}
+
+/// Event delivered to broadcast subscribers; errors are shared between receivers
+pub type BroadcastEvent = Result<StreamEvent, Arc<Error>>;
+
+/// A [`MessageStream`] waiting to be fanned out, created by
+/// [`MessageStream::into_broadcast`]
+pub struct StreamBroadcast {
+    stream: MessageStream,
+    sender: tokio::sync::broadcast::Sender<BroadcastEvent>,
+}
+
+impl StreamBroadcast {
+    /// Create a receiver that will see every event once forwarding starts
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<BroadcastEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Spawn a task that forwards the stream's events to all subscribers
+    ///
+    /// The channel closes once the stream ends or every receiver has been dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a Tokio runtime.
+    pub fn start(self) -> tokio::task::JoinHandle<()> {
+        let Self { mut stream, sender } = self;
+        tokio::spawn(async move {
+            while let Some(event) = stream.next().await {
+                // Sending only fails once every receiver has been dropped
+                if sender.send(event.map_err(Arc::new)).is_err() {
+                    break;
+                }
+            }
+        })
+    }
 }
 
 impl Stream for MessageStream {
     type Item = Result<StreamEvent, Error>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.inner.as_mut().poll_next(cx)
+        let poll = self.inner.as_mut().poll_next(cx);
+        if let (Some(state), Poll::Ready(Some(Ok(event)))) = (&mut self.state, &poll) {
+            // Malformed tool input only affects the snapshot; accumulators report it
+            let _ = state.apply(event.clone());
+        }
+        poll
+    }
+}
+
+/// How streaming responses are read from the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StreamTransport {
+    /// Standard server-sent events framing
+    #[default]
+    Sse,
+    /// Raw response chunks scanned for JSON objects, ignoring any event framing
+    ///
+    /// Useful behind proxies or servers that buffer or strip SSE framing.
+    PollingChunks,
+}
+
+/// Incremental scanner that extracts complete top-level JSON objects from raw chunks
+///
+/// Bytes outside of an object (SSE `event:`/`data:` prefixes, newlines, whitespace)
+/// are ignored, so the scanner works with or without event framing.
+#[derive(Debug, Default)]
+pub(crate) struct JsonObjectScanner {
+    buffer: Vec<u8>,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl JsonObjectScanner {
+    /// Feed a chunk and return every JSON object completed by it
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<Vec<u8>> {
+        let mut objects = Vec::new();
+
+        for &byte in chunk {
+            if self.depth == 0 {
+                if byte == b'{' {
+                    self.buffer.clear();
+                    self.buffer.push(byte);
+                    self.depth = 1;
+                }
+                continue;
+            }
+
+            self.buffer.push(byte);
+
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                }
+                continue;
+            }
+
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' => self.depth += 1,
+                b'}' => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        objects.push(std::mem::take(&mut self.buffer));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        objects
+    }
+}
+
+/// Convert an in-stream `error` event into a typed error
+///
+/// Overloaded, generic API, and rate limit errors are reported with the HTTP status the
+/// API would have used (529, 500, and 429), so they are retryable just like their
+/// non-streaming form.
+pub(crate) fn stream_error_event(value: &serde_json::Value) -> Error {
+    let error = value.get("error");
+    let error_type = error
+        .and_then(|e| e.get("type"))
+        .and_then(|t| t.as_str())
+        .map(|t| t.to_string());
+    let message = error
+        .and_then(|e| e.get("message"))
+        .and_then(|m| m.as_str())
+        .unwrap_or("Unknown stream error")
+        .to_string();
+
+    let status = match error_type.as_deref() {
+        Some("overloaded_error") => reqwest::StatusCode::from_u16(529).ok(),
+        Some("api_error") => Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR),
+        Some("rate_limit_error") => Some(reqwest::StatusCode::TOO_MANY_REQUESTS),
+        _ => None,
+    };
+
+    match status {
+        Some(status) => Error::api(status, message, error_type, None),
+        None => Error::Stream(format!("Stream error: {}", message)),
+    }
+}
+
+/// Parse a JSON event payload into a stream event, skipping keep-alive pings
+fn parse_event_json(object: &[u8]) -> Option<Result<StreamEvent, Error>> {
+    let value: serde_json::Value = match serde_json::from_slice(object) {
+        Ok(value) => value,
+        Err(e) => {
+            return Some(Err(Error::Stream(format!(
+                "Invalid JSON object in stream: {}",
+                e
+            ))))
+        }
+    };
+
+    match value.get("type").and_then(|t| t.as_str()) {
+        Some("ping") => return None,
+        Some("error") => return Some(Err(stream_error_event(&value))),
+        _ => {}
+    }
+
+    Some(
+        serde_json::from_value(value)
+            .map_err(|e| Error::Stream(format!("Failed to parse stream event: {}", e))),
+    )
+}
+
+/// Incremental parser for server-sent event lines
+///
+/// Accumulates `data:` lines and emits an event at each blank-line delimiter. The
+/// `event:` field is ignored because every payload carries its own `type`.
+#[derive(Debug, Default)]
+pub(crate) struct SseParser {
+    data: String,
+    /// Bytes of a line not yet terminated, carried over between chunks
+    partial_line: Vec<u8>,
+}
+
+impl SseParser {
+    /// Feed a raw chunk, returning the events completed by it
+    ///
+    /// Lines and events may be split across chunks at any byte, including inside a
+    /// multi-byte UTF-8 character. Both `\n` and `\r\n` line endings are accepted.
+    pub(crate) fn push_bytes(&mut self, chunk: &[u8]) -> Vec<Result<StreamEvent, Error>> {
+        let mut events = Vec::new();
+
+        for &byte in chunk {
+            if byte != b'\n' {
+                self.partial_line.push(byte);
+                continue;
+            }
+
+            let mut line = std::mem::take(&mut self.partial_line);
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            match String::from_utf8(line) {
+                Ok(line) => events.extend(self.push_line(&line)),
+                Err(_) => events.push(Err(Error::Stream(
+                    "Stream contained a line that was not valid UTF-8".to_string(),
+                ))),
+            }
+        }
+
+        events
+    }
+
+    /// Flush the unterminated final line and any buffered event at end of input
+    pub(crate) fn finish_bytes(&mut self) -> Vec<Result<StreamEvent, Error>> {
+        let mut events = if self.partial_line.is_empty() {
+            Vec::new()
+        } else {
+            self.push_bytes(b"\n")
+        };
+        events.extend(self.finish());
+        events
     }
+
+    /// Feed one line without its line terminator, returning an event when one completes
+    pub(crate) fn push_line(&mut self, line: &str) -> Option<Result<StreamEvent, Error>> {
+        if line.is_empty() {
+            return self.finish();
+        }
+        if line.starts_with(':') {
+            return None; // Comment line
+        }
+
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        if field == "data" {
+            if !self.data.is_empty() {
+                self.data.push('\n');
+            }
+            self.data.push_str(value.strip_prefix(' ').unwrap_or(value));
+        }
+        None
+    }
+
+    /// Emit any event still buffered, for input that ends without a trailing blank line
+    pub(crate) fn finish(&mut self) -> Option<Result<StreamEvent, Error>> {
+        if self.data.is_empty() {
+            return None;
+        }
+        parse_event_json(std::mem::take(&mut self.data).as_bytes())
+    }
+}
+
+/// Parse a stored SSE transcript into stream events
+///
+/// Reads synchronously, so saved streams can be processed offline without a runtime.
+/// Keep-alive pings are skipped and in-stream `error` events become errors, exactly as
+/// when streaming from the API. Iteration ends after the first error.
+///
+/// # Examples
+///
+/// ```rust
+/// use anthropic_rust::streaming::{events_from_reader, StreamEvent};
+///
+/// let transcript = "event: message_stop\ndata: {\"type\": \"message_stop\"}\n\n";
+/// let events: Vec<_> = events_from_reader(transcript.as_bytes()).collect();
+/// assert!(matches!(events[..], [Ok(StreamEvent::MessageStop)]));
+/// ```
+pub fn events_from_reader(
+    reader: impl BufRead,
+) -> impl Iterator<Item = Result<StreamEvent, Error>> {
+    let mut lines = reader.lines();
+    let mut parser = SseParser::default();
+    let mut done = false;
+
+    std::iter::from_fn(move || loop {
+        if done {
+            return None;
+        }
+        match lines.next() {
+            Some(Ok(line)) => {
+                if let Some(event) = parser.push_line(&line) {
+                    done = event.is_err();
+                    return Some(event);
+                }
+            }
+            Some(Err(e)) => {
+                done = true;
+                return Some(Err(Error::Stream(format!(
+                    "Failed to read SSE input: {}",
+                    e
+                ))));
+            }
+            None => {
+                done = true;
+                return parser.finish();
+            }
+        }
+    })
+}
+
+/// Build a [`MessageStream`] from raw byte chunks using [`StreamTransport::PollingChunks`] parsing
+pub(crate) fn chunked_event_stream<S, B, E>(chunks: S) -> MessageStream
+where
+    S: Stream<Item = Result<B, E>> + Send + 'static,
+    B: AsRef<[u8]>,
+    E: Into<Error>,
+{
+    let progress = ByteProgress::default();
+    let reporter = progress.clone();
+    let mut bytes_read = 0;
+
+    let events = chunks
+        .scan(JsonObjectScanner::default(), move |scanner, chunk| {
+            let events: Vec<Result<StreamEvent, Error>> = match chunk {
+                Ok(bytes) => {
+                    let bytes = bytes.as_ref();
+                    reporter.tap(bytes);
+                    bytes_read += bytes.len();
+                    reporter.report(bytes_read);
+                    scanner
+                        .push(bytes)
+                        .iter()
+                        .filter_map(|object| parse_event_json(object))
+                        .collect()
+                }
+                Err(e) => vec![Err(e.into())],
+            };
+            futures::future::ready(Some(futures::stream::iter(events)))
+        })
+        .flatten();
+
+    MessageStream::with_progress(Box::pin(end_after_error(events)), progress)
+}
+
+/// End a stream after its first error
+///
+/// An in-stream `error` event or a failed read leaves nothing meaningful to follow.
+fn end_after_error<S>(events: S) -> impl Stream<Item = Result<StreamEvent, Error>>
+where
+    S: Stream<Item = Result<StreamEvent, Error>>,
+{
+    events.scan(false, |failed, event| {
+        if *failed {
+            return futures::future::ready(None);
+        }
+        *failed = event.is_err();
+        futures::future::ready(Some(event))
+    })
+}
+
+/// Build a [`MessageStream`] from raw byte chunks using [`StreamTransport::Sse`] parsing
+pub(crate) fn sse_event_stream<S, B, E>(chunks: S) -> MessageStream
+where
+    S: Stream<Item = Result<B, E>> + Send + 'static,
+    B: AsRef<[u8]> + Send + 'static,
+    E: Into<Error> + Send + 'static,
+{
+    let progress = ByteProgress::default();
+    let reporter = progress.clone();
+    let mut bytes_read = 0;
+
+    // A trailing `None` marks the end of input so the last event can be flushed
+    let events = chunks
+        .map(Some)
+        .chain(futures::stream::once(futures::future::ready(None)))
+        .scan(SseParser::default(), move |parser, chunk| {
+            let events: Vec<Result<StreamEvent, Error>> = match chunk {
+                Some(Ok(bytes)) => {
+                    let bytes = bytes.as_ref();
+                    reporter.tap(bytes);
+                    bytes_read += bytes.len();
+                    reporter.report(bytes_read);
+                    parser.push_bytes(bytes)
+                }
+                Some(Err(e)) => vec![Err(e.into())],
+                None => parser.finish_bytes(),
+            };
+            futures::future::ready(Some(futures::stream::iter(events)))
+        })
+        .flatten();
+
+    MessageStream::with_progress(Box::pin(end_after_error(events)), progress)
+}
+
+/// Fold a `message_delta` usage report into the usage from `message_start`
+///
+/// Deltas carry the cumulative output count but usually omit input tokens, so input
+/// and cache counts are only replaced when the delta actually reports them.
+fn merge_usage_delta(usage: &mut Usage, delta: Usage) {
+    usage.output_tokens = delta.output_tokens;
+    if delta.input_tokens > 0 {
+        usage.input_tokens = delta.input_tokens;
+    }
+    usage.cache_creation_input_tokens = delta
+        .cache_creation_input_tokens
+        .or(usage.cache_creation_input_tokens);
+    usage.cache_read_input_tokens = delta
+        .cache_read_input_tokens
+        .or(usage.cache_read_input_tokens);
+}
+
+/// Reduce a message stream to the cumulative usage reported by each `message_delta`
+///
+/// Content events are discarded. Input and cache counts come from `message_start`
+/// unless a delta reports them again.
+pub(crate) fn usage_stream(stream: MessageStream) -> impl Stream<Item = Result<Usage, Error>> {
+    stream
+        .scan(Usage::zero(), |usage, event| {
+            let item = match event {
+                Ok(StreamEvent::MessageStart { message }) => {
+                    *usage = message.usage;
+                    None
+                }
+                Ok(StreamEvent::MessageDelta { delta }) => {
+                    if let Some(delta_usage) = delta.usage {
+                        merge_usage_delta(usage, delta_usage);
+                    }
+                    Some(Ok(usage.clone()))
+                }
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            };
+            futures::future::ready(Some(item))
+        })
+        .filter_map(futures::future::ready)
 }
 
 /// Events that can be received in a message stream
@@ -60,6 +851,31 @@ pub enum StreamEvent {
     MessageStop,
 }
 
+impl StreamEvent {
+    /// Get the content block index for block-level events, or `None` for message-level events
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            StreamEvent::ContentBlockStart { index, .. }
+            | StreamEvent::ContentBlockDelta { index, .. }
+            | StreamEvent::ContentBlockStop { index } => Some(*index),
+            StreamEvent::MessageStart { .. }
+            | StreamEvent::MessageDelta { .. }
+            | StreamEvent::MessageStop => None,
+        }
+    }
+
+    /// Get the text carried by a text delta event
+    pub fn text_delta(&self) -> Option<&str> {
+        match self {
+            StreamEvent::ContentBlockDelta {
+                delta: ContentDelta::TextDelta { text },
+                ..
+            } => Some(text),
+            _ => None,
+        }
+    }
+}
+
 /// Partial message for stream start events
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PartialMessage {
@@ -76,7 +892,13 @@ pub struct PartialMessage {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ContentDelta {
-    TextDelta { text: String },
+    TextDelta {
+        text: String,
+    },
+    /// Fragment of a tool call's JSON input; fragments concatenate into the full input
+    InputJsonDelta {
+        partial_json: String,
+    },
 }
 
 /// Message delta for streaming updates
@@ -87,41 +909,39 @@ pub struct MessageDelta {
     pub usage: Option<Usage>,
 }
 
-/// Accumulator for reconstructing messages from stream events
-pub struct MessageAccumulator {
-    stream: MessageStream,
+/// Message state rebuilt from the stream events seen so far
+#[derive(Debug)]
+struct StreamState {
     message: Option<Message>,
     content_blocks: Vec<ContentBlock>,
+    /// Tool input JSON fragments received so far, keyed by content block index
+    partial_json: HashMap<usize, String>,
+    /// Largest tool input, in bytes, accepted for a single content block
+    max_tool_input_bytes: usize,
+    /// Whether `message_stop` has been received for the current message
+    stopped: bool,
 }
 
-impl MessageAccumulator {
-    /// Create a new message accumulator from a stream
-    pub fn new(stream: MessageStream) -> Self {
+impl Default for StreamState {
+    fn default() -> Self {
         Self {
-            stream,
             message: None,
             content_blocks: Vec::new(),
+            partial_json: HashMap::new(),
+            max_tool_input_bytes: MessageAccumulator::DEFAULT_MAX_TOOL_INPUT_BYTES,
+            stopped: false,
         }
     }
+}
 
-    /// Process the stream and accumulate the final message
-    pub async fn accumulate(mut self) -> Result<Message, Error> {
-        while let Some(event_result) = self.stream.next().await {
-            let event = event_result?;
-            self.apply_event(event)?;
-        }
-
-        self.message.ok_or_else(|| {
-            Error::Stream("Stream ended without producing a complete message".to_string())
-        })
-    }
-
-    /// Apply a stream event to update the accumulated message
-    pub fn apply_event(&mut self, event: StreamEvent) -> Result<(), Error> {
+impl StreamState {
+    /// Apply a stream event to the accumulated message
+    fn apply(&mut self, event: StreamEvent) -> Result<(), Error> {
         match event {
             StreamEvent::MessageStart { message } => {
                 self.message = Some(Message {
                     id: message.id,
+                    message_type: Some("message".to_string()),
                     role: message.role,
                     content: Vec::new(), // Will be populated from content block events
                     model: message.model,
@@ -130,6 +950,8 @@ impl MessageAccumulator {
                     usage: message.usage,
                 });
                 self.content_blocks.clear();
+                self.partial_json.clear();
+                self.stopped = false;
             }
             StreamEvent::ContentBlockStart {
                 index,
@@ -152,7 +974,7 @@ impl MessageAccumulator {
                     ContentDelta::TextDelta { text } => {
                         if let ContentBlock::Text {
                             text: existing_text,
-                            citations: _,
+                            ..
                         } = &mut self.content_blocks[index]
                         {
                             existing_text.push_str(&text);
@@ -161,13 +983,36 @@ impl MessageAccumulator {
                             self.content_blocks[index] = ContentBlock::Text {
                                 text,
                                 citations: None,
+                                cache_control: None,
                             };
                         }
                     }
+                    ContentDelta::InputJsonDelta { partial_json } => {
+                        let buffered = self.partial_json.entry(index).or_default();
+                        if buffered.len() + partial_json.len() > self.max_tool_input_bytes {
+                            return Err(Error::Stream(format!(
+                                "Tool input for content block {} exceeded the {}-byte limit",
+                                index, self.max_tool_input_bytes
+                            )));
+                        }
+                        buffered.push_str(&partial_json);
+                    }
                 }
             }
-            StreamEvent::ContentBlockStop { .. } => {
-                // Content block is complete, no action needed
+            StreamEvent::ContentBlockStop { index } => {
+                // Tool inputs arrive as JSON fragments and are only parseable once complete
+                if let Some(json) = self.partial_json.remove(&index) {
+                    if let Some(ContentBlock::ToolUse { input, .. }) =
+                        self.content_blocks.get_mut(index)
+                    {
+                        *input = serde_json::from_str(&json).map_err(|e| {
+                            Error::Stream(format!(
+                                "Invalid tool input JSON in content block {}: {} (received {:?})",
+                                index, e, json
+                            ))
+                        })?;
+                    }
+                }
             }
             StreamEvent::MessageDelta { delta } => {
                 if let Some(ref mut message) = self.message {
@@ -178,7 +1023,7 @@ impl MessageAccumulator {
                         message.stop_sequence = Some(stop_sequence);
                     }
                     if let Some(usage) = delta.usage {
-                        message.usage = usage;
+                        merge_usage_delta(&mut message.usage, usage);
                     }
                 }
             }
@@ -187,19 +1032,77 @@ impl MessageAccumulator {
                 if let Some(ref mut message) = self.message {
                     message.content = self.content_blocks.clone();
                 }
+                self.stopped = true;
             }
         }
 
         Ok(())
     }
+}
+
+/// Accumulator for reconstructing messages from stream events
+pub struct MessageAccumulator {
+    stream: MessageStream,
+    state: StreamState,
+}
+
+impl MessageAccumulator {
+    /// Default limit on the size of a single tool call's streamed input (1 MiB)
+    pub const DEFAULT_MAX_TOOL_INPUT_BYTES: usize = 1024 * 1024;
+
+    /// Create a new message accumulator from a stream
+    pub fn new(stream: MessageStream) -> Self {
+        Self {
+            stream,
+            state: StreamState::default(),
+        }
+    }
+
+    /// Set the largest tool input, in bytes, accepted for a single content block
+    ///
+    /// Guards against a broken or malicious server streaming unbounded
+    /// `input_json_delta` fragments. Exceeding the limit fails accumulation with
+    /// `Error::Stream`. Defaults to [`DEFAULT_MAX_TOOL_INPUT_BYTES`](Self::DEFAULT_MAX_TOOL_INPUT_BYTES).
+    pub fn max_tool_input_bytes(mut self, limit: usize) -> Self {
+        self.state.max_tool_input_bytes = limit;
+        self
+    }
+
+    /// Process the stream and accumulate the final message
+    ///
+    /// Text deltas are merged, tool inputs are parsed once their block stops, and the
+    /// `message_delta` stop reason and usage are applied, so the result matches what
+    /// a non-streaming request returns. Fails with `Error::Stream` if the stream ends
+    /// before `message_stop`.
+    pub async fn accumulate(mut self) -> Result<Message, Error> {
+        while let Some(event_result) = self.stream.next().await {
+            let event = event_result?;
+            self.apply_event(event)?;
+        }
+
+        let message = self.state.message.ok_or_else(|| {
+            Error::Stream("Stream ended without producing a complete message".to_string())
+        })?;
+        if !self.state.stopped {
+            return Err(Error::Stream(
+                "Stream ended before message_stop; the message is incomplete".to_string(),
+            ));
+        }
+        Ok(message)
+    }
+
+    /// Apply a stream event to update the accumulated message
+    pub fn apply_event(&mut self, event: StreamEvent) -> Result<(), Error> {
+        self.state.apply(event)
+    }
 
     /// Get the current accumulated message (may be incomplete)
     pub fn current_message(&self) -> Option<&Message> {
-        self.message.as_ref()
+        self.state.message.as_ref()
     }
 
     /// Get the current content blocks (may be incomplete)
     pub fn current_content_blocks(&self) -> &[ContentBlock] {
-        &self.content_blocks
+        &self.state.content_blocks
     }
 }
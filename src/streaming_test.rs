@@ -83,6 +83,7 @@ mod tests {
                 assert_eq!(index, 0);
                 match delta {
                     ContentDelta::TextDelta { text } => assert_eq!(text, " world"),
+                    other => panic!("Expected text delta, got {:?}", other),
                 }
             }
             _ => panic!("Expected ContentBlockDelta event"),
@@ -359,10 +360,34 @@ mod tests {
 
         let result = accumulator.accumulate().await;
 
-        // Should still return the message even if incomplete
-        assert!(result.is_ok());
-        let message = result.unwrap();
-        assert_eq!(message.id, "msg_incomplete");
+        // A truncated stream must not be mistaken for a complete message
+        match result.unwrap_err() {
+            crate::Error::Stream(msg) => assert!(msg.contains("before message_stop")),
+            other => panic!("Expected Stream error, got {:?}", other),
+        }
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_message_accumulator_matches_non_streaming_message() {
+        let transcript = include_str!("../tests/fixtures/message_stream.sse");
+        let events: Vec<_> = crate::streaming::events_from_reader(transcript.as_bytes()).collect();
+        let message_stream = crate::streaming::MessageStream::new(Box::pin(stream::iter(events)));
+
+        let message = message_stream.accumulate().accumulate().await.unwrap();
+
+        let expected: crate::types::Message = serde_json::from_value(serde_json::json!({
+            "id": "msg_fixture",
+            "type": "message",
+            "role": "assistant",
+            "content": [{ "type": "text", "text": "Hello, world" }],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": { "input_tokens": 25, "output_tokens": 6 }
+        }))
+        .unwrap();
+        assert_eq!(message, expected);
     }
 
     #[test]
@@ -407,7 +432,16 @@ mod tests {
 
         match parsed {
             ContentDelta::TextDelta { text } => assert_eq!(text, "Delta text"),
+            other => panic!("Expected text delta, got {:?}", other),
         }
+
+        let parsed: ContentDelta =
+            serde_json::from_str(r#"{"type": "input_json_delta", "partial_json": "{\"city\": "}"#)
+                .unwrap();
+        assert!(matches!(
+            parsed,
+            ContentDelta::InputJsonDelta { partial_json } if partial_json == "{\"city\": "
+        ));
     }
 
     #[test]
@@ -467,4 +501,922 @@ mod tests {
 
         assert_eq!(event_count, 2);
     }
+
+    #[test]
+    fn test_json_object_scanner_handles_split_chunks() {
+        let mut scanner = crate::streaming::JsonObjectScanner::default();
+
+        assert!(scanner
+            .push(br#"{"type":"content_block_delta","index":0,"#)
+            .is_empty());
+        let objects = scanner.push(
+            br#""delta":{"type":"text_delta","text":"a } and \" {"}}{"type":"message_stop"}"#,
+        );
+
+        assert_eq!(objects.len(), 2);
+        let first: serde_json::Value = serde_json::from_slice(&objects[0]).unwrap();
+        assert_eq!(first["delta"]["text"], "a } and \" {");
+        assert_eq!(objects[1], br#"{"type":"message_stop"}"#.to_vec());
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_chunked_event_stream_recovers_events() {
+        let body = concat!(
+            r#"{"type":"message_start","message":{"id":"msg_chunked","role":"assistant","content":[],"#,
+            r#""model":"claude-3-5-sonnet-20241022","stop_reason":null,"stop_sequence":null,"#,
+            r#""usage":{"input_tokens":3,"output_tokens":0}}}"#,
+            "\n",
+            r#"{"type":"ping"}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi {there}"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"message_stop"}"#,
+        );
+
+        // Split the body into small frame-less chunks that cut through objects
+        let chunks: Vec<Result<Vec<u8>, crate::Error>> = body
+            .as_bytes()
+            .chunks(7)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+
+        let message = crate::streaming::chunked_event_stream(stream::iter(chunks))
+            .accumulate()
+            .accumulate()
+            .await
+            .unwrap();
+
+        assert_eq!(message.id, "msg_chunked");
+        match &message.content[0] {
+            ContentBlock::Text { text, .. } => assert_eq!(text, "Hi {there}"),
+            _ => panic!("Expected text content"),
+        }
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_on_bytes_reports_cumulative_total() {
+        use std::sync::{Arc, Mutex};
+
+        let transcript = concat!(
+            "event: content_block_delta\n",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#,
+            "\n\n",
+            "event: message_stop\n",
+            r#"data: {"type":"message_stop"}"#,
+            "\n\n",
+        );
+        let chunks: Vec<Result<Vec<u8>, crate::Error>> = transcript
+            .as_bytes()
+            .chunks(16)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+        let chunk_count = chunks.len();
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let sink = reported.clone();
+        let events: Vec<_> = crate::streaming::chunked_event_stream(stream::iter(chunks))
+            .on_bytes(move |total| sink.lock().unwrap().push(total))
+            .collect()
+            .await;
+
+        assert_eq!(events.len(), 2);
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), chunk_count);
+        assert!(reported.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(reported.last().copied(), Some(transcript.len()));
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_sse_event_stream_handles_split_chunks() {
+        let transcript = include_str!("../tests/fixtures/message_stream.sse");
+        let expected: Vec<String> = crate::streaming::events_from_reader(transcript.as_bytes())
+            .map(|event| format!("{:?}", event.unwrap()))
+            .collect();
+        assert_eq!(expected.len(), 7);
+
+        // Chunk sizes that split lines, `\n\n` delimiters, and JSON payloads
+        for size in [1, 5, 13, transcript.len()] {
+            let chunks: Vec<Result<Vec<u8>, crate::Error>> = transcript
+                .as_bytes()
+                .chunks(size)
+                .map(|chunk| Ok(chunk.to_vec()))
+                .collect();
+            let events: Vec<String> = crate::streaming::sse_event_stream(stream::iter(chunks))
+                .map(|event| format!("{:?}", event.unwrap()))
+                .collect()
+                .await;
+            assert_eq!(events, expected, "chunk size {}", size);
+        }
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_sse_event_stream_splits_multibyte_text() {
+        let transcript = concat!(
+            ": keep-alive comment\r\n",
+            "event: content_block_delta\r\n",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"héllo ✓"}}"#,
+            "\r\n\r\n",
+        );
+        let chunks: Vec<Result<Vec<u8>, crate::Error>> = transcript
+            .as_bytes()
+            .chunks(1)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+
+        let events: Vec<_> = crate::streaming::sse_event_stream(stream::iter(chunks))
+            .collect()
+            .await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].as_ref().unwrap().text_delta(), Some("héllo ✓"));
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_sse_event_stream_emits_block_boundaries() {
+        let transcript = concat!(
+            "event: message_start\n",
+            r#"data: {"type":"message_start","message":{"id":"msg_blocks","type":"message","role":"assistant","content":[],"model":"claude-3-5-sonnet-20241022","stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":9,"output_tokens":1}}}"#,
+            "\n\n",
+            "event: content_block_start\n",
+            r#"data: {"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            "\n\n",
+            "event: content_block_delta\n",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Checking the time."}}"#,
+            "\n\n",
+            "event: content_block_stop\n",
+            r#"data: {"type":"content_block_stop","index":0}"#,
+            "\n\n",
+            "event: content_block_start\n",
+            r#"data: {"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"get_time","input":{}}}"#,
+            "\n\n",
+            "event: content_block_stop\n",
+            r#"data: {"type":"content_block_stop","index":1}"#,
+            "\n\n",
+            "event: message_stop\n",
+            r#"data: {"type":"message_stop"}"#,
+            "\n\n",
+        );
+        let chunks: Vec<Result<Vec<u8>, crate::Error>> = transcript
+            .as_bytes()
+            .chunks(32)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+
+        let events: Vec<StreamEvent> = crate::streaming::sse_event_stream(stream::iter(chunks))
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        assert_eq!(events.len(), 7);
+        assert!(matches!(
+            &events[4],
+            StreamEvent::ContentBlockStart {
+                index: 1,
+                content_block: ContentBlock::ToolUse { name, input, .. },
+            } if name == "get_time" && *input == serde_json::json!({})
+        ));
+        assert!(matches!(
+            events[5],
+            StreamEvent::ContentBlockStop { index: 1 }
+        ));
+
+        // A tool call without arguments keeps its empty input object
+        let stream = crate::streaming::MessageStream::new(Box::pin(stream::iter(
+            events.into_iter().map(Ok),
+        )));
+        let message = stream.accumulate().accumulate().await.unwrap();
+        assert_eq!(message.content.len(), 2);
+        assert!(matches!(
+            &message.content[1],
+            ContentBlock::ToolUse { id, input, .. } if id == "toolu_1" && input.as_object().is_some_and(|o| o.is_empty())
+        ));
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_sse_event_stream_skips_pings_and_ends_on_error() {
+        let transcript = concat!(
+            "event: ping\n",
+            r#"data: {"type":"ping"}"#,
+            "\n\n",
+            "event: content_block_delta\n",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hel"}}"#,
+            "\n\n",
+            "event: error\n",
+            r#"data: {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+            "\n\n",
+            "event: content_block_delta\n",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"lo"}}"#,
+            "\n\n",
+        );
+        let chunks: Vec<Result<Vec<u8>, crate::Error>> = vec![Ok(transcript.as_bytes().to_vec())];
+
+        let events: Vec<_> = crate::streaming::sse_event_stream(stream::iter(chunks))
+            .collect()
+            .await;
+
+        // The ping yields nothing and the delta after the error is never delivered
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].as_ref().unwrap().text_delta(), Some("Hel"));
+        let error = events[1].as_ref().unwrap_err();
+        assert!(error.is_overloaded());
+        assert!(error.is_retryable());
+        assert!(matches!(
+            error,
+            crate::Error::Api { status, .. } if status.as_u16() == 529
+        ));
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_into_broadcast_fans_out_to_subscribers() {
+        let transcript = include_str!("../tests/fixtures/message_stream.sse");
+        let events: Vec<_> = crate::streaming::events_from_reader(transcript.as_bytes()).collect();
+        let message_stream = crate::streaming::MessageStream::new(Box::pin(stream::iter(events)));
+
+        let broadcast = message_stream.into_broadcast(16);
+        let first = broadcast.subscribe();
+        let second = broadcast.subscribe();
+
+        // Forwarding finishes before anyone receives; nothing is missed
+        broadcast.start().await.unwrap();
+
+        let drain = |mut receiver: tokio::sync::broadcast::Receiver<
+            crate::streaming::BroadcastEvent,
+        >| async move {
+            let mut received = Vec::new();
+            while let Ok(event) = receiver.recv().await {
+                received.push(format!("{:?}", event.unwrap()));
+            }
+            received
+        };
+        let (first, second) = tokio::join!(drain(first), drain(second));
+
+        assert_eq!(first.len(), 7);
+        assert_eq!(first, second);
+        assert!(first[0].starts_with("MessageStart"));
+        assert_eq!(first[6], "MessageStop");
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_into_broadcast_forwards_errors() {
+        let events: Vec<Result<StreamEvent, crate::Error>> = vec![
+            Ok(StreamEvent::MessageStop),
+            Err(crate::Error::Stream("connection reset".to_string())),
+        ];
+        let broadcast =
+            crate::streaming::MessageStream::new(Box::pin(stream::iter(events))).into_broadcast(4);
+        let mut receiver = broadcast.subscribe();
+        broadcast.start().await.unwrap();
+
+        assert!(receiver.recv().await.unwrap().is_ok());
+        let error = receiver.recv().await.unwrap().unwrap_err();
+        assert!(error.to_string().contains("connection reset"));
+        assert!(matches!(
+            receiver.recv().await,
+            Err(tokio::sync::broadcast::error::RecvError::Closed)
+        ));
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_tap_raw_receives_transcript_in_order() {
+        use std::sync::{Arc, Mutex};
+
+        let transcript = include_str!("../tests/fixtures/message_stream.sse");
+        let chunks: Vec<Result<Vec<u8>, crate::Error>> = transcript
+            .as_bytes()
+            .chunks(7)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+
+        let tapped = Arc::new(Mutex::new(Vec::new()));
+        let sink = tapped.clone();
+        let events: Vec<_> = crate::streaming::chunked_event_stream(stream::iter(chunks))
+            .tap_raw(move |bytes| sink.lock().unwrap().extend_from_slice(bytes))
+            .collect()
+            .await;
+
+        assert!(!events.is_empty());
+        assert_eq!(*tapped.lock().unwrap(), transcript.as_bytes());
+    }
+
+    /// In-memory `AsyncWrite` sink whose contents stay readable after it is moved
+    #[derive(Clone, Default)]
+    struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl tokio::io::AsyncWrite for SharedBuffer {
+        fn poll_write(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            std::task::Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    /// `AsyncWrite` sink that rejects every write
+    struct FailingWriter;
+
+    impl tokio::io::AsyncWrite for FailingWriter {
+        fn poll_write(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            _buf: &[u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            std::task::Poll::Ready(Err(std::io::Error::other("disk full")))
+        }
+
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    fn transcript_chunks(transcript: &str) -> Vec<Result<Vec<u8>, crate::Error>> {
+        transcript
+            .as_bytes()
+            .chunks(11)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect()
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_persist_to_tees_raw_transcript() {
+        let transcript = include_str!("../tests/fixtures/message_stream.sse");
+        let expected: Vec<String> = crate::streaming::events_from_reader(transcript.as_bytes())
+            .map(|event| format!("{:?}", event.unwrap()))
+            .collect();
+
+        let buffer = SharedBuffer::default();
+        let events: Vec<String> =
+            crate::streaming::sse_event_stream(stream::iter(transcript_chunks(transcript)))
+                .persist_to(buffer.clone())
+                .map(|event| format!("{:?}", event.unwrap()))
+                .collect()
+                .await;
+
+        assert_eq!(events, expected);
+        assert_eq!(*buffer.0.lock().unwrap(), transcript.as_bytes());
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_persist_to_surfaces_write_errors() {
+        let transcript = include_str!("../tests/fixtures/message_stream.sse");
+        let events: Vec<_> =
+            crate::streaming::sse_event_stream(stream::iter(transcript_chunks(transcript)))
+                .persist_to(FailingWriter)
+                .collect()
+                .await;
+
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            Err(crate::Error::Stream(message)) => assert!(message.contains("disk full")),
+            other => panic!("Expected a stream error, got {:?}", other),
+        }
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_message_accumulator_keeps_input_tokens_from_start() {
+        let body = concat!(
+            r#"{"type":"message_start","message":{"id":"msg_usage","role":"assistant","content":[],"#,
+            r#""model":"claude-3-5-sonnet-20241022","stop_reason":null,"stop_sequence":null,"#,
+            r#""usage":{"input_tokens":50,"output_tokens":1,"cache_read_input_tokens":30}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":null,"stop_sequence":null,"usage":{"output_tokens":5}}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null,"usage":{"output_tokens":12}}}"#,
+            r#"{"type":"message_stop"}"#,
+        );
+        let chunks: Vec<Result<Vec<u8>, crate::Error>> = vec![Ok(body.as_bytes().to_vec())];
+
+        let message = crate::streaming::chunked_event_stream(stream::iter(chunks))
+            .accumulate()
+            .accumulate()
+            .await
+            .unwrap();
+
+        assert_eq!(message.usage.input_tokens, 50);
+        assert_eq!(message.usage.output_tokens, 12);
+        // Deltas omit cache counts, so the message_start values are kept
+        assert_eq!(message.usage.cache_read_input_tokens, Some(30));
+        assert_eq!(message.stop_reason, Some(StopReason::EndTurn));
+        assert_eq!(message.stop_sequence, None);
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_usage_stream_yields_cumulative_usage() {
+        let events = vec![
+            Ok(StreamEvent::MessageStart {
+                message: PartialMessage {
+                    id: "msg_usage".to_string(),
+                    role: Role::Assistant,
+                    content: vec![],
+                    model: Model::Claude35Sonnet20241022,
+                    stop_reason: None,
+                    stop_sequence: None,
+                    usage: Usage {
+                        input_tokens: 12,
+                        output_tokens: 1,
+                        cache_creation_input_tokens: None,
+                        cache_read_input_tokens: Some(4),
+                    },
+                },
+            }),
+            Ok(StreamEvent::ContentBlockDelta {
+                index: 0,
+                delta: ContentDelta::TextDelta {
+                    text: "ignored".to_string(),
+                },
+            }),
+            Ok(StreamEvent::MessageDelta {
+                delta: MessageDelta {
+                    stop_reason: None,
+                    stop_sequence: None,
+                    usage: Some(Usage {
+                        input_tokens: 0,
+                        output_tokens: 8,
+                        cache_creation_input_tokens: None,
+                        cache_read_input_tokens: None,
+                    }),
+                },
+            }),
+            Ok(StreamEvent::MessageDelta {
+                delta: MessageDelta {
+                    stop_reason: Some(StopReason::EndTurn),
+                    stop_sequence: None,
+                    usage: Some(Usage {
+                        input_tokens: 0,
+                        output_tokens: 25,
+                        cache_creation_input_tokens: None,
+                        cache_read_input_tokens: None,
+                    }),
+                },
+            }),
+            Ok(StreamEvent::MessageStop),
+        ];
+
+        let stream = crate::streaming::MessageStream::new(Box::pin(stream::iter(events)));
+        let usages: Vec<Usage> = crate::streaming::usage_stream(stream)
+            .map(|usage| usage.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(usages.len(), 2);
+        assert_eq!(usages[0].output_tokens, 8);
+        let last = usages.last().unwrap();
+        assert_eq!(last.output_tokens, 25);
+        assert_eq!(last.input_tokens, 12);
+        assert_eq!(last.cache_read_input_tokens, Some(4));
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_idle_timeout_ends_stalled_stream() {
+        use std::time::Duration;
+
+        // One event, then the connection stalls forever
+        let events = stream::iter(vec![Ok(StreamEvent::MessageStop)]).chain(stream::pending());
+        let mut stream = crate::streaming::MessageStream::new(Box::pin(events))
+            .with_idle_timeout(Duration::from_millis(20));
+
+        assert!(matches!(
+            stream.next().await,
+            Some(Ok(StreamEvent::MessageStop))
+        ));
+        match stream.next().await {
+            Some(Err(crate::Error::Timeout { timeout, .. })) => {
+                assert_eq!(timeout, Duration::from_millis(20))
+            }
+            other => panic!("Expected idle timeout, got {:?}", other),
+        }
+        assert!(stream.next().await.is_none());
+    }
+
+    #[cfg(not(miri))]
+    #[tokio::test]
+    async fn test_mid_stream_overloaded_error_is_retryable() {
+        let body = concat!(
+            r#"{"type":"message_start","message":{"id":"msg_overloaded","role":"assistant","content":[],"#,
+            r#""model":"claude-3-5-sonnet-20241022","stop_reason":null,"stop_sequence":null,"#,
+            r#""usage":{"input_tokens":3,"output_tokens":0}}}"#,
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+        );
+        let chunks: Vec<Result<Vec<u8>, crate::Error>> = vec![Ok(body.as_bytes().to_vec())];
+
+        let events: Vec<_> = crate::streaming::chunked_event_stream(stream::iter(chunks))
+            .collect()
+            .await;
+
+        assert_eq!(events.len(), 2);
+        assert!(events[0].is_ok());
+        let error = events[1].as_ref().unwrap_err();
+        assert!(error.is_overloaded());
+        assert!(error.is_retryable());
+    }
+
+    #[test]
+    fn test_stream_error_event_classification() {
+        use crate::streaming::stream_error_event;
+        use serde_json::json;
+
+        let api_error = stream_error_event(&json!({
+            "type": "error",
+            "error": {"type": "api_error", "message": "Internal error"}
+        }));
+        assert!(api_error.is_server_error());
+        assert!(api_error.is_retryable());
+
+        let invalid = stream_error_event(&json!({
+            "type": "error",
+            "error": {"type": "invalid_request_error", "message": "Bad input"}
+        }));
+        assert!(matches!(invalid, crate::Error::Stream(_)));
+        assert!(!invalid.is_retryable());
+
+        let rate_limited = stream_error_event(&json!({
+            "type": "error",
+            "error": {"type": "rate_limit_error", "message": "Slow down"}
+        }));
+        assert!(rate_limited.is_rate_limit_error());
+        assert!(rate_limited.is_retryable());
+        assert!(rate_limited.to_string().contains("Slow down"));
+    }
+
+    #[test]
+    fn test_stream_event_index_and_text_delta() {
+        let message_start = StreamEvent::MessageStart {
+            message: PartialMessage {
+                id: "msg_accessors".to_string(),
+                role: Role::Assistant,
+                content: vec![],
+                model: Model::Claude35Sonnet20241022,
+                stop_reason: None,
+                stop_sequence: None,
+                usage: Usage {
+                    input_tokens: 1,
+                    output_tokens: 0,
+                    cache_creation_input_tokens: None,
+                    cache_read_input_tokens: None,
+                },
+            },
+        };
+        assert_eq!(message_start.index(), None);
+        assert_eq!(message_start.text_delta(), None);
+
+        let block_start = StreamEvent::ContentBlockStart {
+            index: 1,
+            content_block: ContentBlock::text(""),
+        };
+        assert_eq!(block_start.index(), Some(1));
+        assert_eq!(block_start.text_delta(), None);
+
+        let block_delta = StreamEvent::ContentBlockDelta {
+            index: 2,
+            delta: ContentDelta::TextDelta {
+                text: "Hello".to_string(),
+            },
+        };
+        assert_eq!(block_delta.index(), Some(2));
+        assert_eq!(block_delta.text_delta(), Some("Hello"));
+
+        let block_stop = StreamEvent::ContentBlockStop { index: 3 };
+        assert_eq!(block_stop.index(), Some(3));
+        assert_eq!(block_stop.text_delta(), None);
+
+        let message_delta = StreamEvent::MessageDelta {
+            delta: MessageDelta {
+                stop_reason: Some(StopReason::EndTurn),
+                stop_sequence: None,
+                usage: None,
+            },
+        };
+        assert_eq!(message_delta.index(), None);
+        assert_eq!(message_delta.text_delta(), None);
+
+        assert_eq!(StreamEvent::MessageStop.index(), None);
+        assert_eq!(StreamEvent::MessageStop.text_delta(), None);
+    }
+
+    #[tokio::test]
+    async fn test_message_stream_snapshot_tracks_polled_events() {
+        let events = vec![
+            StreamEvent::MessageStart {
+                message: PartialMessage {
+                    id: "msg_snapshot".to_string(),
+                    role: Role::Assistant,
+                    content: vec![],
+                    model: Model::Claude35Sonnet20241022,
+                    stop_reason: None,
+                    stop_sequence: None,
+                    usage: Usage {
+                        input_tokens: 12,
+                        output_tokens: 0,
+                        cache_creation_input_tokens: None,
+                        cache_read_input_tokens: None,
+                    },
+                },
+            },
+            StreamEvent::ContentBlockStart {
+                index: 0,
+                content_block: ContentBlock::text(""),
+            },
+            StreamEvent::ContentBlockDelta {
+                index: 0,
+                delta: ContentDelta::TextDelta {
+                    text: "Partial".to_string(),
+                },
+            },
+            StreamEvent::ContentBlockStart {
+                index: 1,
+                content_block: ContentBlock::text(""),
+            },
+            StreamEvent::MessageDelta {
+                delta: MessageDelta {
+                    stop_reason: Some(StopReason::EndTurn),
+                    stop_sequence: None,
+                    usage: Some(Usage {
+                        input_tokens: 0,
+                        output_tokens: 4,
+                        cache_creation_input_tokens: None,
+                        cache_read_input_tokens: None,
+                    }),
+                },
+            },
+        ];
+
+        let mut message_stream = crate::streaming::MessageStream::new(Box::pin(stream::iter(
+            events.clone().into_iter().map(Ok),
+        )))
+        .with_snapshot();
+        assert!(message_stream.snapshot().is_none());
+
+        for _ in 0..3 {
+            message_stream.next().await.unwrap().unwrap();
+        }
+        let snapshot = message_stream.snapshot().unwrap();
+        assert_eq!(snapshot.id, "msg_snapshot");
+        assert_eq!(snapshot.content.len(), 1);
+        assert!(
+            matches!(&snapshot.content[0], ContentBlock::Text { text, .. } if text == "Partial")
+        );
+        assert_eq!(snapshot.stop_reason, None);
+
+        while message_stream.next().await.is_some() {}
+        let snapshot = message_stream.snapshot().unwrap();
+        assert_eq!(snapshot.content.len(), 2);
+        assert_eq!(snapshot.stop_reason, Some(StopReason::EndTurn));
+        assert_eq!(snapshot.usage.input_tokens, 12);
+        assert_eq!(snapshot.usage.output_tokens, 4);
+
+        let mut untracked = crate::streaming::MessageStream::new(Box::pin(stream::iter(
+            events.into_iter().map(Ok),
+        )));
+        while untracked.next().await.is_some() {}
+        assert!(untracked.snapshot().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_on_tool_input_delta_receives_fragments_in_order() {
+        let input_delta = |index: usize, fragment: &str| {
+            Ok(StreamEvent::ContentBlockDelta {
+                index,
+                delta: ContentDelta::InputJsonDelta {
+                    partial_json: fragment.to_string(),
+                },
+            })
+        };
+        let events = vec![
+            Ok(StreamEvent::MessageStart {
+                message: PartialMessage {
+                    id: "msg_tool".to_string(),
+                    role: Role::Assistant,
+                    content: vec![],
+                    model: Model::Claude35Sonnet20241022,
+                    stop_reason: None,
+                    stop_sequence: None,
+                    usage: Usage {
+                        input_tokens: 20,
+                        output_tokens: 0,
+                        cache_creation_input_tokens: None,
+                        cache_read_input_tokens: None,
+                    },
+                },
+            }),
+            Ok(StreamEvent::ContentBlockStart {
+                index: 1,
+                content_block: ContentBlock::ToolUse {
+                    id: "toolu_1".to_string(),
+                    name: "get_weather".to_string(),
+                    input: serde_json::json!({}),
+                },
+            }),
+            input_delta(1, "{\"city\": "),
+            input_delta(1, "\"Paris\""),
+            input_delta(1, "}"),
+            Ok(StreamEvent::ContentBlockStop { index: 1 }),
+            Ok(StreamEvent::MessageStop),
+        ];
+
+        let fragments = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = fragments.clone();
+        let message_stream = crate::streaming::MessageStream::new(Box::pin(stream::iter(events)))
+            .on_tool_input_delta(move |index, fragment| {
+                recorded.lock().unwrap().push((index, fragment.to_string()));
+            });
+
+        let message = message_stream.accumulate().accumulate().await.unwrap();
+
+        assert_eq!(
+            *fragments.lock().unwrap(),
+            vec![
+                (1, "{\"city\": ".to_string()),
+                (1, "\"Paris\"".to_string()),
+                (1, "}".to_string()),
+            ]
+        );
+        assert!(matches!(
+            &message.content[1],
+            ContentBlock::ToolUse { input, .. } if input["city"] == "Paris"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_accumulator_rejects_malformed_tool_input() {
+        let events = vec![
+            Ok(StreamEvent::MessageStart {
+                message: PartialMessage {
+                    id: "msg_bad_tool".to_string(),
+                    role: Role::Assistant,
+                    content: vec![],
+                    model: Model::Claude35Sonnet20241022,
+                    stop_reason: None,
+                    stop_sequence: None,
+                    usage: Usage::zero(),
+                },
+            }),
+            Ok(StreamEvent::ContentBlockStart {
+                index: 0,
+                content_block: ContentBlock::ToolUse {
+                    id: "toolu_1".to_string(),
+                    name: "get_weather".to_string(),
+                    input: serde_json::json!({}),
+                },
+            }),
+            Ok(StreamEvent::ContentBlockDelta {
+                index: 0,
+                delta: ContentDelta::InputJsonDelta {
+                    partial_json: "{\"city\": \"Par".to_string(),
+                },
+            }),
+            Ok(StreamEvent::ContentBlockStop { index: 0 }),
+            Ok(StreamEvent::MessageStop),
+        ];
+
+        let message_stream = crate::streaming::MessageStream::new(Box::pin(stream::iter(events)));
+        let error = message_stream.accumulate().accumulate().await.unwrap_err();
+
+        assert!(matches!(error, crate::Error::Stream(_)));
+        let message = error.to_string();
+        assert!(message.contains("content block 0"));
+        assert!(message.contains(r#"{\"city\": \"Par"#));
+    }
+
+    #[tokio::test]
+    async fn test_accumulator_limits_tool_input_size() {
+        let tool_events = |fragments: Vec<&str>| {
+            let mut events = vec![
+                Ok(StreamEvent::MessageStart {
+                    message: PartialMessage {
+                        id: "msg_big_tool".to_string(),
+                        role: Role::Assistant,
+                        content: vec![],
+                        model: Model::Claude35Sonnet20241022,
+                        stop_reason: None,
+                        stop_sequence: None,
+                        usage: Usage::zero(),
+                    },
+                }),
+                Ok(StreamEvent::ContentBlockStart {
+                    index: 0,
+                    content_block: ContentBlock::ToolUse {
+                        id: "toolu_1".to_string(),
+                        name: "write_file".to_string(),
+                        input: serde_json::json!({}),
+                    },
+                }),
+            ];
+            events.extend(fragments.into_iter().map(|fragment| {
+                Ok(StreamEvent::ContentBlockDelta {
+                    index: 0,
+                    delta: ContentDelta::InputJsonDelta {
+                        partial_json: fragment.to_string(),
+                    },
+                })
+            }));
+            events.push(Ok(StreamEvent::ContentBlockStop { index: 0 }));
+            events.push(Ok(StreamEvent::MessageStop));
+            crate::streaming::MessageStream::new(Box::pin(stream::iter(events)))
+        };
+
+        // 21 bytes in total fits within a 32-byte limit
+        let message = tool_events(vec!["{\"path\": ", "\"notes.txt\"}"])
+            .accumulate()
+            .max_tool_input_bytes(32)
+            .accumulate()
+            .await
+            .unwrap();
+        assert!(matches!(
+            &message.content[0],
+            ContentBlock::ToolUse { input, .. } if input["path"] == "notes.txt"
+        ));
+
+        let error = tool_events(vec!["{\"data\": \"", &"x".repeat(40), "\"}"])
+            .accumulate()
+            .max_tool_input_bytes(32)
+            .accumulate()
+            .await
+            .unwrap_err();
+        assert!(matches!(error, crate::Error::Stream(_)));
+        assert!(error.to_string().contains("exceeded the 32-byte limit"));
+
+        assert_eq!(
+            MessageAccumulator::DEFAULT_MAX_TOOL_INPUT_BYTES,
+            1024 * 1024
+        );
+    }
+
+    #[test]
+    fn test_events_from_reader_parses_fixture() {
+        let transcript = include_str!("../tests/fixtures/message_stream.sse");
+        let events: Vec<StreamEvent> = crate::streaming::events_from_reader(transcript.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        // The ping is skipped and the final event is emitted without a trailing blank line
+        assert_eq!(events.len(), 7);
+        assert!(
+            matches!(&events[0], StreamEvent::MessageStart { message } if message.id == "msg_fixture")
+        );
+        assert!(matches!(
+            events[1],
+            StreamEvent::ContentBlockStart { index: 0, .. }
+        ));
+        assert_eq!(events[2].text_delta(), Some("Hello"));
+        assert_eq!(events[3].text_delta(), Some(", world"));
+        assert!(matches!(
+            events[4],
+            StreamEvent::ContentBlockStop { index: 0 }
+        ));
+        assert!(matches!(
+            &events[5],
+            StreamEvent::MessageDelta { delta } if delta.stop_reason == Some(StopReason::EndTurn)
+        ));
+        assert!(matches!(events[6], StreamEvent::MessageStop));
+    }
+
+    #[test]
+    fn test_events_from_reader_multiline_data_and_errors() {
+        let transcript = "data: {\"type\":\r\ndata: \"message_stop\"}\r\n\r\n\
+                          event: error\n\
+                          data: {\"type\": \"error\", \"error\": {\"type\": \"overloaded_error\", \"message\": \"Overloaded\"}}\n\n";
+        let events: Vec<_> = crate::streaming::events_from_reader(transcript.as_bytes()).collect();
+
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], Ok(StreamEvent::MessageStop)));
+        assert!(matches!(&events[1], Err(error) if error.is_overloaded()));
+    }
 }
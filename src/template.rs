@@ -0,0 +1,140 @@
+//! Prompt templates with `{{variable}}` placeholders
+
+use std::collections::HashMap;
+
+use crate::{error::Error, Result};
+
+/// A prompt with `{{name}}` placeholders substituted at render time
+///
+/// Whitespace inside a placeholder is ignored, so `{{ name }}` and `{{name}}` are
+/// equivalent. Single braces are always literal, which keeps JSON examples in prompts
+/// intact. Prefix a placeholder with a backslash (`\{{name}}`) to emit it verbatim.
+///
+/// # Examples
+///
+/// ```rust
+/// use anthropic_rust::PromptTemplate;
+/// use std::collections::HashMap;
+///
+/// let template = PromptTemplate::new("Summarize this {{kind}} in {{words}} words.");
+/// let vars = HashMap::from([
+///     ("kind".to_string(), "article".to_string()),
+///     ("words".to_string(), "50".to_string()),
+/// ]);
+/// assert_eq!(template.render(&vars)?, "Summarize this article in 50 words.");
+/// # Ok::<(), anthropic_rust::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplate {
+    source: String,
+    keep_missing: bool,
+}
+
+impl PromptTemplate {
+    /// Create a template from a string with `{{name}}` placeholders
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            keep_missing: false,
+        }
+    }
+
+    /// Leave placeholders without a value in the output instead of failing (defaults to false)
+    pub fn keep_missing(mut self, keep: bool) -> Self {
+        self.keep_missing = keep;
+        self
+    }
+
+    /// Substitute `vars` into the template
+    ///
+    /// Returns `Error::Config` for an unclosed placeholder, or for a placeholder with
+    /// no value unless [`keep_missing`](Self::keep_missing) is enabled.
+    pub fn render(&self, vars: &HashMap<String, String>) -> Result<String> {
+        let mut output = String::with_capacity(self.source.len());
+        let mut rest = self.source.as_str();
+
+        while let Some(start) = rest.find("{{") {
+            if let Some(literal) = rest[..start].strip_suffix('\\') {
+                output.push_str(literal);
+                output.push_str("{{");
+                rest = &rest[start + 2..];
+                continue;
+            }
+
+            output.push_str(&rest[..start]);
+            let placeholder = &rest[start..];
+            let end = placeholder.find("}}").ok_or_else(|| {
+                Error::Config(format!(
+                    "Unclosed placeholder in prompt template: {}",
+                    placeholder
+                ))
+            })?;
+            let name = placeholder[2..end].trim();
+
+            match vars.get(name) {
+                Some(value) => output.push_str(value),
+                None if self.keep_missing => output.push_str(&placeholder[..end + 2]),
+                None => {
+                    return Err(Error::Config(format!(
+                        "Missing value for prompt template variable '{}'",
+                        name
+                    )))
+                }
+            }
+            rest = &placeholder[end + 2..];
+        }
+
+        output.push_str(rest);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_render_substitutes_variables() {
+        let template = PromptTemplate::new("Hello {{name}}, welcome to {{ place }}! {{name}}?");
+        let rendered = template
+            .render(&vars(&[("name", "Ada"), ("place", "Rust")]))
+            .unwrap();
+        assert_eq!(rendered, "Hello Ada, welcome to Rust! Ada?");
+    }
+
+    #[test]
+    fn test_render_missing_variable() {
+        let template = PromptTemplate::new("Hello {{name}} from {{place}}");
+        let error = template.render(&vars(&[("name", "Ada")])).unwrap_err();
+        assert!(matches!(error, Error::Config(_)));
+        assert!(error.to_string().contains("'place'"));
+
+        let kept = template
+            .keep_missing(true)
+            .render(&vars(&[("name", "Ada")]))
+            .unwrap();
+        assert_eq!(kept, "Hello Ada from {{place}}");
+    }
+
+    #[test]
+    fn test_render_literal_braces() {
+        let template = PromptTemplate::new(r#"Reply as {"answer": "{{answer}}"} not \{{answer}}"#);
+        let rendered = template.render(&vars(&[("answer", "42")])).unwrap();
+        assert_eq!(rendered, r#"Reply as {"answer": "42"} not {{answer}}"#);
+    }
+
+    #[test]
+    fn test_render_unclosed_placeholder() {
+        let error = PromptTemplate::new("Hello {{name")
+            .render(&HashMap::new())
+            .unwrap_err();
+        assert!(error.to_string().contains("Unclosed placeholder"));
+    }
+}
@@ -0,0 +1,112 @@
+//! Offline token estimation
+//!
+//! Enabled with the `token-estimator` feature. Estimates come from a heuristic that
+//! mimics how byte-pair encoders split text; they are approximate and can differ
+//! from the API's count by a noticeable margin. Use
+//! [`Client::count_tokens`](crate::Client::count_tokens) when an exact count matters.
+
+/// Approximate token counter that works without network access
+///
+/// Useful in CI or air-gapped environments where the token counting endpoint is
+/// unreachable. The estimate is deterministic: the same text always yields the
+/// same count.
+///
+/// # Examples
+///
+/// ```rust
+/// use anthropic_rust::TokenEstimator;
+///
+/// let tokens = TokenEstimator::estimate("The quick brown fox jumps over the lazy dog.");
+/// assert!((8..=12).contains(&tokens));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenEstimator;
+
+impl TokenEstimator {
+    /// Estimate the number of tokens in `text`
+    ///
+    /// The result is approximate; see the [module documentation](self).
+    pub fn estimate(text: &str) -> u32 {
+        let mut tokens = 0usize;
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c.is_ascii_alphabetic() {
+                // Common words are a single token; longer ones split into sub-words
+                let mut len = 1usize;
+                while chars.next_if(char::is_ascii_alphabetic).is_some() {
+                    len += 1;
+                }
+                tokens += len.div_ceil(6);
+            } else if c.is_ascii_digit() {
+                // Numbers are split into groups of up to three digits
+                let mut len = 1usize;
+                while chars.next_if(char::is_ascii_digit).is_some() {
+                    len += 1;
+                }
+                tokens += len.div_ceil(3);
+            } else if c.is_whitespace() {
+                // A single space merges into the following word; other runs of
+                // whitespace (indentation, newlines) cost a token
+                let mut len = 1;
+                while chars.next_if(|c| c.is_whitespace()).is_some() {
+                    len += 1;
+                }
+                if c != ' ' || len > 1 {
+                    tokens += 1;
+                }
+            } else {
+                // Punctuation and non-ASCII characters are roughly a token each
+                tokens += 1;
+            }
+        }
+
+        u32::try_from(tokens).unwrap_or(u32::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_within(text: &str, reference: u32, tolerance: f64) {
+        let estimate = TokenEstimator::estimate(text);
+        let band = (reference as f64 * tolerance).ceil() as u32;
+        assert!(
+            estimate.abs_diff(reference) <= band,
+            "estimate {} for {:?} outside {} ± {}",
+            estimate,
+            text,
+            reference,
+            band
+        );
+    }
+
+    #[test]
+    fn test_estimate_within_band_of_reference_counts() {
+        assert_within("Hello, world!", 4, 0.25);
+        assert_within("The quick brown fox jumps over the lazy dog.", 10, 0.25);
+        assert_within(
+            "Rust is a multi-paradigm, general-purpose programming language that \
+             emphasizes performance, type safety, and concurrency.",
+            24,
+            0.3,
+        );
+        assert_within("fn main() {\n    println!(\"hi\");\n}", 14, 0.4);
+    }
+
+    #[test]
+    fn test_estimate_edge_cases() {
+        assert_eq!(TokenEstimator::estimate(""), 0);
+        assert_eq!(TokenEstimator::estimate("1234567"), 3);
+        assert_eq!(TokenEstimator::estimate("こんにちは"), 5);
+    }
+
+    #[test]
+    fn test_estimate_is_deterministic() {
+        let text = "Deterministic estimates make offline budgets reproducible.";
+        let first = TokenEstimator::estimate(text);
+        assert!(first > 0);
+        assert_eq!(TokenEstimator::estimate(text), first);
+    }
+}
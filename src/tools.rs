@@ -4,6 +4,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::types::CacheControl;
+
 #[cfg(feature = "schemars")]
 use schemars::JsonSchema;
 
@@ -14,6 +16,8 @@ pub struct Tool {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub input_schema: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
 }
 
 impl Tool {
@@ -23,12 +27,44 @@ impl Tool {
     }
 }
 
+/// JSON schema type hint for a tool parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParamType {
+    String,
+    Number,
+    Integer,
+    Boolean,
+    Object,
+    Array,
+}
+
+impl ParamType {
+    /// Get the JSON schema type name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParamType::String => "string",
+            ParamType::Number => "number",
+            ParamType::Integer => "integer",
+            ParamType::Boolean => "boolean",
+            ParamType::Object => "object",
+            ParamType::Array => "array",
+        }
+    }
+}
+
+impl std::fmt::Display for ParamType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Builder for creating tools
 #[derive(Debug)]
 pub struct ToolBuilder {
     name: String,
     description: Option<String>,
     schema: serde_json::Value,
+    cache_control: Option<CacheControl>,
 }
 
 impl ToolBuilder {
@@ -42,6 +78,7 @@ impl ToolBuilder {
                 "properties": {},
                 "required": []
             }),
+            cache_control: None,
         }
     }
 
@@ -116,12 +153,47 @@ impl ToolBuilder {
         self
     }
 
+    /// Add a described parameter using a typed schema hint
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use anthropic_rust::{ParamType, Tool};
+    ///
+    /// let tool = Tool::builder("get_weather")
+    ///     .description("Get the current weather")
+    ///     .param("location", ParamType::String, "City and country", true)
+    ///     .param("days", ParamType::Integer, "Forecast length", false)
+    ///     .build();
+    ///
+    /// assert_eq!(tool.input_schema["properties"]["days"]["type"], "integer");
+    /// ```
+    pub fn param(
+        self,
+        name: impl Into<String>,
+        type_hint: ParamType,
+        description: impl Into<String>,
+        required: bool,
+    ) -> Self {
+        self.property(name, type_hint.as_str(), Some(description), required)
+    }
+
+    /// Mark the tool as a prompt cache breakpoint
+    ///
+    /// Caching applies to every tool definition up to and including this one, so
+    /// this is usually set on the last tool in the request.
+    pub fn cached(mut self) -> Self {
+        self.cache_control = Some(CacheControl::ephemeral());
+        self
+    }
+
     /// Build the tool
     pub fn build(self) -> Tool {
         Tool {
             name: self.name,
             description: self.description,
             input_schema: self.schema,
+            cache_control: self.cache_control,
         }
     }
 }
@@ -198,6 +270,29 @@ mod tests {
         assert_eq!(tool.input_schema["required"], json!([]));
     }
 
+    #[test]
+    fn test_cached_tool_serialization() {
+        let plain = serde_json::to_value(Tool::builder("search").build()).unwrap();
+        assert!(plain.get("cache_control").is_none());
+
+        let cached = Tool::builder("search").cached().build();
+        assert_eq!(cached.cache_control, Some(CacheControl::ephemeral()));
+        let serialized = serde_json::to_value(&cached).unwrap();
+        assert_eq!(serialized["cache_control"], json!({"type": "ephemeral"}));
+
+        // Each cached tool is a breakpoint counted against the per-request limit
+        let model = crate::Model::Claude35Sonnet20241022;
+        let tools: Vec<Tool> = (0..=crate::ChatRequest::MAX_CACHE_BREAKPOINTS)
+            .map(|i| Tool::builder(format!("tool_{}", i)).cached().build())
+            .collect();
+        let mut request = crate::ChatRequest::user("Hi");
+        request.tools = Some(tools);
+        assert!(request.validate(&model, 16).is_err());
+
+        request.tools.as_mut().unwrap().pop();
+        assert!(request.validate(&model, 16).is_ok());
+    }
+
     #[test]
     fn test_tool_creation_with_description() {
         let tool = Tool::builder("calculator")
@@ -498,6 +593,41 @@ mod tests {
         assert!(required.contains(&json!("new_prop")));
     }
 
+    #[test]
+    fn test_tool_builder_param_matches_hand_written_schema() {
+        let tool = Tool::builder("get_weather")
+            .param("location", ParamType::String, "City and country", true)
+            .param("days", ParamType::Integer, "Forecast length", false)
+            .build();
+
+        let expected = json!({
+            "type": "object",
+            "properties": {
+                "location": {
+                    "type": "string",
+                    "description": "City and country"
+                },
+                "days": {
+                    "type": "integer",
+                    "description": "Forecast length"
+                }
+            },
+            "required": ["location"]
+        });
+
+        assert_eq!(tool.input_schema, expected);
+    }
+
+    #[test]
+    fn test_param_type_names() {
+        assert_eq!(ParamType::String.as_str(), "string");
+        assert_eq!(ParamType::Number.as_str(), "number");
+        assert_eq!(ParamType::Integer.as_str(), "integer");
+        assert_eq!(ParamType::Boolean.as_str(), "boolean");
+        assert_eq!(ParamType::Object.as_str(), "object");
+        assert_eq!(ParamType::Array.to_string(), "array");
+    }
+
     #[test]
     fn test_tool_builder_duplicate_required_property() {
         let tool = Tool::builder("test_tool")
@@ -4,7 +4,10 @@
 //! with the Anthropic API, including message structures, content blocks, and
 //! configuration enums.
 
-use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Deserializer, Serialize};
 use url::Url;
 
 /// Available Claude models with their capabilities and token limits.
@@ -21,12 +24,14 @@ use url::Url;
 /// ```rust
 /// use anthropic_rust::Model;
 ///
-/// // Get the maximum tokens for a model
-/// let max_tokens = Model::Claude35Sonnet20241022.max_tokens();
-/// println!("Max tokens: {}", max_tokens);
+/// // Get the context window and output limit for a model
+/// let context_window = Model::Claude35Sonnet20241022.context_window();
+/// let max_output = Model::Claude35Sonnet20241022.max_output_tokens();
+/// println!("Context window: {}, max output: {}", context_window, max_output);
 ///
 /// // Compare models
-/// assert_eq!(Model::Claude3Haiku20240307.max_tokens(), 200_000);
+/// assert_eq!(Model::Claude3Haiku20240307.context_window(), 200_000);
+/// assert_eq!(Model::Claude3Haiku20240307.max_output_tokens(), 4096);
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -43,11 +48,48 @@ pub enum Model {
     Claude35Sonnet20250114,
     #[serde(rename = "claude-4-sonnet-20250514")]
     Claude4Sonnet20250514,
+    /// A model identifier this SDK version does not know about, kept verbatim
+    ///
+    /// Lets responses from newly released models deserialize, and lets requests
+    /// target them before the SDK adds a dedicated variant.
+    #[serde(untagged)]
+    Other(String),
 }
 
 impl Model {
+    /// Every model with a dedicated variant
+    const KNOWN: &'static [Model] = &[
+        Model::Claude3Haiku20240307,
+        Model::Claude3Sonnet20240229,
+        Model::Claude3Opus20240229,
+        Model::Claude35Sonnet20241022,
+        Model::Claude35Sonnet20250114,
+        Model::Claude4Sonnet20250514,
+    ];
+
+    /// Get the model identifier used by the API
+    pub fn as_str(&self) -> &str {
+        match self {
+            Model::Claude3Haiku20240307 => "claude-3-haiku-20240307",
+            Model::Claude3Sonnet20240229 => "claude-3-sonnet-20240229",
+            Model::Claude3Opus20240229 => "claude-3-opus-20240229",
+            Model::Claude35Sonnet20241022 => "claude-3-5-sonnet-20241022",
+            Model::Claude35Sonnet20250114 => "claude-3-5-sonnet-20250114",
+            Model::Claude4Sonnet20250514 => "claude-4-sonnet-20250514",
+            Model::Other(id) => id,
+        }
+    }
+
     /// Returns the maximum tokens supported by this model
+    #[deprecated(
+        note = "returns the context window, not the output limit; use `context_window` or `max_output_tokens`"
+    )]
     pub fn max_tokens(&self) -> u32 {
+        self.context_window()
+    }
+
+    /// Returns the context window of this model (input and output tokens combined)
+    pub fn context_window(&self) -> u32 {
         match self {
             Model::Claude3Haiku20240307 => 200_000,
             Model::Claude3Sonnet20240229 => 200_000,
@@ -55,8 +97,89 @@ impl Model {
             Model::Claude35Sonnet20241022 => 200_000,
             Model::Claude35Sonnet20250114 => 200_000,
             Model::Claude4Sonnet20250514 => 200_000,
+            Model::Other(_) => 200_000,
+        }
+    }
+
+    /// Returns the maximum number of output tokens this model can generate in one response
+    pub fn max_output_tokens(&self) -> u32 {
+        match self {
+            Model::Claude3Haiku20240307 => 4_096,
+            Model::Claude3Sonnet20240229 => 4_096,
+            Model::Claude3Opus20240229 => 4_096,
+            Model::Claude35Sonnet20241022 => 8_192,
+            Model::Claude35Sonnet20250114 => 8_192,
+            Model::Claude4Sonnet20250514 => 64_000,
+            // Every known model can produce at least this many tokens
+            Model::Other(_) => 4_096,
+        }
+    }
+
+    /// Returns the family this model belongs to
+    pub fn family(&self) -> ModelFamily {
+        match self {
+            Model::Claude3Haiku20240307 => ModelFamily::Haiku,
+            Model::Claude3Sonnet20240229
+            | Model::Claude35Sonnet20241022
+            | Model::Claude35Sonnet20250114
+            | Model::Claude4Sonnet20250514 => ModelFamily::Sonnet,
+            Model::Claude3Opus20240229 => ModelFamily::Opus,
+            Model::Other(id) if id.contains("haiku") => ModelFamily::Haiku,
+            Model::Other(id) if id.contains("opus") => ModelFamily::Opus,
+            Model::Other(_) => ModelFamily::Sonnet,
+        }
+    }
+}
+
+impl std::fmt::Display for Model {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Model {
+    type Err = crate::Error;
+
+    /// Parse a known model identifier
+    ///
+    /// Unlike deserialization, unknown identifiers are rejected with `Error::Model`;
+    /// construct [`Model::Other`] explicitly to target a model the SDK does not list.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Model::KNOWN
+            .iter()
+            .find(|model| model.as_str() == s)
+            .cloned()
+            .ok_or_else(|| crate::Error::Model(format!("Unknown model: {}", s)))
+    }
+}
+
+/// Model family, grouping models of similar speed, cost, and capability
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModelFamily {
+    Haiku,
+    Sonnet,
+    Opus,
+}
+
+impl ModelFamily {
+    /// Recommended `max_tokens` for non-streaming requests to this family
+    ///
+    /// Long non-streaming generations risk running into HTTP timeouts, so this stays
+    /// below the output limit of the family's larger models.
+    pub fn recommended_max_tokens(&self) -> u32 {
+        match self {
+            ModelFamily::Haiku => 4_096,
+            ModelFamily::Sonnet => 8_192,
+            ModelFamily::Opus => 4_096,
         }
     }
+
+    /// Whether long responses from this family are better streamed
+    ///
+    /// Haiku generates quickly enough that non-streaming requests rarely time out.
+    pub fn streaming_recommended(&self) -> bool {
+        !matches!(self, ModelFamily::Haiku)
+    }
 }
 
 /// Message role indicating who sent the message.
@@ -105,6 +228,7 @@ pub enum Role {
 ///     StopReason::MaxTokens => println!("Response was truncated due to token limit"),
 ///     StopReason::StopSequence => println!("Response stopped at a stop sequence"),
 ///     StopReason::ToolUse => println!("Response ended to use a tool"),
+///     StopReason::PauseTurn => println!("Long-running turn paused; resume to continue"),
 /// }
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -118,6 +242,8 @@ pub enum StopReason {
     StopSequence,
     /// Claude wants to use a tool
     ToolUse,
+    /// A long-running turn was paused and can be resumed by resending the response
+    PauseTurn,
 }
 
 /// Token usage information for a request/response.
@@ -144,6 +270,9 @@ pub enum StopReason {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Usage {
     /// Number of input tokens (from your messages)
+    ///
+    /// Streaming `message_delta` usage usually omits this field, so it defaults to zero.
+    #[serde(default)]
     pub input_tokens: u32,
     /// Number of output tokens (from Claude's response)
     pub output_tokens: u32,
@@ -155,6 +284,88 @@ pub struct Usage {
     pub cache_read_input_tokens: Option<u32>,
 }
 
+impl Usage {
+    /// Create an empty usage record, useful as the starting point for aggregation
+    pub fn zero() -> Self {
+        Self {
+            input_tokens: 0,
+            output_tokens: 0,
+            cache_creation_input_tokens: None,
+            cache_read_input_tokens: None,
+        }
+    }
+
+    /// Signed per-field difference between this usage and `baseline`
+    ///
+    /// Positive values mean this usage is higher. Cache fields are `None` only when
+    /// absent on both sides; a missing side otherwise counts as zero.
+    pub fn delta(&self, baseline: &Usage) -> UsageDelta {
+        UsageDelta {
+            input_tokens: i64::from(self.input_tokens) - i64::from(baseline.input_tokens),
+            output_tokens: i64::from(self.output_tokens) - i64::from(baseline.output_tokens),
+            cache_creation_input_tokens: sub_cache_tokens(
+                self.cache_creation_input_tokens,
+                baseline.cache_creation_input_tokens,
+            ),
+            cache_read_input_tokens: sub_cache_tokens(
+                self.cache_read_input_tokens,
+                baseline.cache_read_input_tokens,
+            ),
+        }
+    }
+}
+
+/// Signed token differences between two [`Usage`] records, from [`Usage::delta`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct UsageDelta {
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<i64>,
+}
+
+/// Subtract optional cache counters, treating a missing side as zero
+fn sub_cache_tokens(current: Option<u32>, baseline: Option<u32>) -> Option<i64> {
+    match (current, baseline) {
+        (None, None) => None,
+        (current, baseline) => {
+            Some(i64::from(current.unwrap_or(0)) - i64::from(baseline.unwrap_or(0)))
+        }
+    }
+}
+
+/// Sum optional cache counters, treating a missing side as zero
+fn add_cache_tokens(lhs: Option<u32>, rhs: Option<u32>) -> Option<u32> {
+    match (lhs, rhs) {
+        (None, None) => None,
+        (lhs, rhs) => Some(lhs.unwrap_or(0).saturating_add(rhs.unwrap_or(0))),
+    }
+}
+
+impl std::ops::Add for Usage {
+    type Output = Usage;
+
+    fn add(mut self, rhs: Usage) -> Usage {
+        self += rhs;
+        self
+    }
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, rhs: Usage) {
+        self.input_tokens = self.input_tokens.saturating_add(rhs.input_tokens);
+        self.output_tokens = self.output_tokens.saturating_add(rhs.output_tokens);
+        self.cache_creation_input_tokens = add_cache_tokens(
+            self.cache_creation_input_tokens,
+            rhs.cache_creation_input_tokens,
+        );
+        self.cache_read_input_tokens =
+            add_cache_tokens(self.cache_read_input_tokens, rhs.cache_read_input_tokens);
+    }
+}
+
 /// Content block types
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -163,12 +374,19 @@ pub enum ContentBlock {
         text: String,
         #[serde(skip_serializing_if = "Option::is_none")]
         citations: Option<Vec<Citation>>,
+        /// Prompt cache breakpoint covering everything up to this block
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
     },
     Image {
         source: ImageSource,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
     },
     Document {
         source: DocumentSource,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
     },
     ToolUse {
         id: String,
@@ -180,7 +398,14 @@ pub enum ContentBlock {
         content: Vec<ContentBlock>,
         #[serde(skip_serializing_if = "Option::is_none")]
         is_error: Option<bool>,
+        /// Prompt cache breakpoint, useful for large retrieved documents
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
     },
+    /// Extended thinking produced by the model
+    Thinking { thinking: String, signature: String },
+    /// Thinking content that was redacted by safety systems
+    RedactedThinking { data: String },
 }
 
 impl ContentBlock {
@@ -189,6 +414,52 @@ impl ContentBlock {
         Self::Text {
             text: content.into(),
             citations: None,
+            cache_control: None,
+        }
+    }
+
+    /// Create a text content block marked as a prompt cache breakpoint
+    pub fn text_cached(content: impl Into<String>) -> Self {
+        Self::text(content).cached()
+    }
+
+    /// Mark this block as an ephemeral prompt cache breakpoint
+    ///
+    /// Applies to text, image, document, and tool result blocks; other blocks cannot
+    /// carry `cache_control` and are returned unchanged.
+    pub fn cached(self) -> Self {
+        self.with_cache_control(CacheControl::ephemeral())
+    }
+
+    /// Set the prompt cache breakpoint on this block
+    ///
+    /// Blocks that cannot carry `cache_control` are returned unchanged.
+    pub fn with_cache_control(mut self, control: CacheControl) -> Self {
+        match &mut self {
+            Self::Text { cache_control, .. }
+            | Self::Image { cache_control, .. }
+            | Self::Document { cache_control, .. }
+            | Self::ToolResult { cache_control, .. } => *cache_control = Some(control),
+            Self::ToolUse { .. } | Self::Thinking { .. } | Self::RedactedThinking { .. } => {}
+        }
+        self
+    }
+
+    /// Number of `cache_control` markers on this block and any blocks nested in it
+    fn cache_breakpoints(&self) -> usize {
+        match self {
+            Self::Text { cache_control, .. }
+            | Self::Image { cache_control, .. }
+            | Self::Document { cache_control, .. } => usize::from(cache_control.is_some()),
+            Self::ToolResult {
+                content,
+                cache_control,
+                ..
+            } => {
+                usize::from(cache_control.is_some())
+                    + content.iter().map(Self::cache_breakpoints).sum::<usize>()
+            }
+            Self::ToolUse { .. } | Self::Thinking { .. } | Self::RedactedThinking { .. } => 0,
         }
     }
 
@@ -199,6 +470,7 @@ impl ContentBlock {
                 media_type,
                 data: data.into(),
             },
+            cache_control: None,
         }
     }
 
@@ -209,9 +481,20 @@ impl ContentBlock {
             .map_err(|_| crate::Error::Config("Invalid image URL".to_string()))?;
         Ok(Self::Image {
             source: ImageSource::Url { url },
+            cache_control: None,
         })
     }
 
+    /// Create an image content block referencing an uploaded file
+    pub fn image_file(file_id: impl Into<String>) -> Self {
+        Self::Image {
+            source: ImageSource::File {
+                file_id: file_id.into(),
+            },
+            cache_control: None,
+        }
+    }
+
     /// Create a tool use content block
     pub fn tool_use(
         id: impl Into<String>,
@@ -232,6 +515,7 @@ impl ContentBlock {
                 media_type,
                 data: data.into(),
             },
+            cache_control: None,
         }
     }
 
@@ -242,19 +526,175 @@ impl ContentBlock {
             .map_err(|_| crate::Error::Config("Invalid document URL".to_string()))?;
         Ok(Self::Document {
             source: DocumentSource::Url { url },
+            cache_control: None,
         })
     }
 
+    /// Create a document content block referencing an uploaded file
+    pub fn document_file(file_id: impl Into<String>) -> Self {
+        Self::Document {
+            source: DocumentSource::File {
+                file_id: file_id.into(),
+            },
+            cache_control: None,
+        }
+    }
+
     /// Create a tool result content block
     pub fn tool_result(tool_use_id: impl Into<String>, content: impl Into<String>) -> Self {
         Self::ToolResult {
             tool_use_id: tool_use_id.into(),
             content: vec![Self::text(content.into())],
             is_error: None,
+            cache_control: None,
+        }
+    }
+
+    /// Create a tool result content block marked as a prompt cache breakpoint
+    pub fn tool_result_cached(tool_use_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::tool_result(tool_use_id, content).cached()
+    }
+
+    /// Approximate serialized size of the block in bytes
+    ///
+    /// Payload lengths plus the fixed JSON framing of each variant; string escaping
+    /// is not accounted for. Tool inputs are serialized to measure them.
+    pub fn estimated_bytes(&self) -> usize {
+        // Allowance for a serialized media type such as "application/pdf"
+        const MEDIA_TYPE_BYTES: usize = 16;
+
+        match self {
+            ContentBlock::Text {
+                text,
+                citations,
+                cache_control,
+            } => {
+                let citations = citations.as_ref().map_or(0, |citations| {
+                    16 + citations
+                        .iter()
+                        .map(|citation| 50 + citation.source.len())
+                        .sum::<usize>()
+                });
+                25 + text.len() + citations + cache_control_bytes(cache_control)
+            }
+            ContentBlock::Image {
+                source,
+                cache_control,
+            } => {
+                cache_control_bytes(cache_control)
+                    + match source {
+                        ImageSource::Base64 { data, .. } => 69 + MEDIA_TYPE_BYTES + data.len(),
+                        ImageSource::Url { url } => 49 + url.as_str().len(),
+                        ImageSource::File { file_id } => 54 + file_id.len(),
+                    }
+            }
+            ContentBlock::Document {
+                source,
+                cache_control,
+            } => {
+                cache_control_bytes(cache_control)
+                    + match source {
+                        DocumentSource::Base64 { data, .. } => 72 + MEDIA_TYPE_BYTES + data.len(),
+                        DocumentSource::Url { url } => 52 + url.as_str().len(),
+                        DocumentSource::File { file_id } => 57 + file_id.len(),
+                    }
+            }
+            ContentBlock::ToolUse { id, name, input } => {
+                46 + id.len() + name.len() + input.to_string().len()
+            }
+            ContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+                cache_control,
+            } => {
+                let is_error = if is_error.is_some() { 16 } else { 0 };
+                52 + tool_use_id.len()
+                    + is_error
+                    + cache_control_bytes(cache_control)
+                    + content
+                        .iter()
+                        .map(|block| block.estimated_bytes() + 1)
+                        .sum::<usize>()
+            }
+            ContentBlock::Thinking {
+                thinking,
+                signature,
+            } => 48 + thinking.len() + signature.len(),
+            ContentBlock::RedactedThinking { data } => 38 + data.len(),
+        }
+    }
+
+    /// Compare two blocks, ignoring the order of citations
+    ///
+    /// `PartialEq` stays strict; this is intended for snapshot-style comparisons of
+    /// responses whose citations may arrive in a different order.
+    pub fn semantically_eq(&self, other: &ContentBlock) -> bool {
+        match (self, other) {
+            (
+                Self::Text {
+                    text,
+                    citations,
+                    cache_control,
+                },
+                Self::Text {
+                    text: other_text,
+                    citations: other_citations,
+                    cache_control: other_cache_control,
+                },
+            ) => {
+                text == other_text
+                    && cache_control == other_cache_control
+                    && sorted_citations(citations) == sorted_citations(other_citations)
+            }
+            (
+                Self::ToolResult {
+                    tool_use_id,
+                    content,
+                    is_error,
+                    cache_control,
+                },
+                Self::ToolResult {
+                    tool_use_id: other_id,
+                    content: other_content,
+                    is_error: other_is_error,
+                    cache_control: other_cache_control,
+                },
+            ) => {
+                tool_use_id == other_id
+                    && is_error == other_is_error
+                    && cache_control == other_cache_control
+                    && content.len() == other_content.len()
+                    && content
+                        .iter()
+                        .zip(other_content)
+                        .all(|(a, b)| a.semantically_eq(b))
+            }
+            _ => self == other,
         }
     }
 }
 
+/// Serialized size of an optional `"cache_control":{"type":"ephemeral"}` entry
+fn cache_control_bytes(cache_control: &Option<CacheControl>) -> usize {
+    if cache_control.is_some() {
+        37
+    } else {
+        0
+    }
+}
+
+/// Citations in a canonical order for order-insensitive comparison
+fn sorted_citations(citations: &Option<Vec<Citation>>) -> Option<Vec<&Citation>> {
+    citations.as_ref().map(|citations| {
+        let mut sorted: Vec<&Citation> = citations.iter().collect();
+        sorted.sort_by(|a, b| {
+            (a.start_index, a.end_index, &a.source).cmp(&(b.start_index, b.end_index, &b.source))
+        });
+        sorted
+    })
+}
+
 /// Image source types
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -266,6 +706,10 @@ pub enum ImageSource {
     Url {
         url: Url,
     },
+    /// Reference to a previously uploaded file
+    File {
+        file_id: String,
+    },
 }
 
 /// Document source types
@@ -279,6 +723,10 @@ pub enum DocumentSource {
     Url {
         url: Url,
     },
+    /// Reference to a previously uploaded file
+    File {
+        file_id: String,
+    },
 }
 
 /// Supported image media types
@@ -327,11 +775,153 @@ impl From<Message> for MessageParam {
     }
 }
 
+/// An ordered history of conversation turns
+///
+/// Use [`validate`](Self::validate) to catch structural errors locally before they are
+/// rejected by the API.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Conversation {
+    pub messages: Vec<MessageParam>,
+}
+
+impl Conversation {
+    /// Create an empty conversation
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a turn to the conversation
+    pub fn push(&mut self, message: impl Into<MessageParam>) {
+        self.messages.push(message.into());
+    }
+
+    /// Check the conversation is well formed
+    ///
+    /// The conversation must start with a user turn and alternate roles. Every turn
+    /// needs content and text blocks must not be empty. Each `tool_result` must answer
+    /// a `tool_use` from the assistant turn just before it, and every `tool_use` must
+    /// be answered in the following user turn unless it ends the conversation.
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        let invalid = |index: usize, reason: String| {
+            Err(crate::Error::InvalidRequest(format!(
+                "Invalid conversation at message {}: {}",
+                index, reason
+            )))
+        };
+
+        match self.messages.first() {
+            None => return invalid(0, "conversation is empty".to_string()),
+            Some(first) if first.role != Role::User => {
+                return invalid(0, "conversation must start with a user turn".to_string())
+            }
+            Some(_) => {}
+        }
+
+        let mut pending_tool_uses: Vec<&str> = Vec::new();
+        for (index, message) in self.messages.iter().enumerate() {
+            if index > 0 && self.messages[index - 1].role == message.role {
+                return invalid(
+                    index,
+                    format!("consecutive {:?} turns; roles must alternate", message.role),
+                );
+            }
+            if message.content.is_empty() {
+                return invalid(index, "turn has no content".to_string());
+            }
+
+            let mut answered = Vec::new();
+            for block in &message.content {
+                match (block, &message.role) {
+                    (ContentBlock::Text { text, .. }, _) if text.trim().is_empty() => {
+                        return invalid(index, "text blocks must not be empty".to_string());
+                    }
+                    (ContentBlock::ToolUse { .. }, Role::User) => {
+                        return invalid(
+                            index,
+                            "tool_use blocks must come from the assistant".to_string(),
+                        );
+                    }
+                    (ContentBlock::ToolResult { .. }, Role::Assistant) => {
+                        return invalid(
+                            index,
+                            "tool_result blocks must come from the user".to_string(),
+                        );
+                    }
+                    (ContentBlock::ToolResult { tool_use_id, .. }, Role::User) => {
+                        if !pending_tool_uses.contains(&tool_use_id.as_str()) {
+                            return invalid(
+                                index,
+                                format!("tool_result '{}' has no matching tool_use", tool_use_id),
+                            );
+                        }
+                        answered.push(tool_use_id.as_str());
+                    }
+                    _ => {}
+                }
+            }
+
+            if let Some(unanswered) = pending_tool_uses.iter().find(|id| !answered.contains(id)) {
+                return invalid(
+                    index,
+                    format!("tool_use '{}' has no tool_result", unanswered),
+                );
+            }
+            pending_tool_uses = message
+                .content
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::ToolUse { id, .. } => Some(id.as_str()),
+                    _ => None,
+                })
+                .collect();
+        }
+
+        Ok(())
+    }
+
+    /// Merge consecutive turns with the same role into a single turn
+    pub fn normalize(&mut self) {
+        let mut merged: Vec<MessageParam> = Vec::with_capacity(self.messages.len());
+
+        for message in std::mem::take(&mut self.messages) {
+            match merged.last_mut() {
+                Some(last) if last.role == message.role => last.content.extend(message.content),
+                _ => merged.push(message),
+            }
+        }
+
+        self.messages = merged;
+    }
+}
+
+impl From<Vec<MessageParam>> for Conversation {
+    fn from(messages: Vec<MessageParam>) -> Self {
+        Self { messages }
+    }
+}
+
+impl From<Conversation> for Vec<MessageParam> {
+    fn from(conversation: Conversation) -> Self {
+        conversation.messages
+    }
+}
+
 /// Complete message response
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
     pub id: String,
+    /// Top-level object type; always `"message"` when present
+    #[serde(
+        rename = "type",
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_message_type"
+    )]
+    pub message_type: Option<String>,
     pub role: Role,
+    /// Response content; a `null` or missing value is treated as empty
+    #[serde(default, deserialize_with = "deserialize_null_as_empty")]
     pub content: Vec<ContentBlock>,
     pub model: Model,
     pub stop_reason: Option<StopReason>,
@@ -339,92 +929,862 @@ pub struct Message {
     pub usage: Usage,
 }
 
-/// System message
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct SystemMessage {
-    #[serde(rename = "type")]
-    pub message_type: String,
-    pub text: String,
+/// Deserialize a `null` sequence as an empty vector
+fn deserialize_null_as_empty<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(Option::<Vec<T>>::deserialize(deserializer)?.unwrap_or_default())
 }
 
-/// Chat request structure
-#[derive(Debug, Clone, PartialEq, Serialize)]
-pub struct ChatRequest {
-    pub messages: Vec<MessageParam>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub system: Option<Vec<SystemMessage>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tools: Option<Vec<crate::tools::Tool>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub temperature: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub top_p: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub stop_sequences: Option<Vec<String>>,
+/// Accept a missing `type` or `"message"`, rejecting anything else
+fn deserialize_message_type<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let message_type = Option::<String>::deserialize(deserializer)?;
+    match message_type.as_deref() {
+        None | Some("message") => Ok(message_type),
+        Some(other) => Err(serde::de::Error::custom(format!(
+            "expected type \"message\", got \"{}\"",
+            other
+        ))),
+    }
 }
 
-/// Builder for chat requests
-#[derive(Debug, Default)]
-pub struct ChatRequestBuilder {
-    messages: Vec<MessageParam>,
-    system: Option<Vec<SystemMessage>>,
-    tools: Option<Vec<crate::tools::Tool>>,
-    temperature: Option<f32>,
-    top_p: Option<f32>,
-    stop_sequences: Option<Vec<String>>,
+/// A tool invocation requested by the model
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub input: serde_json::Value,
 }
 
-impl ChatRequestBuilder {
-    /// Create a new chat request builder
-    pub fn new() -> Self {
-        Self::default()
+impl Message {
+    /// Split the message into its concatenated text and the tool calls it requests
+    ///
+    /// Other block types, such as thinking, are skipped.
+    pub fn split(&self) -> (String, Vec<ToolCall>) {
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+
+        for block in &self.content {
+            match block {
+                ContentBlock::Text {
+                    text: block_text, ..
+                } => text.push_str(block_text),
+                ContentBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                    id: id.clone(),
+                    name: name.clone(),
+                    input: input.clone(),
+                }),
+                _ => {}
+            }
+        }
+
+        (text, tool_calls)
     }
 
-    /// Add a message with specified role and content
-    pub fn message(mut self, role: Role, content: ContentBlock) -> Self {
-        self.messages.push(MessageParam {
-            role,
-            content: vec![content],
+    /// Return a copy of the message with thinking and redacted thinking blocks removed
+    pub fn without_thinking(&self) -> Message {
+        let mut message = self.clone();
+        message.content.retain(|block| {
+            !matches!(
+                block,
+                ContentBlock::Thinking { .. } | ContentBlock::RedactedThinking { .. }
+            )
         });
-        self
+        message
     }
 
-    /// Add a message with specified role and multiple content blocks
-    pub fn message_with_content(mut self, role: Role, content: Vec<ContentBlock>) -> Self {
-        self.messages.push(MessageParam { role, content });
-        self
+    /// Concatenate the text of all thinking blocks in the message
+    pub fn thinking_text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Thinking { thinking, .. } => Some(thinking.as_str()),
+                _ => None,
+            })
+            .collect()
     }
 
-    /// Add multiple messages at once
-    pub fn messages(mut self, messages: Vec<MessageParam>) -> Self {
-        self.messages.extend(messages);
-        self
-    }
+    /// Merge runs of consecutive text blocks into single blocks
+    ///
+    /// Citation indices of each merged block are shifted by the character length of
+    /// the text before it. Non-text blocks are left in place and separate runs.
+    pub fn coalesce_text(&mut self) {
+        let mut merged: Vec<ContentBlock> = Vec::with_capacity(self.content.len());
+
+        for block in std::mem::take(&mut self.content) {
+            match (merged.last_mut(), block) {
+                (
+                    Some(ContentBlock::Text {
+                        text, citations, ..
+                    }),
+                    ContentBlock::Text {
+                        text: next_text,
+                        citations: next_citations,
+                        ..
+                    },
+                ) => {
+                    let offset = text.chars().count() as u32;
+                    if let Some(next_citations) = next_citations {
+                        citations.get_or_insert_with(Vec::new).extend(
+                            next_citations.into_iter().map(|citation| Citation {
+                                start_index: citation.start_index + offset,
+                                end_index: citation.end_index + offset,
+                                ..citation
+                            }),
+                        );
+                    }
+                    text.push_str(&next_text);
+                }
+                (_, block) => merged.push(block),
+            }
+        }
 
-    /// Add a user message
-    pub fn user_message(self, content: ContentBlock) -> Self {
-        self.message(Role::User, content)
+        self.content = merged;
     }
 
-    /// Add an assistant message
-    pub fn assistant_message(self, content: ContentBlock) -> Self {
-        self.message(Role::Assistant, content)
+    /// Get the stop sequence that ended generation, if the message stopped on one
+    pub fn matched_stop_sequence(&self) -> Option<&str> {
+        match self.stop_reason {
+            Some(StopReason::StopSequence) => self.stop_sequence.as_deref(),
+            _ => None,
+        }
     }
+}
 
-    /// Add a system message
-    pub fn system(mut self, content: impl Into<String>) -> Self {
-        let system_msg = SystemMessage {
-            message_type: "text".to_string(),
-            text: content.into(),
-        };
-        self.system.get_or_insert_with(Vec::new).push(system_msg);
-        self
-    }
+/// Prompt caching marker attached to cacheable request content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheControl {
+    #[serde(rename = "type")]
+    pub cache_type: String,
+}
 
-    /// Add a tool
-    pub fn tool(mut self, tool: crate::tools::Tool) -> Self {
-        self.tools.get_or_insert_with(Vec::new).push(tool);
-        self
+impl CacheControl {
+    /// Create an ephemeral cache breakpoint
+    pub fn ephemeral() -> Self {
+        Self {
+            cache_type: "ephemeral".to_string(),
+        }
+    }
+}
+
+/// Extended thinking configuration for a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThinkingConfig {
+    #[serde(rename = "type")]
+    pub thinking_type: String,
+    /// Maximum tokens the model may spend thinking; must be below `max_tokens`
+    pub budget_tokens: u32,
+}
+
+impl ThinkingConfig {
+    /// Smallest thinking budget the API accepts
+    pub const MIN_BUDGET_TOKENS: u32 = 1024;
+
+    /// Enable extended thinking with the given token budget
+    pub fn enabled(budget_tokens: u32) -> Self {
+        Self {
+            thinking_type: "enabled".to_string(),
+            budget_tokens,
+        }
+    }
+}
+
+/// How the model may use the tools provided with a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// The model decides whether to call a tool
+    Auto {
+        /// Call at most one tool per response
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        disable_parallel_tool_use: bool,
+    },
+    /// The model must call one of the provided tools
+    Any {
+        /// Call exactly one tool per response
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        disable_parallel_tool_use: bool,
+    },
+    /// The model must call the named tool
+    Tool {
+        name: String,
+        /// Call exactly one tool per response
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        disable_parallel_tool_use: bool,
+    },
+    /// The model must not call any tool
+    None,
+}
+
+impl ToolChoice {
+    /// Let the model decide whether to call a tool
+    pub fn auto() -> Self {
+        Self::Auto {
+            disable_parallel_tool_use: false,
+        }
+    }
+
+    /// Require a call to one of the provided tools
+    pub fn any() -> Self {
+        Self::Any {
+            disable_parallel_tool_use: false,
+        }
+    }
+
+    /// Force a call to the named tool
+    pub fn tool(name: impl Into<String>) -> Self {
+        Self::Tool {
+            name: name.into(),
+            disable_parallel_tool_use: false,
+        }
+    }
+
+    /// Set whether the model is limited to one tool call per response
+    ///
+    /// Has no effect on [`ToolChoice::None`], which allows no tool calls at all.
+    pub fn with_disable_parallel_tool_use(mut self, disable: bool) -> Self {
+        match &mut self {
+            Self::Auto {
+                disable_parallel_tool_use,
+            }
+            | Self::Any {
+                disable_parallel_tool_use,
+            }
+            | Self::Tool {
+                disable_parallel_tool_use,
+                ..
+            } => *disable_parallel_tool_use = disable,
+            Self::None => {}
+        }
+        self
+    }
+
+    /// Whether this choice requires the model to call a tool
+    pub fn forces_tool(&self) -> bool {
+        matches!(self, Self::Any { .. } | Self::Tool { .. })
+    }
+}
+
+/// Output format hint appended to the system prompt by
+/// [`ChatRequestBuilder::output_format`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputFormat {
+    /// Markdown formatting
+    Markdown,
+    /// Plain text without markup
+    PlainText,
+    /// A single JSON value
+    Json,
+}
+
+impl OutputFormat {
+    /// System prompt text requesting this format
+    pub fn system_instruction(&self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "Format your response using Markdown.",
+            OutputFormat::PlainText => {
+                "Respond in plain text only. Do not use Markdown or any other markup."
+            }
+            OutputFormat::Json => {
+                "Respond with a single valid JSON value and nothing else. \
+                 Do not wrap it in code fences or add commentary."
+            }
+        }
+    }
+}
+
+/// Request metadata sent with a chat request
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RequestMetadata {
+    /// Opaque identifier for the end user, used by Anthropic for abuse monitoring
+    ///
+    /// Use a hash or UUID rather than a name, email address, or phone number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+
+/// System message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemMessage {
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub text: String,
+    /// Prompt cache breakpoint covering the tools and system prompt up to this block
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+
+/// Chat request structure
+///
+/// New parameters may be added in minor releases, so build requests with
+/// [`ChatRequestBuilder`] or start from [`ChatRequest::default`] rather than a struct
+/// literal.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ChatRequest {
+    pub messages: Vec<MessageParam>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<Vec<SystemMessage>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<crate::tools::Tool>>,
+    /// How the model may use `tools`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Only sample from the `top_k` most likely tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    /// Sampling seed for reproducible output
+    ///
+    /// Sent whenever set so requests stay forward-compatible, even though the API may
+    /// not honour it yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Extended thinking configuration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<ThinkingConfig>,
+    /// Request metadata such as the end-user ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RequestMetadata>,
+    /// Beta features enabled through the request body
+    ///
+    /// Independent of the `anthropic-beta` header set with
+    /// [`ClientBuilder::beta_feature`](crate::ClientBuilder::beta_feature).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub betas: Option<Vec<String>>,
+    /// Additional top-level fields sent to the API verbatim, for parameters the SDK
+    /// does not model yet
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+    /// Number of candidate completions to generate with
+    /// [`Client::execute_chat_candidates`](crate::Client::execute_chat_candidates)
+    ///
+    /// The API has no candidate-count parameter, so this is never sent; candidates are
+    /// produced by issuing parallel requests.
+    #[serde(skip)]
+    pub candidate_count: Option<u32>,
+}
+
+/// Sampling strategy for a chat request
+///
+/// The API recommends adjusting only one of `temperature` and `top_p`; this enum
+/// makes that choice explicit when used with [`ChatRequestBuilder::sampling`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Sampling {
+    /// Use the API defaults for both parameters
+    #[default]
+    Default,
+    /// Sample with the given temperature
+    Temperature(f32),
+    /// Sample with the given nucleus (top_p) probability
+    TopP(f32),
+}
+
+impl ChatRequest {
+    /// Create a request with a single user text message and default parameters
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use anthropic_rust::ChatRequest;
+    ///
+    /// let request = ChatRequest::user("Hello, Claude!");
+    /// assert_eq!(request.messages.len(), 1);
+    /// ```
+    pub fn user(text: impl Into<String>) -> ChatRequest {
+        ChatRequestBuilder::new()
+            .user_message(ContentBlock::text(text))
+            .build()
+    }
+
+    /// Create a request from an existing conversation with default parameters
+    pub fn conversation(messages: Vec<MessageParam>) -> ChatRequest {
+        ChatRequest {
+            messages,
+            ..Default::default()
+        }
+    }
+
+    /// Clone this request with its system prompt replaced by `system`
+    ///
+    /// Useful for comparing system prompts over the same conversation.
+    pub fn with_system(&self, system: impl Into<String>) -> ChatRequest {
+        ChatRequest {
+            system: Some(vec![SystemMessage {
+                message_type: "text".to_string(),
+                text: system.into(),
+                cache_control: None,
+            }]),
+            ..self.clone()
+        }
+    }
+
+    /// Clone this request with a different temperature
+    pub fn with_temperature(&self, temperature: f32) -> ChatRequest {
+        ChatRequest {
+            temperature: Some(temperature),
+            ..self.clone()
+        }
+    }
+
+    /// Maximum number of `cache_control` breakpoints allowed in a single request
+    pub const MAX_CACHE_BREAKPOINTS: usize = 4;
+
+    /// Check parameter constraints that depend on the model and `max_tokens`
+    ///
+    /// Also enforces the [`MAX_CACHE_BREAKPOINTS`](Self::MAX_CACHE_BREAKPOINTS) limit.
+    /// The client calls this before sending a chat request, so violations surface as
+    /// `Error::InvalidRequest` instead of an API error.
+    pub fn validate(&self, model: &Model, max_tokens: u32) -> Result<(), crate::Error> {
+        let breakpoints = self.cache_breakpoints();
+        if breakpoints > Self::MAX_CACHE_BREAKPOINTS {
+            return Err(crate::Error::InvalidRequest(format!(
+                "Request has {} cache_control breakpoints; at most {} are allowed",
+                breakpoints,
+                Self::MAX_CACHE_BREAKPOINTS
+            )));
+        }
+
+        if let Some(thinking) = &self.thinking {
+            let budget = thinking.budget_tokens;
+            if budget < ThinkingConfig::MIN_BUDGET_TOKENS {
+                return Err(crate::Error::InvalidRequest(format!(
+                    "Thinking budget_tokens must be at least {}, got {}",
+                    ThinkingConfig::MIN_BUDGET_TOKENS,
+                    budget
+                )));
+            }
+            if budget >= max_tokens {
+                return Err(crate::Error::InvalidRequest(format!(
+                    "Thinking budget_tokens ({}) must be less than max_tokens ({})",
+                    budget, max_tokens
+                )));
+            }
+            if budget >= model.max_output_tokens() {
+                return Err(crate::Error::InvalidRequest(format!(
+                    "Thinking budget_tokens ({}) must be less than the model's output limit ({})",
+                    budget,
+                    model.max_output_tokens()
+                )));
+            }
+            if let Some(temperature) = self.temperature.filter(|t| *t != 1.0) {
+                return Err(crate::Error::InvalidRequest(format!(
+                    "temperature must be unset or 1.0 when extended thinking is enabled, got {}",
+                    temperature
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Count `cache_control` markers on tools, system blocks, and message content blocks,
+    /// including blocks nested in tool results
+    fn cache_breakpoints(&self) -> usize {
+        let tools = self.tools.iter().flatten();
+        let system = self.system.iter().flatten();
+        let blocks = self.messages.iter().flat_map(|message| &message.content);
+
+        tools.filter(|tool| tool.cache_control.is_some()).count()
+            + system.filter(|block| block.cache_control.is_some()).count()
+            + blocks.map(ContentBlock::cache_breakpoints).sum::<usize>()
+    }
+
+    /// Whether the response is fully determined by the request, making it safe to cache
+    pub(crate) fn is_deterministic(&self) -> bool {
+        self.temperature == Some(0.0)
+            && self.tools.as_ref().is_none_or(|tools| tools.is_empty())
+            && self.thinking.is_none()
+    }
+
+    /// Get the stop sequences configured on this request
+    pub fn configured_stop_sequences(&self) -> &[String] {
+        self.stop_sequences.as_deref().unwrap_or_default()
+    }
+
+    /// Approximate serialized size of the request in bytes, excluding `model` and `max_tokens`
+    ///
+    /// Sums [`ContentBlock::estimated_bytes`] over all messages plus the system prompt,
+    /// tools, and other parameters, so request size can be checked without
+    /// serializing large message histories.
+    pub fn estimated_bytes(&self) -> usize {
+        let messages: usize = self
+            .messages
+            .iter()
+            .map(|message| {
+                29 + message
+                    .content
+                    .iter()
+                    .map(|block| block.estimated_bytes() + 1)
+                    .sum::<usize>()
+            })
+            .sum();
+        let system: usize = self.system.as_ref().map_or(0, |system| {
+            11 + system
+                .iter()
+                .map(|message| 26 + message.text.len())
+                .sum::<usize>()
+        });
+        let tools: usize = self.tools.as_ref().map_or(0, |tools| {
+            10 + tools
+                .iter()
+                .map(|tool| {
+                    45 + tool.name.len()
+                        + tool.description.as_ref().map_or(0, String::len)
+                        + tool.input_schema.to_string().len()
+                })
+                .sum::<usize>()
+        });
+        let stop_sequences: usize = self.stop_sequences.as_ref().map_or(0, |sequences| {
+            20 + sequences
+                .iter()
+                .map(|sequence| 3 + sequence.len())
+                .sum::<usize>()
+        });
+        let sampling = self.temperature.map_or(0, |_| 20)
+            + self.top_p.map_or(0, |_| 14)
+            + self.top_k.map_or(0, |_| 12);
+        let extra: usize = self
+            .extra
+            .iter()
+            .map(|(key, value)| 4 + key.len() + value.to_string().len())
+            .sum();
+
+        14 + messages + system + tools + stop_sequences + sampling + extra
+    }
+
+    /// Render the request as canonical JSON for snapshot tests
+    ///
+    /// Object keys are sorted recursively and the output is pretty-printed, so logically
+    /// identical requests produce byte-identical strings.
+    pub fn to_canonical_json(&self) -> String {
+        let value = serde_json::to_value(self).expect("ChatRequest always serializes to JSON");
+        serde_json::to_string_pretty(&sort_json_keys(value))
+            .expect("JSON values always serialize to a string")
+    }
+
+    /// Describe how `other` differs from this request, one line per difference
+    ///
+    /// Intended for debugging: messages are compared by count and then position, tools
+    /// by name, and the remaining parameters by value. Returns an empty list when the
+    /// requests are equal.
+    pub fn diff(&self, other: &ChatRequest) -> Vec<String> {
+        fn describe<T: std::fmt::Debug>(value: &Option<T>) -> String {
+            value
+                .as_ref()
+                .map_or_else(|| "unset".to_string(), |value| format!("{:?}", value))
+        }
+
+        let mut differences = Vec::new();
+
+        if self.messages.len() != other.messages.len() {
+            differences.push(format!(
+                "message count: {} -> {}",
+                self.messages.len(),
+                other.messages.len()
+            ));
+        }
+        for (index, (before, after)) in self.messages.iter().zip(&other.messages).enumerate() {
+            if before != after {
+                differences.push(format!("message {} changed", index));
+            }
+        }
+
+        if self.system != other.system {
+            differences.push("system prompt changed".to_string());
+        }
+
+        let tools = |request: &ChatRequest| request.tools.clone().unwrap_or_default();
+        let (before_tools, after_tools) = (tools(self), tools(other));
+        for tool in &after_tools {
+            match before_tools.iter().find(|before| before.name == tool.name) {
+                None => differences.push(format!("tool added: {}", tool.name)),
+                Some(before) if before != tool => {
+                    differences.push(format!("tool changed: {}", tool.name))
+                }
+                Some(_) => {}
+            }
+        }
+        for tool in &before_tools {
+            if !after_tools.iter().any(|after| after.name == tool.name) {
+                differences.push(format!("tool removed: {}", tool.name));
+            }
+        }
+
+        let parameters = [
+            (
+                "tool_choice",
+                describe(&self.tool_choice),
+                describe(&other.tool_choice),
+            ),
+            (
+                "temperature",
+                describe(&self.temperature),
+                describe(&other.temperature),
+            ),
+            ("top_p", describe(&self.top_p), describe(&other.top_p)),
+            ("top_k", describe(&self.top_k), describe(&other.top_k)),
+            (
+                "stop_sequences",
+                describe(&self.stop_sequences),
+                describe(&other.stop_sequences),
+            ),
+            ("seed", describe(&self.seed), describe(&other.seed)),
+            (
+                "thinking",
+                describe(&self.thinking),
+                describe(&other.thinking),
+            ),
+            (
+                "metadata",
+                describe(&self.metadata),
+                describe(&other.metadata),
+            ),
+            ("betas", describe(&self.betas), describe(&other.betas)),
+            (
+                "candidate_count",
+                describe(&self.candidate_count),
+                describe(&other.candidate_count),
+            ),
+        ];
+        for (name, before, after) in parameters {
+            if before != after {
+                differences.push(format!("{}: {} -> {}", name, before, after));
+            }
+        }
+
+        let mut keys: Vec<&String> = self.extra.keys().chain(other.extra.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let (before, after) = (self.extra.get(key), other.extra.get(key));
+            if before != after {
+                differences.push(format!(
+                    "extra {}: {} -> {}",
+                    key,
+                    before.map_or_else(|| "unset".to_string(), ToString::to_string),
+                    after.map_or_else(|| "unset".to_string(), ToString::to_string)
+                ));
+            }
+        }
+
+        differences
+    }
+
+    /// Remove tools whose name duplicates an earlier tool, keeping the first definition
+    ///
+    /// The API rejects requests with duplicate tool names. Returns the names of the
+    /// dropped tools, one entry per removed definition; with the `tracing` feature a
+    /// warning is also logged for each.
+    pub fn dedup_tools(&mut self) -> Vec<String> {
+        let Some(tools) = self.tools.as_mut() else {
+            return Vec::new();
+        };
+
+        let mut seen = std::collections::HashSet::new();
+        let mut dropped = Vec::new();
+        tools.retain(|tool| {
+            let first = seen.insert(tool.name.clone());
+            if !first {
+                #[cfg(feature = "tracing")]
+                tracing::warn!("removing duplicate tool definition '{}'", tool.name);
+                dropped.push(tool.name.clone());
+            }
+            first
+        });
+        dropped
+    }
+}
+
+/// Rebuild a JSON value with every object's keys in sorted order
+fn sort_json_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            serde_json::Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_json_keys(value)))
+                    .collect(),
+            )
+        }
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(sort_json_keys).collect())
+        }
+        other => other,
+    }
+}
+
+/// Builder for chat requests
+#[derive(Debug, Default)]
+pub struct ChatRequestBuilder {
+    messages: Vec<MessageParam>,
+    system: Option<Vec<SystemMessage>>,
+    tools: Option<Vec<crate::tools::Tool>>,
+    tool_choice: Option<ToolChoice>,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    top_k: Option<u32>,
+    stop_sequences: Option<Vec<String>>,
+    seed: Option<u64>,
+    thinking: Option<ThinkingConfig>,
+    metadata: Option<RequestMetadata>,
+    betas: Option<Vec<String>>,
+    extra: serde_json::Map<String, serde_json::Value>,
+    candidate_count: Option<u32>,
+    /// First error recorded by a fallible builder method
+    error: Option<crate::Error>,
+}
+
+impl ChatRequestBuilder {
+    /// Create a new chat request builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a message with specified role and content
+    pub fn message(mut self, role: Role, content: ContentBlock) -> Self {
+        self.messages.push(MessageParam {
+            role,
+            content: vec![content],
+        });
+        self
+    }
+
+    /// Add a message with specified role and multiple content blocks
+    pub fn message_with_content(mut self, role: Role, content: Vec<ContentBlock>) -> Self {
+        self.messages.push(MessageParam { role, content });
+        self
+    }
+
+    /// Add multiple messages at once
+    pub fn messages(mut self, messages: Vec<MessageParam>) -> Self {
+        self.messages.extend(messages);
+        self
+    }
+
+    /// Add a user message
+    pub fn user_message(self, content: ContentBlock) -> Self {
+        self.message(Role::User, content)
+    }
+
+    /// Add a user text message rendered from a prompt template
+    ///
+    /// A rendering error does not interrupt the chain; the first such error is
+    /// reported by [`build_validated`](Self::build_validated).
+    pub fn user_template(
+        mut self,
+        template: &crate::template::PromptTemplate,
+        vars: &std::collections::HashMap<String, String>,
+    ) -> Self {
+        match template.render(vars) {
+            Ok(text) => self.user_message(ContentBlock::text(text)),
+            Err(error) => {
+                self.error.get_or_insert(error);
+                self
+            }
+        }
+    }
+
+    /// Add a user message with an image from a URL
+    ///
+    /// An invalid URL does not interrupt the chain; the first such error is
+    /// reported by [`build_validated`](Self::build_validated).
+    pub fn try_user_image(mut self, url: impl TryInto<Url>) -> Self {
+        match ContentBlock::image_url(url) {
+            Ok(content) => self.user_message(content),
+            Err(error) => {
+                self.error.get_or_insert(error);
+                self
+            }
+        }
+    }
+
+    /// Add a single user message of captioned images
+    ///
+    /// Each pair contributes a text block with the caption followed by the image block.
+    /// Pairs whose block is not an image are skipped and an `Error::Content` is recorded
+    /// for [`build_validated`](Self::build_validated).
+    pub fn captioned_images(mut self, pairs: Vec<(String, ContentBlock)>) -> Self {
+        let mut content = Vec::with_capacity(pairs.len() * 2);
+
+        for (index, (caption, image)) in pairs.into_iter().enumerate() {
+            if !matches!(image, ContentBlock::Image { .. }) {
+                self.error.get_or_insert(crate::Error::Content(format!(
+                    "Captioned block {} is not an image",
+                    index
+                )));
+                continue;
+            }
+            content.push(ContentBlock::text(caption));
+            content.push(image);
+        }
+
+        if !content.is_empty() {
+            self = self.message_with_content(Role::User, content);
+        }
+        self
+    }
+
+    /// Add an assistant message
+    pub fn assistant_message(self, content: ContentBlock) -> Self {
+        self.message(Role::Assistant, content)
+    }
+
+    /// Add a system message
+    pub fn system(mut self, content: impl Into<String>) -> Self {
+        let system_msg = SystemMessage {
+            message_type: "text".to_string(),
+            text: content.into(),
+            cache_control: None,
+        };
+        self.system.get_or_insert_with(Vec::new).push(system_msg);
+        self
+    }
+
+    /// Add a system message marked as a prompt cache breakpoint
+    ///
+    /// The cached prefix covers the tools and every system block up to this one.
+    pub fn system_cached(mut self, content: impl Into<String>) -> Self {
+        self = self.system(content);
+        if let Some(last) = self.system.as_mut().and_then(|system| system.last_mut()) {
+            last.cache_control = Some(CacheControl::ephemeral());
+        }
+        self
+    }
+
+    /// Ask for responses in the given format by appending a system instruction
+    pub fn output_format(self, format: OutputFormat) -> Self {
+        self.system(format.system_instruction())
+    }
+
+    /// Request structured JSON output by forcing a call to `tool`
+    ///
+    /// The tool's input schema describes the expected output; read the result from
+    /// the `tool_use` block's `input`. Also appends the [`OutputFormat::Json`]
+    /// instruction.
+    pub fn json_output(self, tool: crate::tools::Tool) -> Self {
+        let choice = ToolChoice::tool(tool.name.clone());
+        self.output_format(OutputFormat::Json)
+            .tool(tool)
+            .tool_choice(choice)
+    }
+
+    /// Add a tool
+    pub fn tool(mut self, tool: crate::tools::Tool) -> Self {
+        self.tools.get_or_insert_with(Vec::new).push(tool);
+        self
     }
 
     /// Set temperature
@@ -439,6 +1799,82 @@ impl ChatRequestBuilder {
         self
     }
 
+    /// Set top_k, limiting sampling to the most likely tokens
+    pub fn top_k(mut self, top_k: u32) -> Self {
+        self.top_k = Some(top_k);
+        self
+    }
+
+    /// Set the end-user ID sent in the request `metadata`
+    pub fn user_id(mut self, user_id: impl Into<String>) -> Self {
+        self.metadata
+            .get_or_insert_with(RequestMetadata::default)
+            .user_id = Some(user_id.into());
+        self
+    }
+
+    /// Enable a beta feature through the request body's `betas` array
+    pub fn beta(mut self, feature: impl Into<String>) -> Self {
+        let feature = feature.into();
+        let betas = self.betas.get_or_insert_with(Vec::new);
+        if !betas.contains(&feature) {
+            betas.push(feature);
+        }
+        self
+    }
+
+    /// Enable several beta features through the request body's `betas` array
+    pub fn betas<I, S>(self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        features
+            .into_iter()
+            .fold(self, |builder, feature| builder.beta(feature))
+    }
+
+    /// Add an extra top-level field to the request body
+    pub fn extra(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.extra.insert(key.into(), value);
+        self
+    }
+
+    /// Enable extended thinking with the given token budget
+    ///
+    /// The budget must be at least [`ThinkingConfig::MIN_BUDGET_TOKENS`] and below the
+    /// request's `max_tokens`, and `temperature` must be unset or 1.0; this is checked
+    /// by [`ChatRequest::validate`].
+    pub fn thinking(mut self, budget_tokens: u32) -> Self {
+        self.thinking = Some(ThinkingConfig::enabled(budget_tokens));
+        self
+    }
+
+    /// Set the sampling seed
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the number of candidate completions for
+    /// [`Client::execute_chat_candidates`](crate::Client::execute_chat_candidates)
+    pub fn candidate_count(mut self, count: u32) -> Self {
+        self.candidate_count = Some(count);
+        self
+    }
+
+    /// Set the sampling strategy, clearing whichever of temperature/top_p it does not use
+    pub fn sampling(mut self, sampling: Sampling) -> Self {
+        let (temperature, top_p) = match sampling {
+            Sampling::Default => (None, None),
+            Sampling::Temperature(temp) => (Some(temp), None),
+            Sampling::TopP(top_p) => (None, Some(top_p)),
+        };
+        self.temperature = temperature;
+        self.top_p = top_p;
+        self
+    }
+
     /// Add stop sequence
     pub fn stop_sequence(mut self, sequence: impl Into<String>) -> Self {
         self.stop_sequences
@@ -461,16 +1897,107 @@ impl ChatRequestBuilder {
         self
     }
 
+    /// Set how the model may use the provided tools
+    pub fn tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    /// Let the model decide whether to call a tool, calling at most one per response
+    pub fn tool_choice_auto_single(self) -> Self {
+        self.tool_choice(ToolChoice::auto().with_disable_parallel_tool_use(true))
+    }
+
     /// Build the chat request
+    ///
+    /// Errors recorded by `try_*` methods are discarded; use
+    /// [`build_validated`](Self::build_validated) to surface them.
     pub fn build(self) -> ChatRequest {
         ChatRequest {
             messages: self.messages,
             system: self.system,
             tools: self.tools,
+            tool_choice: self.tool_choice,
             temperature: self.temperature,
             top_p: self.top_p,
+            top_k: self.top_k,
             stop_sequences: self.stop_sequences,
+            seed: self.seed,
+            thinking: self.thinking,
+            metadata: self.metadata,
+            betas: self.betas,
+            extra: self.extra,
+            candidate_count: self.candidate_count,
+        }
+    }
+
+    /// Build the chat request, returning the first error recorded while chaining
+    ///
+    /// Also fails with `Error::InvalidRequest` when `temperature` or `top_p` is outside
+    /// `0.0..=1.0`, or when both are set: the API recommends adjusting only one of them
+    /// (see [`sampling`](Self::sampling)).
+    ///
+    /// With the `tracing` feature enabled, this also emits a warning when
+    /// `tool_choice` forces a tool call but no system prompt is present. The request is still built.
+    pub fn build_validated(mut self) -> Result<ChatRequest, crate::Error> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        self.validate_sampling()?;
+
+        #[cfg(feature = "tracing")]
+        if self.forces_tool_without_system() {
+            tracing::warn!("tool_choice forces a tool call but the request has no system prompt");
         }
+
+        Ok(self.build())
+    }
+
+    /// Build the chat request, validating it as [`build_validated`](Self::build_validated) does
+    ///
+    /// [`build`](Self::build) stays infallible for backward compatibility and sends
+    /// out-of-range values to the API unchanged.
+    pub fn try_build(self) -> Result<ChatRequest, crate::Error> {
+        self.build_validated()
+    }
+
+    /// Check that temperature and top_p are in range and not combined
+    fn validate_sampling(&self) -> Result<(), crate::Error> {
+        for (name, value) in [("temperature", self.temperature), ("top_p", self.top_p)] {
+            if let Some(value) = value.filter(|value| !(0.0..=1.0).contains(value)) {
+                return Err(crate::Error::InvalidRequest(format!(
+                    "{} must be between 0.0 and 1.0, got {}",
+                    name, value
+                )));
+            }
+        }
+        if self.temperature.is_some() && self.top_p.is_some() {
+            return Err(crate::Error::InvalidRequest(
+                "temperature and top_p should not both be set; adjust only one".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Whether `tool_choice` forces a tool call while no system prompt is set
+    #[cfg(any(feature = "tracing", test))]
+    fn forces_tool_without_system(&self) -> bool {
+        let forced = match &self.tool_choice {
+            Some(choice) => choice.forces_tool(),
+            None => self
+                .extra
+                .get("tool_choice")
+                .and_then(|choice| choice.get("type"))
+                .and_then(serde_json::Value::as_str)
+                .is_some_and(|choice| matches!(choice, "any" | "tool")),
+        };
+        let has_system = self
+            .system
+            .as_ref()
+            .is_some_and(|system| system.iter().any(|block| !block.text.trim().is_empty()));
+
+        forced && !has_system
     }
 }
 
@@ -482,16 +2009,121 @@ pub struct CountTokensRequest {
     pub system: Option<Vec<SystemMessage>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<crate::tools::Tool>>,
+    /// Additional top-level fields sent to the API verbatim, for parameters the SDK
+    /// does not model yet
+    ///
+    /// A `stream` entry is never sent; the token counting endpoint does not stream.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl CountTokensRequest {
+    /// Compute a hash of the request content, suitable as a cache key
+    ///
+    /// Two requests with identical messages, system prompts, and tools produce the same hash.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_string(self)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Builder for token counting requests
+///
+/// Mirrors the message, system prompt, and tool methods of [`ChatRequestBuilder`].
+#[derive(Debug, Clone, Default)]
+pub struct CountTokensRequestBuilder {
+    messages: Vec<MessageParam>,
+    system: Option<Vec<SystemMessage>>,
+    tools: Option<Vec<crate::tools::Tool>>,
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl CountTokensRequestBuilder {
+    /// Create a new token counting request builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a message with specified role and content
+    pub fn message(mut self, role: Role, content: ContentBlock) -> Self {
+        self.messages.push(MessageParam {
+            role,
+            content: vec![content],
+        });
+        self
+    }
+
+    /// Add a message with specified role and multiple content blocks
+    pub fn message_with_content(mut self, role: Role, content: Vec<ContentBlock>) -> Self {
+        self.messages.push(MessageParam { role, content });
+        self
+    }
+
+    /// Add a user message
+    pub fn user_message(self, content: ContentBlock) -> Self {
+        self.message(Role::User, content)
+    }
+
+    /// Add an assistant message
+    pub fn assistant_message(self, content: ContentBlock) -> Self {
+        self.message(Role::Assistant, content)
+    }
+
+    /// Add a system message
+    pub fn system(mut self, content: impl Into<String>) -> Self {
+        self.system
+            .get_or_insert_with(Vec::new)
+            .push(SystemMessage {
+                message_type: "text".to_string(),
+                text: content.into(),
+                cache_control: None,
+            });
+        self
+    }
+
+    /// Add a tool
+    pub fn tool(mut self, tool: crate::tools::Tool) -> Self {
+        self.tools.get_or_insert_with(Vec::new).push(tool);
+        self
+    }
+
+    /// Add multiple tools
+    pub fn tools(mut self, tools: Vec<crate::tools::Tool>) -> Self {
+        self.tools.get_or_insert_with(Vec::new).extend(tools);
+        self
+    }
+
+    /// Add an extra top-level field to the request body
+    pub fn extra(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.extra.insert(key.into(), value);
+        self
+    }
+
+    /// Build the token counting request
+    pub fn build(self) -> CountTokensRequest {
+        CountTokensRequest {
+            messages: self.messages,
+            system: self.system,
+            tools: self.tools,
+            extra: self.extra,
+        }
+    }
 }
 
 impl From<ChatRequest> for CountTokensRequest {
     /// Convert a ChatRequest to a CountTokensRequest
     /// This is useful for counting tokens in a message before sending it
+    ///
+    /// Fields in `extra` are carried over, since they may affect the prompt.
     fn from(chat_request: ChatRequest) -> Self {
         Self {
             messages: chat_request.messages,
             system: chat_request.system,
             tools: chat_request.tools,
+            extra: chat_request.extra,
         }
     }
 }
@@ -502,6 +2134,20 @@ pub struct TokenCount {
     pub input_tokens: u32,
 }
 
+/// Metadata for a file stored with the Files API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    pub id: String,
+    pub filename: String,
+    pub mime_type: String,
+    pub size_bytes: u64,
+    /// RFC 3339 creation timestamp
+    pub created_at: String,
+    /// Whether the file content can be downloaded
+    #[serde(default)]
+    pub downloadable: bool,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -522,6 +2168,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_model_max_tokens() {
         assert_eq!(Model::Claude3Haiku20240307.max_tokens(), 200_000);
         assert_eq!(Model::Claude3Sonnet20240229.max_tokens(), 200_000);
@@ -531,6 +2178,83 @@ mod tests {
         assert_eq!(Model::Claude4Sonnet20250514.max_tokens(), 200_000);
     }
 
+    #[test]
+    fn test_model_display_and_from_str_round_trip() {
+        for model in Model::KNOWN {
+            let id = model.to_string();
+            assert_eq!(id.parse::<Model>().unwrap(), *model);
+            // Display matches the serde rename
+            assert_eq!(serde_json::to_value(model).unwrap(), id);
+        }
+
+        assert_eq!(
+            "claude-3-5-sonnet-20241022".parse::<Model>().unwrap(),
+            Model::Claude35Sonnet20241022
+        );
+        assert!(matches!(
+            "claude-unknown".parse::<Model>(),
+            Err(crate::Error::Model(_))
+        ));
+        assert_eq!(
+            Model::Other("claude-next".to_string()).to_string(),
+            "claude-next"
+        );
+    }
+
+    #[test]
+    fn test_unknown_model_round_trips_as_other() {
+        let model: Model = serde_json::from_str("\"claude-opus-5-20270101\"").unwrap();
+        assert_eq!(model, Model::Other("claude-opus-5-20270101".to_string()));
+        assert_eq!(
+            serde_json::to_string(&model).unwrap(),
+            "\"claude-opus-5-20270101\""
+        );
+        assert_eq!(model.family(), ModelFamily::Opus);
+
+        // Known identifiers still map to their dedicated variants
+        let known: Model = serde_json::from_str("\"claude-3-haiku-20240307\"").unwrap();
+        assert_eq!(known, Model::Claude3Haiku20240307);
+    }
+
+    #[test]
+    fn test_model_context_window_and_output_limits() {
+        let models = [
+            Model::Claude3Haiku20240307,
+            Model::Claude3Sonnet20240229,
+            Model::Claude3Opus20240229,
+            Model::Claude35Sonnet20241022,
+            Model::Claude35Sonnet20250114,
+            Model::Claude4Sonnet20250514,
+        ];
+
+        for model in models {
+            assert_eq!(model.context_window(), 200_000);
+            assert!(model.max_output_tokens() < model.context_window());
+        }
+
+        assert_eq!(Model::Claude3Haiku20240307.max_output_tokens(), 4_096);
+        assert_eq!(Model::Claude35Sonnet20241022.max_output_tokens(), 8_192);
+        assert_eq!(Model::Claude4Sonnet20250514.max_output_tokens(), 64_000);
+    }
+
+    #[test]
+    fn test_model_family_and_defaults() {
+        assert_eq!(Model::Claude3Haiku20240307.family(), ModelFamily::Haiku);
+        assert_eq!(Model::Claude3Sonnet20240229.family(), ModelFamily::Sonnet);
+        assert_eq!(Model::Claude35Sonnet20241022.family(), ModelFamily::Sonnet);
+        assert_eq!(Model::Claude35Sonnet20250114.family(), ModelFamily::Sonnet);
+        assert_eq!(Model::Claude4Sonnet20250514.family(), ModelFamily::Sonnet);
+        assert_eq!(Model::Claude3Opus20240229.family(), ModelFamily::Opus);
+
+        assert_eq!(ModelFamily::Haiku.recommended_max_tokens(), 4_096);
+        assert_eq!(ModelFamily::Sonnet.recommended_max_tokens(), 8_192);
+        assert_eq!(ModelFamily::Opus.recommended_max_tokens(), 4_096);
+
+        assert!(!ModelFamily::Haiku.streaming_recommended());
+        assert!(ModelFamily::Sonnet.streaming_recommended());
+        assert!(ModelFamily::Opus.streaming_recommended());
+    }
+
     #[test]
     fn test_role_serialization() {
         let user_role = Role::User;
@@ -562,6 +2286,7 @@ mod tests {
             (StopReason::MaxTokens, "\"max_tokens\""),
             (StopReason::StopSequence, "\"stop_sequence\""),
             (StopReason::ToolUse, "\"tool_use\""),
+            (StopReason::PauseTurn, "\"pause_turn\""),
         ];
 
         for (reason, expected_json) in reasons {
@@ -577,6 +2302,7 @@ mod tests {
             ("\"max_tokens\"", StopReason::MaxTokens),
             ("\"stop_sequence\"", StopReason::StopSequence),
             ("\"tool_use\"", StopReason::ToolUse),
+            ("\"pause_turn\"", StopReason::PauseTurn),
         ];
 
         for (json, expected_reason) in reasons {
@@ -604,18 +2330,135 @@ mod tests {
     }
 
     #[test]
-    fn test_usage_deserialization() {
-        let json = r#"{
-            "input_tokens": 100,
-            "output_tokens": 50,
-            "cache_creation_input_tokens": 10
-        }"#;
+    fn test_usage_deserialization() {
+        let json = r#"{
+            "input_tokens": 100,
+            "output_tokens": 50,
+            "cache_creation_input_tokens": 10
+        }"#;
+
+        let usage: Usage = serde_json::from_str(json).unwrap();
+        assert_eq!(usage.input_tokens, 100);
+        assert_eq!(usage.output_tokens, 50);
+        assert_eq!(usage.cache_creation_input_tokens, Some(10));
+        assert_eq!(usage.cache_read_input_tokens, None);
+    }
+
+    #[test]
+    fn test_usage_add() {
+        let first = Usage {
+            input_tokens: 100,
+            output_tokens: 50,
+            cache_creation_input_tokens: Some(10),
+            cache_read_input_tokens: None,
+        };
+        let second = Usage {
+            input_tokens: 20,
+            output_tokens: 5,
+            cache_creation_input_tokens: None,
+            cache_read_input_tokens: None,
+        };
+
+        let total = first.clone() + second.clone();
+        assert_eq!(total.input_tokens, 120);
+        assert_eq!(total.output_tokens, 55);
+        assert_eq!(total.cache_creation_input_tokens, Some(10));
+        assert_eq!(total.cache_read_input_tokens, None);
+
+        let mut running = Usage::zero();
+        running += first;
+        running += second;
+        running += Usage {
+            cache_read_input_tokens: Some(7),
+            ..Usage::zero()
+        };
+        assert_eq!(running.input_tokens, 120);
+        assert_eq!(running.cache_creation_input_tokens, Some(10));
+        assert_eq!(running.cache_read_input_tokens, Some(7));
+    }
+
+    #[test]
+    fn test_usage_delta() {
+        let baseline = Usage {
+            input_tokens: 1000,
+            output_tokens: 50,
+            cache_creation_input_tokens: None,
+            cache_read_input_tokens: None,
+        };
+        let cached = Usage {
+            input_tokens: 100,
+            output_tokens: 60,
+            cache_creation_input_tokens: None,
+            cache_read_input_tokens: Some(900),
+        };
+
+        let delta = cached.delta(&baseline);
+        assert_eq!(delta.input_tokens, -900);
+        assert_eq!(delta.output_tokens, 10);
+        assert_eq!(delta.cache_creation_input_tokens, None);
+        assert_eq!(delta.cache_read_input_tokens, Some(900));
+
+        // Reversing the comparison flips signs, including Some baseline vs None current
+        let reverse = baseline.delta(&cached);
+        assert_eq!(reverse.input_tokens, 900);
+        assert_eq!(reverse.cache_read_input_tokens, Some(-900));
+
+        assert_eq!(
+            cached.delta(&cached),
+            UsageDelta {
+                cache_read_input_tokens: Some(0),
+                ..UsageDelta::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_content_block_semantically_eq_ignores_citation_order() {
+        let first = Citation {
+            start_index: 0,
+            end_index: 10,
+            source: "doc-a".to_string(),
+        };
+        let second = Citation {
+            start_index: 20,
+            end_index: 30,
+            source: "doc-b".to_string(),
+        };
 
-        let usage: Usage = serde_json::from_str(json).unwrap();
-        assert_eq!(usage.input_tokens, 100);
-        assert_eq!(usage.output_tokens, 50);
-        assert_eq!(usage.cache_creation_input_tokens, Some(10));
-        assert_eq!(usage.cache_read_input_tokens, None);
+        let block = ContentBlock::Text {
+            text: "Cited text".to_string(),
+            citations: Some(vec![first.clone(), second.clone()]),
+            cache_control: None,
+        };
+        let reordered = ContentBlock::Text {
+            text: "Cited text".to_string(),
+            citations: Some(vec![second.clone(), first.clone()]),
+            cache_control: None,
+        };
+
+        assert!(block.semantically_eq(&reordered));
+        assert_ne!(block, reordered);
+
+        let different_text = ContentBlock::Text {
+            text: "Other text".to_string(),
+            citations: Some(vec![second, first]),
+            cache_control: None,
+        };
+        assert!(!block.semantically_eq(&different_text));
+
+        let wrapped = ContentBlock::ToolResult {
+            tool_use_id: "tool_1".to_string(),
+            content: vec![block],
+            is_error: None,
+            cache_control: None,
+        };
+        let wrapped_reordered = ContentBlock::ToolResult {
+            tool_use_id: "tool_1".to_string(),
+            content: vec![reordered],
+            is_error: None,
+            cache_control: None,
+        };
+        assert!(wrapped.semantically_eq(&wrapped_reordered));
     }
 
     #[test]
@@ -623,6 +2466,7 @@ mod tests {
         let text_block = ContentBlock::Text {
             text: "Hello, world!".to_string(),
             citations: None,
+            cache_control: None,
         };
 
         let json = serde_json::to_string(&text_block).unwrap();
@@ -644,6 +2488,7 @@ mod tests {
         let text_block = ContentBlock::Text {
             text: "Hello, world!".to_string(),
             citations: Some(vec![citation]),
+            cache_control: None,
         };
 
         let json = serde_json::to_string(&text_block).unwrap();
@@ -664,6 +2509,7 @@ mod tests {
                 media_type: ImageMediaType::Png,
                 data: "base64data".to_string(),
             },
+            cache_control: None,
         };
 
         let json = serde_json::to_string(&image_block).unwrap();
@@ -700,6 +2546,7 @@ mod tests {
             tool_use_id: "tool_123".to_string(),
             content: vec![ContentBlock::text("Result: 3")],
             is_error: Some(false),
+            cache_control: None,
         };
 
         let json = serde_json::to_string(&tool_result_block).unwrap();
@@ -721,7 +2568,9 @@ mod tests {
 
         let content_block: ContentBlock = serde_json::from_str(json).unwrap();
         match content_block {
-            ContentBlock::Text { text, citations } => {
+            ContentBlock::Text {
+                text, citations, ..
+            } => {
                 assert_eq!(text, "Hello, world!");
                 assert_eq!(citations, None);
             }
@@ -806,45 +2655,662 @@ mod tests {
     }
 
     #[test]
-    fn test_system_message_serialization() {
-        let system_msg = SystemMessage {
-            message_type: "text".to_string(),
-            text: "You are a helpful assistant.".to_string(),
-        };
+    fn test_message_type_discriminator() {
+        let json = r#"{
+            "id": "msg_123",
+            "type": "message",
+            "role": "assistant",
+            "content": [],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 1, "output_tokens": 1}
+        }"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+        assert_eq!(message.message_type.as_deref(), Some("message"));
+
+        let wrong_type = json.replace(r#""type": "message""#, r#""type": "completion""#);
+        let result = serde_json::from_str::<Message>(&wrong_type);
+        assert!(result.unwrap_err().to_string().contains("expected type"));
+    }
+
+    #[test]
+    fn test_message_null_content() {
+        let json = r#"{
+            "id": "msg_null",
+            "type": "message",
+            "role": "assistant",
+            "content": null,
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 1, "output_tokens": 1}
+        }"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+        assert!(message.content.is_empty());
+
+        let with_text = json.replace(
+            r#""content": null"#,
+            r#""content": [{"type": "text", "text": "Hi"}]"#,
+        );
+        let message: Message = serde_json::from_str(&with_text).unwrap();
+        assert_eq!(message.content, vec![ContentBlock::text("Hi")]);
+    }
+
+    #[test]
+    fn test_message_thinking_helpers() {
+        let json = r#"{
+            "id": "msg_think",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                {"type": "thinking", "thinking": "Let me add these. ", "signature": "sig"},
+                {"type": "redacted_thinking", "data": "opaque"},
+                {"type": "thinking", "thinking": "The sum is 4.", "signature": "sig2"},
+                {"type": "text", "text": "2 + 2 = 4"}
+            ],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 10, "output_tokens": 20}
+        }"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+
+        assert_eq!(message.thinking_text(), "Let me add these. The sum is 4.");
+
+        let visible = message.without_thinking();
+        assert_eq!(visible.content.len(), 1);
+        assert!(
+            matches!(&visible.content[0], ContentBlock::Text { text, .. } if text == "2 + 2 = 4")
+        );
+        assert_eq!(visible.thinking_text(), "");
+        // The original message is left untouched
+        assert_eq!(message.content.len(), 4);
+    }
+
+    #[test]
+    fn test_message_split() {
+        let message = Message {
+            id: "msg_split".to_string(),
+            message_type: Some("message".to_string()),
+            role: Role::Assistant,
+            content: vec![
+                ContentBlock::Thinking {
+                    thinking: "Need the weather".to_string(),
+                    signature: "sig".to_string(),
+                },
+                ContentBlock::text("Let me check. "),
+                ContentBlock::ToolUse {
+                    id: "toolu_1".to_string(),
+                    name: "get_weather".to_string(),
+                    input: serde_json::json!({ "city": "Paris" }),
+                },
+                ContentBlock::text("One moment."),
+            ],
+            model: Model::Claude35Sonnet20241022,
+            stop_reason: Some(StopReason::ToolUse),
+            stop_sequence: None,
+            usage: Usage::zero(),
+        };
+
+        let (text, tool_calls) = message.split();
+        assert_eq!(text, "Let me check. One moment.");
+        assert_eq!(
+            tool_calls,
+            vec![ToolCall {
+                id: "toolu_1".to_string(),
+                name: "get_weather".to_string(),
+                input: serde_json::json!({ "city": "Paris" }),
+            }]
+        );
+    }
+
+    fn turn(role: Role, content: Vec<ContentBlock>) -> MessageParam {
+        MessageParam { role, content }
+    }
+
+    fn tool_use_block(id: &str) -> ContentBlock {
+        ContentBlock::ToolUse {
+            id: id.to_string(),
+            name: "get_weather".to_string(),
+            input: serde_json::json!({ "city": "Paris" }),
+        }
+    }
+
+    #[test]
+    fn test_conversation_validate_accepts_tool_round_trip() {
+        let conversation = Conversation::from(vec![
+            turn(Role::User, vec![ContentBlock::text("Weather in Paris?")]),
+            turn(
+                Role::Assistant,
+                vec![ContentBlock::text("Checking."), tool_use_block("toolu_1")],
+            ),
+            turn(
+                Role::User,
+                vec![ContentBlock::tool_result("toolu_1", "18C")],
+            ),
+            turn(Role::Assistant, vec![ContentBlock::text("It is 18C.")]),
+        ]);
+        assert!(conversation.validate().is_ok());
+
+        // A trailing tool_use is allowed; the caller has yet to answer it
+        let mut pending = conversation.clone();
+        pending.push(turn(Role::User, vec![ContentBlock::text("And Rome?")]));
+        pending.push(turn(Role::Assistant, vec![tool_use_block("toolu_2")]));
+        assert!(pending.validate().is_ok());
+    }
+
+    #[test]
+    fn test_conversation_validate_rejects_dangling_tool_result() {
+        let conversation = Conversation::from(vec![
+            turn(Role::User, vec![ContentBlock::text("Weather in Paris?")]),
+            turn(Role::Assistant, vec![tool_use_block("toolu_1")]),
+            turn(
+                Role::User,
+                vec![ContentBlock::tool_result("toolu_9", "18C")],
+            ),
+        ]);
+
+        let error = conversation.validate().unwrap_err();
+        assert!(matches!(error, crate::Error::InvalidRequest(_)));
+        assert!(error.to_string().contains("message 2"));
+        assert!(error
+            .to_string()
+            .contains("'toolu_9' has no matching tool_use"));
+    }
+
+    #[test]
+    fn test_conversation_validate_structure() {
+        let unanswered = Conversation::from(vec![
+            turn(Role::User, vec![ContentBlock::text("Hi")]),
+            turn(Role::Assistant, vec![tool_use_block("toolu_1")]),
+            turn(Role::User, vec![ContentBlock::text("Never mind")]),
+        ]);
+        assert!(unanswered
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("'toolu_1' has no tool_result"));
+
+        let starts_with_assistant =
+            Conversation::from(vec![turn(Role::Assistant, vec![ContentBlock::text("Hi")])]);
+        assert!(starts_with_assistant.validate().is_err());
+
+        let repeated_role = Conversation::from(vec![
+            turn(Role::User, vec![ContentBlock::text("Hi")]),
+            turn(Role::User, vec![ContentBlock::text("Hello?")]),
+        ]);
+        assert!(repeated_role.validate().is_err());
+
+        let empty_text = Conversation::from(vec![turn(Role::User, vec![ContentBlock::text(" ")])]);
+        assert!(empty_text.validate().is_err());
+
+        assert!(Conversation::new().validate().is_err());
+    }
+
+    #[test]
+    fn test_conversation_normalize_merges_same_role_turns() {
+        let mut conversation = Conversation::from(vec![
+            turn(Role::User, vec![ContentBlock::text("Hi")]),
+            turn(Role::User, vec![ContentBlock::text("Are you there?")]),
+            turn(Role::Assistant, vec![ContentBlock::text("Yes.")]),
+        ]);
+        assert!(conversation.validate().is_err());
+
+        conversation.normalize();
+        assert_eq!(conversation.messages.len(), 2);
+        assert_eq!(
+            conversation.messages[0].content,
+            vec![
+                ContentBlock::text("Hi"),
+                ContentBlock::text("Are you there?")
+            ]
+        );
+        assert!(conversation.validate().is_ok());
+    }
+
+    #[test]
+    fn test_message_coalesce_text() {
+        let citation = |start_index, end_index| Citation {
+            start_index,
+            end_index,
+            source: "https://example.com".to_string(),
+        };
+        let mut message = Message {
+            id: "msg_coalesce".to_string(),
+            message_type: Some("message".to_string()),
+            role: Role::Assistant,
+            content: vec![
+                ContentBlock::Text {
+                    text: "Hello ".to_string(),
+                    citations: Some(vec![citation(0, 5)]),
+                    cache_control: None,
+                },
+                ContentBlock::Text {
+                    text: "world".to_string(),
+                    citations: Some(vec![citation(0, 5)]),
+                    cache_control: None,
+                },
+                ContentBlock::image_base64(ImageMediaType::Png, "data"),
+                ContentBlock::text("After image"),
+            ],
+            model: Model::Claude35Sonnet20241022,
+            stop_reason: Some(StopReason::EndTurn),
+            stop_sequence: None,
+            usage: Usage::zero(),
+        };
+
+        message.coalesce_text();
+
+        assert_eq!(message.content.len(), 3);
+        assert_eq!(
+            message.content[0],
+            ContentBlock::Text {
+                text: "Hello world".to_string(),
+                citations: Some(vec![citation(0, 5), citation(6, 11)]),
+                cache_control: None,
+            }
+        );
+        assert!(matches!(message.content[1], ContentBlock::Image { .. }));
+        assert_eq!(message.content[2], ContentBlock::text("After image"));
+    }
+
+    #[test]
+    fn test_message_matched_stop_sequence() {
+        let json = r#"{
+            "id": "msg_stop",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "1, 2, 3"}],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "stop_sequence",
+            "stop_sequence": "END",
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        }"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+        assert_eq!(message.matched_stop_sequence(), Some("END"));
+
+        let json = r#"{
+            "id": "msg_end",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Done"}],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        }"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+        assert_eq!(message.matched_stop_sequence(), None);
+    }
+
+    #[test]
+    fn test_chat_request_configured_stop_sequences() {
+        let request = ChatRequestBuilder::new()
+            .stop_sequence("END")
+            .stop_sequence("STOP")
+            .build();
+        assert_eq!(request.configured_stop_sequences(), ["END", "STOP"]);
+
+        let request = ChatRequestBuilder::new().build();
+        assert!(request.configured_stop_sequences().is_empty());
+    }
+
+    #[test]
+    fn test_estimated_bytes_close_to_serialized_length() {
+        fn assert_close(estimate: usize, actual: usize) {
+            let tolerance = actual / 10 + 16;
+            assert!(
+                estimate.abs_diff(actual) <= tolerance,
+                "estimate {} too far from actual {}",
+                estimate,
+                actual
+            );
+        }
+
+        let text = ContentBlock::text("The quick brown fox jumps over the lazy dog. ".repeat(20));
+        assert_close(
+            text.estimated_bytes(),
+            serde_json::to_string(&text).unwrap().len(),
+        );
+
+        let image = ContentBlock::image_base64(ImageMediaType::Png, "iVBORw0KGgo".repeat(500));
+        assert_close(
+            image.estimated_bytes(),
+            serde_json::to_string(&image).unwrap().len(),
+        );
+
+        let request = ChatRequestBuilder::new()
+            .system("You are a helpful assistant")
+            .user_message(text)
+            .message_with_content(
+                Role::User,
+                vec![ContentBlock::text("What is in this image?"), image],
+            )
+            .temperature(0.7)
+            .build();
+        assert_close(
+            request.estimated_bytes(),
+            serde_json::to_string(&request).unwrap().len(),
+        );
+    }
+
+    #[test]
+    fn test_chat_request_canonical_json_is_order_independent() {
+        let first = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Hello"))
+            .temperature(0.5)
+            .system("Be brief")
+            .extra(
+                "metadata",
+                serde_json::json!({"user_id": "u1", "app": "docs"}),
+            )
+            .extra("top_k", serde_json::json!(5))
+            .build();
+        let second = ChatRequestBuilder::new()
+            .extra("top_k", serde_json::json!(5))
+            .system("Be brief")
+            .extra(
+                "metadata",
+                serde_json::json!({"app": "docs", "user_id": "u1"}),
+            )
+            .user_message(ContentBlock::text("Hello"))
+            .temperature(0.5)
+            .build();
+
+        let canonical = first.to_canonical_json();
+        assert_eq!(canonical, second.to_canonical_json());
+        assert!(canonical.find("\"messages\"").unwrap() < canonical.find("\"system\"").unwrap());
+        assert!(canonical.find("\"app\"").unwrap() < canonical.find("\"user_id\"").unwrap());
+    }
+
+    #[test]
+    fn test_chat_request_diff() {
+        let before = ChatRequestBuilder::new()
+            .system("Be brief")
+            .user_message(ContentBlock::text("Hello"))
+            .temperature(0.5)
+            .build();
+        let after = ChatRequestBuilder::new()
+            .system("Be brief")
+            .user_message(ContentBlock::text("Hello"))
+            .assistant_message(ContentBlock::text("Hi!"))
+            .user_message(ContentBlock::text("How are you?"))
+            .temperature(0.9)
+            .build();
+
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                "message count: 1 -> 3".to_string(),
+                "temperature: 0.5 -> 0.9".to_string(),
+            ]
+        );
+        assert!(before.diff(&before.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_chat_request_diff_tools_and_extra() {
+        let tool = |name: &str| crate::tools::Tool::builder(name).build();
+        let before = ChatRequestBuilder::new()
+            .tools(vec![tool("search"), tool("fetch")])
+            .build();
+        let after = ChatRequestBuilder::new()
+            .tools(vec![tool("search"), tool("calculate")])
+            .extra("top_k", serde_json::json!(5))
+            .top_p(0.9)
+            .build();
+
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                "tool added: calculate".to_string(),
+                "tool removed: fetch".to_string(),
+                "top_p: unset -> 0.9".to_string(),
+                "extra top_k: unset -> 5".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chat_request_dedup_tools() {
+        let mut request = ChatRequestBuilder::new()
+            .tool(
+                crate::tools::Tool::builder("search")
+                    .description("first")
+                    .build(),
+            )
+            .tool(
+                crate::tools::Tool::builder("search")
+                    .description("second")
+                    .build(),
+            )
+            .build();
+        assert_eq!(request.dedup_tools(), vec!["search".to_string()]);
+        let tools = request.tools.as_ref().unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].description.as_deref(), Some("first"));
+
+        let mut request = ChatRequestBuilder::new()
+            .tool(crate::tools::Tool::builder("search").build())
+            .tool(crate::tools::Tool::builder("calculator").build())
+            .build();
+        let original = request.clone();
+        assert!(request.dedup_tools().is_empty());
+        assert_eq!(request, original);
+    }
+
+    #[test]
+    fn test_system_message_serialization() {
+        let system_msg = SystemMessage {
+            message_type: "text".to_string(),
+            text: "You are a helpful assistant.".to_string(),
+            cache_control: None,
+        };
+
+        let json = serde_json::to_string(&system_msg).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed["type"], "text");
+        assert_eq!(parsed["text"], "You are a helpful assistant.");
+    }
+
+    #[test]
+    fn test_chat_request_serialization() {
+        let chat_request = ChatRequest {
+            messages: vec![MessageParam {
+                role: Role::User,
+                content: vec![ContentBlock::text("Hello!")],
+            }],
+            system: Some(vec![SystemMessage {
+                message_type: "text".to_string(),
+                text: "Be helpful.".to_string(),
+                cache_control: None,
+            }]),
+            tools: None,
+            temperature: Some(0.7),
+            top_p: None,
+            stop_sequences: Some(vec!["STOP".to_string()]),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&chat_request).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed["messages"][0]["role"], "user");
+        assert_eq!(parsed["system"][0]["text"], "Be helpful.");
+        assert_eq!(parsed["temperature"], 0.7);
+        assert_eq!(parsed["stop_sequences"][0], "STOP");
+        assert!(parsed.get("top_p").is_none());
+        assert!(parsed.get("tools").is_none());
+    }
+
+    #[test]
+    fn test_chat_request_seed_serialization() {
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Hello!"))
+            .build();
+        let parsed = serde_json::to_value(&request).unwrap();
+        assert!(parsed.get("seed").is_none());
+
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Hello!"))
+            .seed(42)
+            .build();
+        let parsed = serde_json::to_value(&request).unwrap();
+        assert_eq!(parsed["seed"], 42);
+    }
+
+    #[test]
+    fn test_chat_request_thinking_serialization() {
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Prove it"))
+            .thinking(2048)
+            .build();
+
+        let parsed = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            parsed["thinking"],
+            serde_json::json!({ "type": "enabled", "budget_tokens": 2048 })
+        );
+        assert!(serde_json::to_value(ChatRequest::user("hi"))
+            .unwrap()
+            .get("thinking")
+            .is_none());
+    }
+
+    #[test]
+    fn test_chat_request_validate_thinking_budget() {
+        let model = Model::Claude35Sonnet20241022;
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Prove it"))
+            .thinking(2048)
+            .build();
+        assert!(request.validate(&model, 4096).is_ok());
+
+        let error = request.validate(&model, 2048).unwrap_err();
+        assert!(matches!(error, crate::Error::InvalidRequest(_)));
+        assert!(error.to_string().contains("less than max_tokens"));
+
+        let too_small = ChatRequestBuilder::new().thinking(512).build();
+        assert!(matches!(
+            too_small.validate(&model, 4096),
+            Err(crate::Error::InvalidRequest(_))
+        ));
+
+        assert!(ChatRequest::user("hi").validate(&model, 1).is_ok());
+    }
+
+    #[test]
+    fn test_chat_request_validate_cache_breakpoints() {
+        let model = Model::Claude35Sonnet20241022;
+        let mut request = ChatRequestBuilder::new()
+            .system_cached("a")
+            .system_cached("b")
+            .user_message(ContentBlock::text_cached("Hello"))
+            .tools(vec![
+                crate::tools::Tool::builder("first").build(),
+                crate::tools::Tool::builder("last").cached().build(),
+            ])
+            .build();
+        assert_eq!(request.cache_breakpoints(), 4);
+        assert!(request.validate(&model, 16).is_ok());
+
+        request.tools.as_mut().unwrap()[0].cache_control = Some(CacheControl::ephemeral());
+        let error = request.validate(&model, 16).unwrap_err();
+        assert!(matches!(error, crate::Error::InvalidRequest(_)));
+        assert!(error.to_string().contains("5 cache_control breakpoints"));
+    }
+
+    #[test]
+    fn test_chat_request_validate_thinking_temperature() {
+        let model = Model::Claude35Sonnet20241022;
+        let thinking = || ChatRequestBuilder::new().thinking(2048);
+
+        let error = thinking()
+            .temperature(0.5)
+            .build()
+            .validate(&model, 4096)
+            .unwrap_err();
+        assert!(matches!(error, crate::Error::InvalidRequest(_)));
+        assert!(error.to_string().contains("extended thinking"));
+
+        assert!(thinking().build().validate(&model, 4096).is_ok());
+        assert!(thinking()
+            .temperature(1.0)
+            .build()
+            .validate(&model, 4096)
+            .is_ok());
+
+        // Without thinking, any temperature is left to the API
+        assert!(ChatRequestBuilder::new()
+            .temperature(0.5)
+            .build()
+            .validate(&model, 4096)
+            .is_ok());
+    }
 
-        let json = serde_json::to_string(&system_msg).unwrap();
-        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+    #[test]
+    fn test_chat_request_shortcut_constructors() {
+        let request = ChatRequest::user("hi");
+        assert_eq!(request.messages.len(), 1);
+        assert_eq!(request.messages[0].role, Role::User);
+        assert!(
+            matches!(&request.messages[0].content[0], ContentBlock::Text { text, .. } if text == "hi")
+        );
+        assert!(request.system.is_none());
+        assert!(request.tools.is_none());
+        assert!(request.temperature.is_none());
+        assert!(request.top_p.is_none());
+        assert!(request.stop_sequences.is_none());
+        assert!(request.extra.is_empty());
 
-        assert_eq!(parsed["type"], "text");
-        assert_eq!(parsed["text"], "You are a helpful assistant.");
+        let messages = vec![
+            MessageParam {
+                role: Role::User,
+                content: vec![ContentBlock::text("What's 2+2?")],
+            },
+            MessageParam {
+                role: Role::Assistant,
+                content: vec![ContentBlock::text("4")],
+            },
+        ];
+        let request = ChatRequest::conversation(messages.clone());
+        assert_eq!(request.messages, messages);
+        assert!(request.system.is_none());
     }
 
     #[test]
-    fn test_chat_request_serialization() {
-        let chat_request = ChatRequest {
-            messages: vec![MessageParam {
-                role: Role::User,
-                content: vec![ContentBlock::text("Hello!")],
-            }],
-            system: Some(vec![SystemMessage {
-                message_type: "text".to_string(),
-                text: "Be helpful.".to_string(),
-            }]),
-            tools: None,
-            temperature: Some(0.7),
-            top_p: None,
-            stop_sequences: Some(vec!["STOP".to_string()]),
-        };
+    fn test_chat_request_with_system_and_temperature() {
+        let original = ChatRequestBuilder::new()
+            .system("Be concise.")
+            .user_message(ContentBlock::text("Hello!"))
+            .temperature(0.2)
+            .stop_sequence("STOP")
+            .build();
 
-        let json = serde_json::to_string(&chat_request).unwrap();
-        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let variant = original.with_system("Be verbose.");
+        assert_eq!(variant.system.as_ref().unwrap().len(), 1);
+        assert_eq!(variant.system.as_ref().unwrap()[0].text, "Be verbose.");
+        assert_eq!(original.system.as_ref().unwrap()[0].text, "Be concise.");
+        assert_eq!(variant.messages, original.messages);
+        assert_eq!(variant.temperature, Some(0.2));
+        assert_eq!(variant.stop_sequences, original.stop_sequences);
+
+        let hotter = original.with_temperature(0.9);
+        assert_eq!(hotter.temperature, Some(0.9));
+        assert_eq!(original.temperature, Some(0.2));
+        assert_eq!(hotter.system, original.system);
+    }
 
-        assert_eq!(parsed["messages"][0]["role"], "user");
-        assert_eq!(parsed["system"][0]["text"], "Be helpful.");
-        assert_eq!(parsed["temperature"], 0.7);
-        assert_eq!(parsed["stop_sequences"][0], "STOP");
-        assert!(parsed.get("top_p").is_none());
-        assert!(parsed.get("tools").is_none());
+    #[test]
+    fn test_chat_request_seed_round_trip() {
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Hello!"))
+            .seed(u64::MAX)
+            .build();
+
+        let json = serde_json::to_string(&request).unwrap();
+        let parsed: ChatRequest = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.seed, Some(u64::MAX));
+        assert!(parsed.extra.is_empty());
+        assert_eq!(parsed, request);
     }
 
     #[test]
@@ -856,6 +3322,7 @@ mod tests {
             }],
             system: None,
             tools: None,
+            extra: Default::default(),
         };
 
         let json = serde_json::to_string(&count_request).unwrap();
@@ -880,11 +3347,13 @@ mod tests {
             system: Some(vec![SystemMessage {
                 message_type: "text".to_string(),
                 text: "System message".to_string(),
+                cache_control: None,
             }]),
             tools: None,
             temperature: Some(0.7),
             top_p: Some(0.9),
             stop_sequences: Some(vec!["STOP".to_string()]),
+            ..Default::default()
         };
 
         // Test From trait implementation
@@ -921,12 +3390,36 @@ mod tests {
         assert_eq!(token_count.input_tokens, 42);
     }
 
+    #[test]
+    fn test_count_tokens_request_content_hash() {
+        let make_request = |text: &str| CountTokensRequest {
+            messages: vec![MessageParam {
+                role: Role::User,
+                content: vec![ContentBlock::text(text)],
+            }],
+            system: None,
+            tools: None,
+            extra: Default::default(),
+        };
+
+        assert_eq!(
+            make_request("Hello").content_hash(),
+            make_request("Hello").content_hash()
+        );
+        assert_ne!(
+            make_request("Hello").content_hash(),
+            make_request("Goodbye").content_hash()
+        );
+    }
+
     #[test]
     fn test_content_block_convenience_constructors() {
         // Test text constructor
         let text_block = ContentBlock::text("Hello!");
         match text_block {
-            ContentBlock::Text { text, citations } => {
+            ContentBlock::Text {
+                text, citations, ..
+            } => {
                 assert_eq!(text, "Hello!");
                 assert_eq!(citations, None);
             }
@@ -936,7 +3429,7 @@ mod tests {
         // Test image base64 constructor
         let image_block = ContentBlock::image_base64(ImageMediaType::Png, "data123");
         match image_block {
-            ContentBlock::Image { source } => match source {
+            ContentBlock::Image { source, .. } => match source {
                 ImageSource::Base64 { media_type, data } => {
                     assert_eq!(media_type, ImageMediaType::Png);
                     assert_eq!(data, "data123");
@@ -950,7 +3443,7 @@ mod tests {
         let url = "https://example.com/image.png";
         let image_block = ContentBlock::image_url(url).unwrap();
         match image_block {
-            ContentBlock::Image { source } => match source {
+            ContentBlock::Image { source, .. } => match source {
                 ImageSource::Url { url } => {
                     assert_eq!(url.as_str(), "https://example.com/image.png");
                 }
@@ -962,7 +3455,7 @@ mod tests {
         // Test document base64 constructor
         let doc_block = ContentBlock::document_base64(DocumentMediaType::Pdf, "pdf_data123");
         match doc_block {
-            ContentBlock::Document { source } => match source {
+            ContentBlock::Document { source, .. } => match source {
                 DocumentSource::Base64 { media_type, data } => {
                     assert_eq!(media_type, DocumentMediaType::Pdf);
                     assert_eq!(data, "pdf_data123");
@@ -976,7 +3469,7 @@ mod tests {
         let doc_url = "https://example.com/document.pdf";
         let doc_block = ContentBlock::document_url(doc_url).unwrap();
         match doc_block {
-            ContentBlock::Document { source } => match source {
+            ContentBlock::Document { source, .. } => match source {
                 DocumentSource::Url { url } => {
                     assert_eq!(url.as_str(), "https://example.com/document.pdf");
                 }
@@ -1004,6 +3497,7 @@ mod tests {
                 tool_use_id,
                 content,
                 is_error,
+                ..
             } => {
                 assert_eq!(tool_use_id, "id123");
                 assert_eq!(is_error, None);
@@ -1177,7 +3671,7 @@ mod tests {
 
     #[test]
     fn test_chat_request_builder_parameter_validation() {
-        // Test temperature bounds (should be between 0.0 and 1.0 in practice, but we don't enforce this in the builder)
+        // Test temperature bounds (build() does not enforce them; try_build() does)
         let request = ChatRequestBuilder::new()
             .temperature(0.0)
             .user_message(ContentBlock::text("Hello!"))
@@ -1190,7 +3684,7 @@ mod tests {
             .build();
         assert_eq!(request.temperature, Some(1.0));
 
-        // Test top_p bounds (should be between 0.0 and 1.0 in practice, but we don't enforce this in the builder)
+        // Test top_p bounds (build() does not enforce them; try_build() does)
         let request = ChatRequestBuilder::new()
             .top_p(0.0)
             .user_message(ContentBlock::text("Hello!"))
@@ -1204,6 +3698,306 @@ mod tests {
         assert_eq!(request.top_p, Some(1.0));
     }
 
+    #[test]
+    fn test_chat_request_builder_try_build_checks_sampling() {
+        let request = ChatRequestBuilder::new()
+            .temperature(1.0)
+            .user_message(ContentBlock::text("Hello!"))
+            .try_build()
+            .unwrap();
+        assert_eq!(request.temperature, Some(1.0));
+
+        let error = ChatRequestBuilder::new()
+            .temperature(2.5)
+            .try_build()
+            .unwrap_err();
+        assert!(matches!(error, crate::Error::InvalidRequest(_)));
+        assert!(error.to_string().contains("temperature"));
+
+        let error = ChatRequestBuilder::new()
+            .top_p(-0.1)
+            .try_build()
+            .unwrap_err();
+        assert!(error.to_string().contains("top_p"));
+
+        let error = ChatRequestBuilder::new()
+            .temperature(0.5)
+            .top_p(0.9)
+            .try_build()
+            .unwrap_err();
+        assert!(error.to_string().contains("both"));
+
+        // build() stays infallible
+        assert_eq!(
+            ChatRequestBuilder::new()
+                .temperature(2.5)
+                .build()
+                .temperature,
+            Some(2.5)
+        );
+    }
+
+    #[test]
+    fn test_chat_request_builder_build_validated() {
+        let result = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Describe these images"))
+            .try_user_image("not a valid url")
+            .try_user_image("https://example.com/image.png")
+            .build_validated();
+        assert!(matches!(result, Err(crate::Error::Config(_))));
+
+        let request = ChatRequestBuilder::new()
+            .try_user_image("https://example.com/image.png")
+            .build_validated()
+            .unwrap();
+        assert_eq!(request.messages.len(), 1);
+        assert!(matches!(
+            request.messages[0].content[0],
+            ContentBlock::Image {
+                source: ImageSource::Url { .. },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_chat_request_builder_forced_tool_lint() {
+        let forced = || {
+            ChatRequestBuilder::new()
+                .user_message(ContentBlock::text("What's the weather?"))
+                .extra(
+                    "tool_choice",
+                    serde_json::json!({ "type": "tool", "name": "get_weather" }),
+                )
+        };
+        assert!(forced().forces_tool_without_system());
+        assert!(!forced()
+            .system("Always use tools")
+            .forces_tool_without_system());
+
+        let auto =
+            ChatRequestBuilder::new().extra("tool_choice", serde_json::json!({ "type": "auto" }));
+        assert!(!auto.forces_tool_without_system());
+
+        assert!(ChatRequestBuilder::new()
+            .tool_choice(ToolChoice::any())
+            .forces_tool_without_system());
+        assert!(!ChatRequestBuilder::new()
+            .tool_choice(ToolChoice::None)
+            .forces_tool_without_system());
+    }
+
+    #[test]
+    fn test_tool_choice_serde_round_trip() {
+        let cases = [
+            (ToolChoice::auto(), serde_json::json!({ "type": "auto" })),
+            (ToolChoice::any(), serde_json::json!({ "type": "any" })),
+            (
+                ToolChoice::tool("get_weather"),
+                serde_json::json!({ "type": "tool", "name": "get_weather" }),
+            ),
+            (ToolChoice::None, serde_json::json!({ "type": "none" })),
+            (
+                ToolChoice::any().with_disable_parallel_tool_use(true),
+                serde_json::json!({ "type": "any", "disable_parallel_tool_use": true }),
+            ),
+        ];
+
+        for (choice, expected) in cases {
+            let value = serde_json::to_value(&choice).unwrap();
+            assert_eq!(value, expected);
+            assert_eq!(serde_json::from_value::<ToolChoice>(value).unwrap(), choice);
+        }
+    }
+
+    #[test]
+    fn test_chat_request_tool_choice_serialization() {
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("What's the weather?"))
+            .tool_choice(ToolChoice::tool("get_weather"))
+            .build();
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            value["tool_choice"],
+            serde_json::json!({ "type": "tool", "name": "get_weather" })
+        );
+
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Hello"))
+            .build();
+        let value = serde_json::to_value(&request).unwrap();
+        assert!(value.get("tool_choice").is_none());
+    }
+
+    #[test]
+    fn test_output_format_appends_system_instruction() {
+        for (format, expected) in [
+            (
+                OutputFormat::Markdown,
+                "Format your response using Markdown.",
+            ),
+            (
+                OutputFormat::PlainText,
+                "Respond in plain text only. Do not use Markdown or any other markup.",
+            ),
+            (
+                OutputFormat::Json,
+                "Respond with a single valid JSON value and nothing else. \
+                 Do not wrap it in code fences or add commentary.",
+            ),
+        ] {
+            let request = ChatRequestBuilder::new()
+                .system("You are helpful.")
+                .output_format(format)
+                .build();
+            let system = request.system.unwrap();
+            assert_eq!(system.len(), 2);
+            assert_eq!(system[0].text, "You are helpful.");
+            assert_eq!(system[1].text, expected);
+            assert!(request.tool_choice.is_none());
+        }
+    }
+
+    #[test]
+    fn test_json_output_forces_tool() {
+        let tool = crate::tools::Tool::builder("record_answer")
+            .schema_value(serde_json::json!({ "type": "object" }))
+            .build();
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("What is 2 + 2?"))
+            .json_output(tool)
+            .build();
+
+        assert_eq!(request.tools.unwrap()[0].name, "record_answer");
+        assert_eq!(request.tool_choice, Some(ToolChoice::tool("record_answer")));
+        assert_eq!(
+            request.system.unwrap()[0].text,
+            OutputFormat::Json.system_instruction()
+        );
+    }
+
+    #[test]
+    fn test_tool_choice_disable_parallel_tool_use() {
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Hello"))
+            .tool_choice_auto_single()
+            .build();
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            value["tool_choice"],
+            serde_json::json!({ "type": "auto", "disable_parallel_tool_use": true })
+        );
+
+        // The flag is omitted when false so existing request bodies are unchanged
+        let choice = ToolChoice::tool("get_weather").with_disable_parallel_tool_use(false);
+        assert_eq!(
+            serde_json::to_value(&choice).unwrap(),
+            serde_json::json!({ "type": "tool", "name": "get_weather" })
+        );
+
+        assert_eq!(
+            ToolChoice::None.with_disable_parallel_tool_use(true),
+            ToolChoice::None
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_build_validated_warns_on_forced_tool_without_system() {
+        ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("What's the weather?"))
+            .extra("tool_choice", serde_json::json!({ "type": "any" }))
+            .build_validated()
+            .unwrap();
+        assert!(logs_contain("no system prompt"));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_dedup_tools_warns_on_dropped_tool() {
+        let mut request = ChatRequestBuilder::new()
+            .tool(crate::tools::Tool::builder("search").build())
+            .tool(crate::tools::Tool::builder("search").build())
+            .build();
+        request.dedup_tools();
+        assert!(logs_contain("removing duplicate tool definition 'search'"));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_build_validated_quiet_with_system_prompt() {
+        ChatRequestBuilder::new()
+            .system("Use the weather tool")
+            .user_message(ContentBlock::text("What's the weather?"))
+            .extra("tool_choice", serde_json::json!({ "type": "any" }))
+            .build_validated()
+            .unwrap();
+        assert!(!logs_contain("no system prompt"));
+    }
+
+    #[test]
+    fn test_chat_request_builder_captioned_images() {
+        let request = ChatRequestBuilder::new()
+            .captioned_images(vec![
+                (
+                    "Page 1".to_string(),
+                    ContentBlock::image_base64(ImageMediaType::Png, "aGVsbG8="),
+                ),
+                (
+                    "Page 2".to_string(),
+                    ContentBlock::image_url("https://example.com/page2.png").unwrap(),
+                ),
+            ])
+            .build_validated()
+            .unwrap();
+
+        assert_eq!(request.messages.len(), 1);
+        assert_eq!(request.messages[0].role, Role::User);
+        let content = &request.messages[0].content;
+        assert_eq!(content.len(), 4);
+        assert!(matches!(&content[0], ContentBlock::Text { text, .. } if text == "Page 1"));
+        assert!(matches!(content[1], ContentBlock::Image { .. }));
+        assert!(matches!(&content[2], ContentBlock::Text { text, .. } if text == "Page 2"));
+        assert!(matches!(content[3], ContentBlock::Image { .. }));
+
+        let result = ChatRequestBuilder::new()
+            .captioned_images(vec![(
+                "Not an image".to_string(),
+                ContentBlock::text("oops"),
+            )])
+            .build_validated();
+        assert!(matches!(result, Err(crate::Error::Content(_))));
+    }
+
+    #[test]
+    fn test_chat_request_builder_sampling() {
+        let request = ChatRequestBuilder::new()
+            .temperature(0.7)
+            .sampling(Sampling::TopP(0.5))
+            .user_message(ContentBlock::text("Hello!"))
+            .build();
+        assert_eq!(request.top_p, Some(0.5));
+        assert_eq!(request.temperature, None);
+
+        let request = ChatRequestBuilder::new()
+            .top_p(0.9)
+            .sampling(Sampling::Temperature(0.2))
+            .build();
+        assert_eq!(request.temperature, Some(0.2));
+        assert_eq!(request.top_p, None);
+
+        let request = ChatRequestBuilder::new()
+            .temperature(0.7)
+            .top_p(0.9)
+            .sampling(Sampling::Default)
+            .build();
+        assert_eq!(request.temperature, None);
+        assert_eq!(request.top_p, None);
+    }
+
     #[test]
     fn test_chat_request_builder_fluent_chaining() {
         // Test that all methods return Self for fluent chaining
@@ -1377,6 +4171,7 @@ mod tests {
                 media_type: DocumentMediaType::Pdf,
                 data: "pdf_base64_data".to_string(),
             },
+            cache_control: None,
         };
 
         let json = serde_json::to_string(&doc_block).unwrap();
@@ -1394,6 +4189,7 @@ mod tests {
             source: DocumentSource::Url {
                 url: "https://example.com/doc.pdf".parse().unwrap(),
             },
+            cache_control: None,
         };
 
         let json = serde_json::to_string(&doc_block).unwrap();
@@ -1440,6 +4236,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_file_source_round_trip() {
+        let document = ContentBlock::document_file("file_011CNha8iCJcU1wXNR6q4V8w");
+        let json = serde_json::to_value(&document).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "document",
+                "source": {"type": "file", "file_id": "file_011CNha8iCJcU1wXNR6q4V8w"}
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<ContentBlock>(json).unwrap(),
+            document
+        );
+
+        let image = ContentBlock::image_file("file_img");
+        let json = serde_json::to_value(&image).unwrap();
+        assert_eq!(json["source"]["type"], "file");
+        assert_eq!(json["source"]["file_id"], "file_img");
+
+        let source: ImageSource =
+            serde_json::from_str(r#"{"type": "file", "file_id": "file_img"}"#).unwrap();
+        assert_eq!(
+            source,
+            ImageSource::File {
+                file_id: "file_img".to_string()
+            }
+        );
+    }
+
     #[test]
     fn test_content_block_document_deserialization() {
         let json = r#"{
@@ -1452,7 +4279,7 @@ mod tests {
 
         let content_block: ContentBlock = serde_json::from_str(json).unwrap();
         match content_block {
-            ContentBlock::Document { source } => match source {
+            ContentBlock::Document { source, .. } => match source {
                 DocumentSource::Url { url } => {
                     assert_eq!(url.as_str(), "https://example.com/document.pdf");
                 }
@@ -1480,7 +4307,7 @@ mod tests {
         for media_type in media_types {
             let block = ContentBlock::image_base64(media_type.clone(), "test_data");
             match block {
-                ContentBlock::Image { source } => match source {
+                ContentBlock::Image { source, .. } => match source {
                     ImageSource::Base64 { media_type: mt, .. } => {
                         assert_eq!(mt, media_type);
                     }
@@ -1498,7 +4325,7 @@ mod tests {
         for media_type in media_types {
             let block = ContentBlock::document_base64(media_type.clone(), "test_data");
             match block {
-                ContentBlock::Document { source } => match source {
+                ContentBlock::Document { source, .. } => match source {
                     DocumentSource::Base64 { media_type: mt, .. } => {
                         assert_eq!(mt, media_type);
                     }
@@ -1509,12 +4336,185 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tool_result_cache_control() {
+        let plain = serde_json::to_value(ContentBlock::tool_result("tool_1", "docs")).unwrap();
+        assert!(plain.get("cache_control").is_none());
+
+        let cached =
+            serde_json::to_value(ContentBlock::tool_result_cached("tool_1", "docs")).unwrap();
+        assert_eq!(cached["type"], "tool_result");
+        assert_eq!(
+            cached["cache_control"],
+            serde_json::json!({"type": "ephemeral"})
+        );
+
+        // Each cached tool result is a breakpoint counted against the per-request limit
+        let model = Model::Claude35Sonnet20241022;
+        let results = (0..=ChatRequest::MAX_CACHE_BREAKPOINTS)
+            .map(|i| ContentBlock::tool_result_cached(format!("tool_{}", i), "docs"))
+            .collect();
+        let mut request = ChatRequestBuilder::new()
+            .message_with_content(Role::User, results)
+            .build();
+        assert!(request.validate(&model, 16).is_err());
+
+        request.messages[0].content.pop();
+        assert!(request.validate(&model, 16).is_ok());
+
+        // Markers on blocks inside a tool result count too
+        let nested = ContentBlock::ToolResult {
+            tool_use_id: "tool_nested".to_string(),
+            content: vec![
+                ContentBlock::text_cached("a"),
+                ContentBlock::text_cached("b"),
+            ],
+            is_error: None,
+            cache_control: None,
+        };
+        request.messages[0].content.truncate(3);
+        request.messages[0].content.push(nested);
+        assert_eq!(request.cache_breakpoints(), 5);
+        assert!(request.validate(&model, 16).is_err());
+    }
+
+    #[test]
+    fn test_chat_request_top_k_serialization() {
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Write a poem"))
+            .build();
+        let value = serde_json::to_value(&request).unwrap();
+        assert!(value.get("top_k").is_none());
+
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Write a poem"))
+            .top_k(40)
+            .build();
+        assert_eq!(request.top_k, Some(40));
+        let json = serde_json::to_string(&request).unwrap();
+        assert!(json.contains("\"top_k\":40"));
+    }
+
+    #[test]
+    fn test_chat_request_betas_in_body() {
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Hello"))
+            .build();
+        let value = serde_json::to_value(&request).unwrap();
+        assert!(value.get("betas").is_none());
+
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Hello"))
+            .beta("token-efficient-tools-2025-02-19")
+            .betas(["token-efficient-tools-2025-02-19", "files-api-2025-04-14"])
+            .build();
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            value["betas"],
+            serde_json::json!(["token-efficient-tools-2025-02-19", "files-api-2025-04-14"])
+        );
+    }
+
+    #[test]
+    fn test_chat_request_metadata_user_id() {
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Hello"))
+            .build();
+        let value = serde_json::to_value(&request).unwrap();
+        assert!(value.get("metadata").is_none());
+
+        let request = ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Hello"))
+            .user_id("user-7f3a")
+            .build();
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            value["metadata"],
+            serde_json::json!({ "user_id": "user-7f3a" })
+        );
+    }
+
+    #[test]
+    fn test_user_template() {
+        let template = crate::template::PromptTemplate::new("Translate {{text}} to French");
+        let vars = std::collections::HashMap::from([("text".to_string(), "hello".to_string())]);
+        let request = ChatRequestBuilder::new()
+            .user_template(&template, &vars)
+            .build_validated()
+            .unwrap();
+        assert_eq!(
+            request.messages[0].content[0],
+            ContentBlock::text("Translate hello to French")
+        );
+
+        let result = ChatRequestBuilder::new()
+            .user_template(&template, &std::collections::HashMap::new())
+            .build_validated();
+        assert!(matches!(result, Err(crate::Error::Config(_))));
+    }
+
+    #[test]
+    fn test_content_block_cache_control() {
+        let plain = serde_json::to_value(ContentBlock::text("context")).unwrap();
+        assert!(plain.get("cache_control").is_none());
+
+        let cached = serde_json::to_value(ContentBlock::text_cached("context")).unwrap();
+        assert_eq!(
+            cached,
+            serde_json::json!({
+                "type": "text",
+                "text": "context",
+                "cache_control": { "type": "ephemeral" }
+            })
+        );
+
+        for block in [
+            ContentBlock::image_file("file_1").cached(),
+            ContentBlock::document_file("file_2").cached(),
+        ] {
+            let value = serde_json::to_value(&block).unwrap();
+            assert_eq!(
+                value["cache_control"],
+                serde_json::json!({"type": "ephemeral"})
+            );
+            let round_trip: ContentBlock = serde_json::from_value(value).unwrap();
+            assert_eq!(round_trip, block);
+        }
+
+        // Blocks that cannot carry cache_control are left unchanged
+        let thinking = ContentBlock::Thinking {
+            thinking: "hmm".to_string(),
+            signature: "sig".to_string(),
+        };
+        assert_eq!(thinking.clone().cached(), thinking);
+    }
+
+    #[test]
+    fn test_system_cached() {
+        let request = ChatRequestBuilder::new()
+            .system("Stable instructions")
+            .system_cached("Large reference document")
+            .build();
+        let value = serde_json::to_value(&request).unwrap();
+
+        assert!(value["system"][0].get("cache_control").is_none());
+        assert_eq!(
+            value["system"][1],
+            serde_json::json!({
+                "type": "text",
+                "text": "Large reference document",
+                "cache_control": { "type": "ephemeral" }
+            })
+        );
+    }
+
     #[test]
     fn test_tool_result_with_error() {
         let tool_result = ContentBlock::ToolResult {
             tool_use_id: "tool_123".to_string(),
             content: vec![ContentBlock::text("Error occurred")],
             is_error: Some(true),
+            cache_control: None,
         };
 
         let json = serde_json::to_string(&tool_result).unwrap();
@@ -27,6 +27,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_model_max_tokens() {
         assert_eq!(Model::Claude3Haiku20240307.max_tokens(), 200_000);
         assert_eq!(Model::Claude3Sonnet20240229.max_tokens(), 200_000);
@@ -120,7 +121,9 @@ mod tests {
 
         let deserialized: ContentBlock = serde_json::from_value(serialized).unwrap();
         match deserialized {
-            ContentBlock::Text { text, citations } => {
+            ContentBlock::Text {
+                text, citations, ..
+            } => {
                 assert_eq!(text, "Hello, world!");
                 assert!(citations.is_none());
             }
@@ -140,7 +143,7 @@ mod tests {
 
         let deserialized: ContentBlock = serde_json::from_value(serialized).unwrap();
         match deserialized {
-            ContentBlock::Image { source } => match source {
+            ContentBlock::Image { source, .. } => match source {
                 ImageSource::Base64 { media_type, data } => {
                     assert_eq!(media_type, ImageMediaType::Png);
                     assert_eq!(data, "base64data");
@@ -162,7 +165,7 @@ mod tests {
 
         let deserialized: ContentBlock = serde_json::from_value(serialized).unwrap();
         match deserialized {
-            ContentBlock::Image { source } => match source {
+            ContentBlock::Image { source, .. } => match source {
                 ImageSource::Url { url } => {
                     assert_eq!(url.as_str(), "https://example.com/image.jpg");
                 }
@@ -217,6 +220,7 @@ mod tests {
                 tool_use_id,
                 content,
                 is_error,
+                ..
             } => {
                 assert_eq!(tool_use_id, "tool-123");
                 assert_eq!(content.len(), 1);
@@ -273,6 +277,7 @@ mod tests {
         let system_msg = SystemMessage {
             message_type: "text".to_string(),
             text: "You are a helpful assistant".to_string(),
+            cache_control: None,
         };
 
         let serialized = serde_json::to_value(&system_msg).unwrap();
@@ -294,11 +299,13 @@ mod tests {
             system: Some(vec![SystemMessage {
                 message_type: "text".to_string(),
                 text: "Be helpful".to_string(),
+                cache_control: None,
             }]),
             tools: None,
             temperature: Some(0.7),
             top_p: Some(0.9),
             stop_sequences: Some(vec!["STOP".to_string()]),
+            ..Default::default()
         };
 
         let serialized = serde_json::to_value(&request).unwrap();
@@ -327,6 +334,7 @@ mod tests {
             }],
             system: None,
             tools: None,
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_value(&request).unwrap();
@@ -391,6 +399,7 @@ mod tests {
                     "operation": {"type": "string"}
                 }
             }),
+            cache_control: None,
         };
 
         let serialized = serde_json::to_value(&tool).unwrap();
@@ -420,6 +429,7 @@ mod tests {
         match image_block {
             ContentBlock::Image {
                 source: ImageSource::Base64 { media_type, data },
+                ..
             } => {
                 assert_eq!(media_type, ImageMediaType::Jpeg);
                 assert_eq!(data, "data");
@@ -0,0 +1,233 @@
+//! Schema validation for outgoing request bodies
+//!
+//! Validation uses a small embedded subset of JSON Schema (`type`, `enum`, `required`,
+//! `properties`, `items`, `minimum`, `maximum`). Unknown fields are allowed so that
+//! new API parameters can be sent through `ChatRequest::extra`.
+//!
+//! Constraints that must hold regardless of this check, such as the prompt caching
+//! breakpoint limit, are enforced by `ChatRequest::validate` instead.
+
+use serde_json::{json, Value};
+
+use crate::{error::Error, Result};
+
+/// Schema for the Messages API request body
+fn messages_request_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["model", "max_tokens", "messages"],
+        "properties": {
+            "model": { "type": "string" },
+            "max_tokens": { "type": "integer", "minimum": 1 },
+            "messages": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["role", "content"],
+                    "properties": {
+                        "role": { "enum": ["user", "assistant"] },
+                        "content": {
+                            "type": ["string", "array"],
+                            "items": {
+                                "type": "object",
+                                "required": ["type"],
+                                "properties": { "type": { "type": "string" } }
+                            }
+                        }
+                    }
+                }
+            },
+            "system": {
+                "type": ["string", "array"],
+                "items": {
+                    "type": "object",
+                    "required": ["type", "text"],
+                    "properties": {
+                        "type": { "type": "string" },
+                        "text": { "type": "string" }
+                    }
+                }
+            },
+            "tools": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["name", "input_schema"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "description": { "type": "string" },
+                        "input_schema": { "type": "object" },
+                        "cache_control": { "type": "object" }
+                    }
+                }
+            },
+            "tool_choice": {
+                "type": "object",
+                "required": ["type"],
+                "properties": {
+                    "type": { "enum": ["auto", "any", "tool", "none"] },
+                    "name": { "type": "string" },
+                    "disable_parallel_tool_use": { "type": "boolean" }
+                }
+            },
+            "temperature": { "type": "number", "minimum": 0, "maximum": 1 },
+            "top_p": { "type": "number", "minimum": 0, "maximum": 1 },
+            "top_k": { "type": "integer", "minimum": 0 },
+            "stop_sequences": { "type": "array", "items": { "type": "string" } },
+            "metadata": {
+                "type": "object",
+                "properties": { "user_id": { "type": "string" } }
+            },
+            "betas": { "type": "array", "items": { "type": "string" } },
+            "stream": { "type": "boolean" }
+        }
+    })
+}
+
+/// Validate a serialized Messages API request body
+///
+/// Returns `Error::InvalidRequest` naming the JSON pointer of the first violation.
+pub(crate) fn validate_messages_request(body: &Value) -> Result<()> {
+    validate(body, &messages_request_schema(), "").map_err(|(path, message)| {
+        let path = if path.is_empty() { "/" } else { &path };
+        Error::InvalidRequest(format!(
+            "Request body failed schema validation at {}: {}",
+            path, message
+        ))
+    })
+}
+
+fn type_matches(value: &Value, type_name: &str) -> bool {
+    match type_name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+fn validate(
+    value: &Value,
+    schema: &Value,
+    path: &str,
+) -> std::result::Result<(), (String, String)> {
+    let fail = |message: String| Err((path.to_string(), message));
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.iter().any(|name| type_matches(value, name)) {
+            return fail(format!("expected {}, got {}", types.join(" or "), value));
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            return fail(format!(
+                "{} is not one of {}",
+                value,
+                Value::from(allowed.clone())
+            ));
+        }
+    }
+
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+            if number < minimum {
+                return fail(format!("{} is less than the minimum {}", number, minimum));
+            }
+        }
+        if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+            if number > maximum {
+                return fail(format!(
+                    "{} is greater than the maximum {}",
+                    number, maximum
+                ));
+            }
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for field in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(field) {
+                    return fail(format!("missing required field '{}'", field));
+                }
+            }
+        }
+        if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+            for (key, property_schema) in properties {
+                if let Some(property) = object.get(key) {
+                    validate(property, property_schema, &format!("{}/{}", path, key))?;
+                }
+            }
+        }
+    }
+
+    if let (Some(items), Some(item_schema)) = (value.as_array(), schema.get("items")) {
+        for (index, item) in items.iter().enumerate() {
+            validate(item, item_schema, &format!("{}/{}", path, index))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid_body() -> Value {
+        json!({
+            "model": "claude-3-5-sonnet-20241022",
+            "max_tokens": 1024,
+            "messages": [
+                { "role": "user", "content": [{ "type": "text", "text": "Hello" }] }
+            ],
+            "temperature": 0.5,
+            "metadata": { "user_id": "abc" }
+        })
+    }
+
+    #[test]
+    fn test_valid_request_passes() {
+        assert!(validate_messages_request(&valid_body()).is_ok());
+    }
+
+    #[test]
+    fn test_violation_reports_path() {
+        let mut body = valid_body();
+        body["messages"][0]["role"] = json!("system");
+
+        let error = validate_messages_request(&body).unwrap_err();
+        assert!(matches!(error, Error::InvalidRequest(_)));
+        assert!(error.to_string().contains("/messages/0/role"));
+    }
+
+    #[test]
+    fn test_missing_required_field() {
+        let mut body = valid_body();
+        body.as_object_mut().unwrap().remove("max_tokens");
+
+        let error = validate_messages_request(&body).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("missing required field 'max_tokens'"));
+    }
+
+    #[test]
+    fn test_out_of_range_number() {
+        let mut body = valid_body();
+        body["top_p"] = json!(1.5);
+
+        let error = validate_messages_request(&body).unwrap_err();
+        assert!(error.to_string().contains("/top_p"));
+    }
+}
@@ -108,20 +108,17 @@ async fn test_chat_request_building() {
     assert_eq!(conversation_request.temperature, Some(0.7));
 
     // Test manual request construction
-    let manual_request = ChatRequest {
-        messages: vec![MessageParam {
-            role: Role::User,
-            content: vec![ContentBlock::text("Manual request")],
-        }],
-        system: Some(vec![SystemMessage {
-            message_type: "text".to_string(),
-            text: "System prompt".to_string(),
-        }]),
-        tools: None,
-        temperature: Some(0.5),
-        top_p: None,
-        stop_sequences: None,
-    };
+    let mut manual_request = ChatRequest::default();
+    manual_request.messages = vec![MessageParam {
+        role: Role::User,
+        content: vec![ContentBlock::text("Manual request")],
+    }];
+    manual_request.system = Some(vec![SystemMessage {
+        message_type: "text".to_string(),
+        text: "System prompt".to_string(),
+        cache_control: None,
+    }]);
+    manual_request.temperature = Some(0.5);
 
     assert_eq!(manual_request.messages.len(), 1);
     assert!(manual_request.system.is_some());
@@ -146,7 +143,7 @@ async fn test_content_blocks() {
     );
 
     match image_content {
-        ContentBlock::Image { source } => match source {
+        ContentBlock::Image { source, .. } => match source {
             anthropic_rust::ImageSource::Base64 { media_type, .. } => {
                 assert_eq!(media_type, anthropic_rust::ImageMediaType::Png);
             }
@@ -283,9 +280,11 @@ async fn test_error_handling() {
 #[tokio::test]
 async fn test_model_capabilities() {
     // Test model token limits
-    assert_eq!(Model::Claude3Haiku20240307.max_tokens(), 200_000);
-    assert_eq!(Model::Claude35Sonnet20241022.max_tokens(), 200_000);
-    assert_eq!(Model::Claude3Opus20240229.max_tokens(), 200_000);
+    assert_eq!(Model::Claude3Haiku20240307.context_window(), 200_000);
+    assert_eq!(Model::Claude35Sonnet20241022.context_window(), 200_000);
+    assert_eq!(Model::Claude3Opus20240229.context_window(), 200_000);
+    assert_eq!(Model::Claude3Haiku20240307.max_output_tokens(), 4_096);
+    assert_eq!(Model::Claude35Sonnet20241022.max_output_tokens(), 8_192);
 
     // Test model serialization
     let model_json = serde_json::to_string(&Model::Claude35Sonnet20241022).unwrap();
@@ -319,6 +318,7 @@ async fn test_token_counting() {
         }],
         system: None,
         tools: None,
+        extra: Default::default(),
     };
 
     // This will fail with invalid API key, but tests the request structure
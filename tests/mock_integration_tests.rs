@@ -39,13 +39,20 @@
 #![cfg(not(miri))]
 
 use anthropic_rust::{
-    types::CountTokensRequest, Client, ContentBlock, Error, MessageParam, Model, Role, StopReason,
+    types::CountTokensRequest, ChatOptions, ChatRequestBuilder, Client, ContentBlock,
+    CountTokensRequestBuilder, Error, Message, MessageParam, Model, ResponseCache, RetryConfig,
+    Role, SequentialIdGenerator, Sleeper, StopReason, StreamEvent, StreamOptions, StreamTransport,
     Tool,
 };
+use futures::StreamExt;
 use serde_json::json;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use wiremock::{
-    matchers::{header, method, path},
+    matchers::{body_partial_json, header, headers, method, path, query_param},
     Mock, MockServer, ResponseTemplate,
 };
 
@@ -269,6 +276,7 @@ async fn test_count_tokens_request() {
         }],
         system: None,
         tools: None,
+        extra: Default::default(),
     };
 
     let response = client.count_tokens(request).await.unwrap();
@@ -332,6 +340,39 @@ async fn test_api_error_handling() {
     }
 }
 
+#[tokio::test]
+async fn test_prompt_too_long_maps_to_context_length_error() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "type": "error",
+            "error": {
+                "type": "invalid_request_error",
+                "message": "prompt is too long: 208310 tokens > 200000 maximum"
+            }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_mock_client(&mock_server).await;
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("A very long prompt"))
+        .build();
+
+    let error = client.execute_chat(request).await.unwrap_err();
+    assert!(error.is_context_length_error());
+    match error {
+        Error::ContextLengthExceeded { tokens, message } => {
+            assert_eq!(tokens, Some(208_310));
+            assert!(message.contains("prompt is too long"));
+        }
+        other => panic!("Expected ContextLengthExceeded, got: {:?}", other),
+    }
+}
+
 #[tokio::test]
 async fn test_authentication_error() {
     let mock_server = MockServer::start().await;
@@ -383,12 +424,21 @@ async fn test_rate_limit_error() {
         .respond_with(
             ResponseTemplate::new(429)
                 .set_body_json(&error_response)
-                .insert_header("request-id", "req-rate-limit"),
+                .insert_header("request-id", "req-rate-limit")
+                .insert_header("anthropic-ratelimit-requests-remaining", "0")
+                .insert_header("anthropic-ratelimit-requests-reset", "1970-01-01T00:01:00Z"),
         )
         .mount(&mock_server)
         .await;
 
-    let client = create_mock_client(&mock_server).await;
+    let sleeper = Arc::new(RecordingSleeper::default());
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .sleeper(sleeper.clone())
+        .build()
+        .unwrap();
 
     let request = client
         .chat_builder()
@@ -397,14 +447,27 @@ async fn test_rate_limit_error() {
 
     let result = client.execute_chat(request).await;
 
+    // Every retry waits the server-requested time
+    assert_eq!(
+        *sleeper.delays.lock().unwrap(),
+        vec![Duration::from_secs_f64(60.5); 3]
+    );
     assert!(result.is_err());
     match result.unwrap_err() {
         Error::RateLimit {
             retry_after,
             request_id,
+            info,
+            ..
         } => {
             assert_eq!(retry_after, Some(Duration::from_secs_f64(60.5)));
             assert_eq!(request_id, Some("req-rate-limit".to_string()));
+            let info = info.expect("rate limit headers should be parsed");
+            assert_eq!(info.requests_remaining, Some(0));
+            assert_eq!(
+                info.requests_reset,
+                Some(std::time::UNIX_EPOCH + Duration::from_secs(60))
+            );
         }
         _ => panic!("Expected rate limit error"),
     }
@@ -646,3 +709,2106 @@ async fn test_concurrent_requests() {
     assert_eq!(response1.id, "msg_concurrent");
     assert_eq!(response2.id, "msg_concurrent");
 }
+
+#[tokio::test]
+async fn test_count_tokens_cache_hit() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages/count_tokens"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"input_tokens": 12})))
+        .expect(1) // The second count must be served from the cache
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .token_count_cache(16)
+        .build()
+        .unwrap();
+
+    let request = CountTokensRequest {
+        messages: vec![MessageParam {
+            role: Role::User,
+            content: vec![ContentBlock::text("Count me twice")],
+        }],
+        system: None,
+        tools: None,
+        extra: Default::default(),
+    };
+
+    let first = client.count_tokens(request.clone()).await.unwrap();
+    let second = client.count_tokens(request).await.unwrap();
+
+    assert_eq!(first.input_tokens, 12);
+    assert_eq!(second.input_tokens, 12);
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_count_tokens_never_sends_stream_flag() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages/count_tokens"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"input_tokens": 7})))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_mock_client(&mock_server).await;
+
+    // A request built for streaming, with the flag leaking through `extra`
+    let chat_request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Count me"))
+        .extra("stream", json!(true))
+        .extra("tool_choice", json!({"type": "auto"}))
+        .build();
+    let token_count = client
+        .count_tokens(CountTokensRequest::from(chat_request))
+        .await
+        .unwrap();
+    assert_eq!(token_count.input_tokens, 7);
+
+    let count_request = CountTokensRequestBuilder::new()
+        .user_message(ContentBlock::text("Count me"))
+        .extra("stream", json!(true))
+        .build();
+    client.count_tokens(count_request).await.unwrap();
+
+    // Other extra fields are forwarded; the stream flag is dropped
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    let bodies: Vec<serde_json::Value> = requests
+        .iter()
+        .map(|request| serde_json::from_slice(&request.body).unwrap())
+        .collect();
+    assert_eq!(bodies[0]["tool_choice"], json!({"type": "auto"}));
+    assert!(bodies.iter().all(|body| body.get("stream").is_none()));
+}
+
+/// Sleeper that records requested delays and returns immediately
+#[derive(Debug, Default)]
+struct RecordingSleeper {
+    delays: Mutex<Vec<Duration>>,
+}
+
+impl Sleeper for RecordingSleeper {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        self.delays.lock().unwrap().push(duration);
+        Box::pin(async {})
+    }
+}
+
+#[tokio::test]
+async fn test_overloaded_uses_overloaded_backoff() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(529).set_body_json(json!({
+            "type": "error",
+            "error": {
+                "type": "overloaded_error",
+                "message": "Overloaded"
+            }
+        })))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_after_overload",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Recovered"}],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 5, "output_tokens": 1}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let sleeper = Arc::new(RecordingSleeper::default());
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .retry_config(
+            RetryConfig::default()
+                .with_max_retries(2)
+                .with_initial_delay(Duration::from_millis(100))
+                .with_overloaded_backoff(Duration::from_secs(7)),
+        )
+        .sleeper(sleeper.clone())
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    let response = client.execute_chat(request).await.unwrap();
+
+    assert_eq!(response.id, "msg_after_overload");
+    assert_eq!(
+        *sleeper.delays.lock().unwrap(),
+        vec![Duration::from_secs(7)]
+    );
+}
+
+#[tokio::test]
+async fn test_redirects_follow_configured_policy() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(307)
+                .insert_header("location", format!("{}/moved", mock_server.uri()).as_str()),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/moved"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_redirected",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Moved"}],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 5, "output_tokens": 1}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client_with = |policy: Option<reqwest::redirect::Policy>| {
+        let builder = Client::builder()
+            .api_key("sk-ant-api03-test-key")
+            .base_url(mock_server.uri().as_str())
+            .unwrap()
+            .max_retries(0);
+        match policy {
+            Some(policy) => builder.redirect_policy(policy),
+            None => builder,
+        }
+        .build()
+        .unwrap()
+    };
+    let request = || {
+        ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Hello"))
+            .build()
+    };
+
+    // Redirects are not followed by default
+    let error = client_with(None).execute_chat(request()).await.unwrap_err();
+    assert!(matches!(error, Error::Api { status, .. } if status.as_u16() == 307));
+    let moved = |requests: &[wiremock::Request]| {
+        requests
+            .iter()
+            .filter(|request| request.url.path() == "/moved")
+            .count()
+    };
+    assert_eq!(moved(&mock_server.received_requests().await.unwrap()), 0);
+
+    // A custom policy opts back in
+    let message = client_with(Some(reqwest::redirect::Policy::limited(3)))
+        .execute_chat(request())
+        .await
+        .unwrap();
+    assert_eq!(message.id, "msg_redirected");
+    assert_eq!(moved(&mock_server.received_requests().await.unwrap()), 1);
+}
+
+#[tokio::test]
+async fn test_retry_after_header_exposed_on_error() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .set_body_json(json!({
+                    "type": "error",
+                    "error": {"type": "rate_limit_error", "message": "Rate limit exceeded"}
+                }))
+                .insert_header("retry-after", "7"),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .max_retries(0)
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    let error = client.execute_chat(request).await.unwrap_err();
+    assert!(error.is_rate_limit_error());
+    assert_eq!(error.retry_after(), Some(Duration::from_secs(7)));
+}
+
+#[tokio::test]
+async fn test_retry_after_header_overrides_body() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .set_body_json(json!({
+                    "type": "error",
+                    "error": {
+                        "type": "rate_limit_error",
+                        "message": "Rate limit exceeded",
+                        "retry_after": 60
+                    }
+                }))
+                .insert_header("retry-after", "2"),
+        )
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_after_rate_limit",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Recovered"}],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 5, "output_tokens": 1}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let sleeper = Arc::new(RecordingSleeper::default());
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .retry_config(RetryConfig::default().with_initial_delay(Duration::from_millis(100)))
+        .sleeper(sleeper.clone())
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    let response = client.execute_chat(request).await.unwrap();
+
+    assert_eq!(response.id, "msg_after_rate_limit");
+    assert_eq!(
+        *sleeper.delays.lock().unwrap(),
+        vec![Duration::from_secs(2)]
+    );
+}
+
+#[tokio::test]
+async fn test_polling_chunks_stream_transport() {
+    let mock_server = MockServer::start().await;
+
+    // Newline-delimited JSON with no SSE `event:`/`data:` framing
+    let body = concat!(
+        r#"{"type":"message_start","message":{"id":"msg_poll","role":"assistant","content":[],"model":"claude-3-5-sonnet-20241022","stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":4,"output_tokens":0}}}"#,
+        "\n",
+        r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+        "\n",
+        r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#,
+        "\n",
+        r#"{"type":"content_block_stop","index":0}"#,
+        "\n",
+        r#"{"type":"message_stop"}"#,
+        "\n",
+    );
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body))
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .stream_transport(StreamTransport::PollingChunks)
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    let events: Vec<_> = client
+        .stream_chat(request)
+        .await
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(events.len(), 5);
+    assert!(events.iter().all(|event| event.is_ok()));
+}
+
+#[tokio::test]
+async fn test_count_tokens_sends_beta_header() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages/count_tokens"))
+        .and(query_param("beta", "true"))
+        .and(headers(
+            "anthropic-beta",
+            vec!["token-counting-2024-11-01", "pdfs-2024-09-25"],
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"input_tokens": 7})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .beta_features(["token-counting-2024-11-01", "pdfs-2024-09-25"])
+        .build()
+        .unwrap();
+
+    let request = CountTokensRequest {
+        messages: vec![MessageParam {
+            role: Role::User,
+            content: vec![ContentBlock::text("Count me")],
+        }],
+        system: None,
+        tools: None,
+        extra: Default::default(),
+    };
+
+    let token_count = client.count_tokens(request).await.unwrap();
+    assert_eq!(token_count.input_tokens, 7);
+}
+
+#[tokio::test]
+async fn test_error_body_with_success_status() {
+    let error_for = |error_type: &str| {
+        json!({
+            "type": "error",
+            "error": {
+                "type": error_type,
+                "message": "Proxied error"
+            }
+        })
+    };
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(error_for("overloaded_error")))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(error_for("invalid_request_error")))
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .model(Model::Claude35Sonnet20241022)
+        .max_retries(0)
+        .build()
+        .unwrap();
+
+    let request = || {
+        client
+            .chat_builder()
+            .user_message(ContentBlock::text("Hello"))
+            .build()
+    };
+
+    // Classified by the status the API would have sent, not the proxy's 200
+    let error = client.execute_chat(request()).await.unwrap_err();
+    assert!(error.is_retryable());
+    match error {
+        Error::Api {
+            status,
+            message,
+            error_type,
+            ..
+        } => {
+            assert_eq!(status.as_u16(), 529);
+            assert_eq!(message, "Proxied error");
+            assert_eq!(error_type, Some("overloaded_error".to_string()));
+        }
+        other => panic!("Expected API error, got {:?}", other),
+    }
+
+    let error = client.execute_chat(request()).await.unwrap_err();
+    assert!(!error.is_retryable());
+    match error {
+        Error::InvalidRequest(message) => assert_eq!(message, "Proxied error"),
+        other => panic!("Expected invalid request error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_resume_after_pause_turn() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_paused",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Searching..."}],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "pause_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 10, "output_tokens": 3}
+        })))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_paused_again",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Still searching..."}],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "pause_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 12, "output_tokens": 4}
+        })))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_resumed",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Here is what I found."}],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 15, "output_tokens": 6}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_mock_client(&mock_server).await;
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Research this"))
+        .build();
+
+    let paused = client.execute_chat(request.clone()).await.unwrap();
+    assert_eq!(paused.stop_reason, Some(StopReason::PauseTurn));
+
+    let resumed = client.resume(paused, request).await.unwrap();
+    assert_eq!(resumed.id, "msg_resumed");
+    assert_eq!(resumed.stop_reason, Some(StopReason::EndTurn));
+
+    // The returned message holds the whole turn, across both pauses
+    let texts: Vec<_> = resumed
+        .content
+        .iter()
+        .map(|block| match block {
+            ContentBlock::Text { text, .. } => text.as_str(),
+            other => panic!("Expected text, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        texts,
+        vec![
+            "Searching...",
+            "Still searching...",
+            "Here is what I found."
+        ]
+    );
+
+    // Each resumed request ends with a single assistant turn holding the content so far
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 3);
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(body["messages"].as_array().unwrap().len(), 2);
+    assert_eq!(body["messages"][1]["role"], "assistant");
+    assert_eq!(body["messages"][1]["content"][0]["text"], "Searching...");
+
+    let body: serde_json::Value = serde_json::from_slice(&requests[2].body).unwrap();
+    assert_eq!(body["messages"].as_array().unwrap().len(), 2);
+    assert_eq!(body["messages"][1]["role"], "assistant");
+    assert_eq!(body["messages"][1]["content"][0]["text"], "Searching...");
+    assert_eq!(
+        body["messages"][1]["content"][1]["text"],
+        "Still searching..."
+    );
+}
+
+#[tokio::test]
+async fn test_stream_include_usage_flag() {
+    let mock_server = MockServer::start().await;
+
+    let body = concat!(
+        "event: message_delta\n",
+        r#"data: {"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null,"usage":{"output_tokens":7}}}"#,
+        "\n\n",
+    );
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(body),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let default_client = create_mock_client(&mock_server).await;
+    let opted_out_client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .stream_include_usage(false)
+        .build()
+        .unwrap();
+
+    let request = default_client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    let delta_usage = |events: Vec<anthropic_rust::Result<StreamEvent>>| match &events[0] {
+        Ok(StreamEvent::MessageDelta { delta }) => delta.usage.clone(),
+        other => panic!("Expected message_delta, got {:?}", other),
+    };
+    let with_usage = default_client.stream_chat(request.clone()).await.unwrap();
+    let without_usage = opted_out_client.stream_chat(request).await.unwrap();
+    assert_eq!(
+        delta_usage(with_usage.collect().await)
+            .unwrap()
+            .output_tokens,
+        7
+    );
+    assert!(delta_usage(without_usage.collect().await).is_none());
+
+    // The toggle is client-side; the API has no parameter for it
+    for request in mock_server.received_requests().await.unwrap() {
+        let sent: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert!(sent.get("stream_options").is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_stream_chat_with_options_applied() {
+    let mock_server = MockServer::start().await;
+
+    let body = concat!(
+        r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+        r#"{"type":"message_stop"}"#,
+    );
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body))
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .stream_transport(StreamTransport::PollingChunks)
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+    let options = StreamOptions {
+        model: Some(Model::Claude3Haiku20240307),
+        timeout: Some(Duration::from_secs(5)),
+        idle_timeout: Some(Duration::from_secs(5)),
+        include_usage: false,
+    };
+
+    let events: Vec<_> = client
+        .stream_chat_with(options, request)
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().all(|event| event.is_ok()));
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let sent: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(sent["model"], "claude-3-haiku-20240307");
+    assert!(sent.get("stream_options").is_none());
+}
+
+#[tokio::test]
+async fn test_files_api_upload_get_and_delete() {
+    let mock_server = MockServer::start().await;
+    let metadata = json!({
+        "id": "file_011CNha8iCJcU1wXNR6q4V8w",
+        "type": "file",
+        "filename": "notes.txt",
+        "mime_type": "text/plain",
+        "size_bytes": 11,
+        "created_at": "2025-04-14T12:00:00Z",
+        "downloadable": false
+    });
+
+    Mock::given(method("POST"))
+        .and(path("/v1/files"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&metadata))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/files/file_011CNha8iCJcU1wXNR6q4V8w"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&metadata))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/v1/files/file_011CNha8iCJcU1wXNR6q4V8w"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "file_011CNha8iCJcU1wXNR6q4V8w",
+            "type": "file_deleted"
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_mock_client(&mock_server).await;
+    let uploaded = client
+        .upload_file(b"hello world".to_vec(), "notes.txt", "text/plain")
+        .await
+        .unwrap();
+    assert_eq!(uploaded.id, "file_011CNha8iCJcU1wXNR6q4V8w");
+    assert_eq!(uploaded.size_bytes, 11);
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let content_type = requests[0].headers.get("content-type").unwrap();
+    assert!(content_type
+        .to_str()
+        .unwrap()
+        .starts_with("multipart/form-data"));
+    let body = String::from_utf8_lossy(&requests[0].body);
+    assert!(body.contains(r#"name="file"; filename="notes.txt""#));
+    assert!(body.contains("hello world"));
+
+    let fetched = client.get_file(&uploaded.id).await.unwrap();
+    assert_eq!(fetched, uploaded);
+    client.delete_file(&uploaded.id).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_model_fallback_on_overload() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(
+            json!({"model": "claude-3-5-sonnet-20241022"}),
+        ))
+        .respond_with(ResponseTemplate::new(529).set_body_json(json!({
+            "type": "error",
+            "error": {"type": "overloaded_error", "message": "Overloaded"}
+        })))
+        .expect(4) // Initial attempt plus one retry on the primary model, twice
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(
+            json!({"model": "claude-3-haiku-20240307"}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_fallback",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Served by the fallback"}],
+            "model": "claude-3-haiku-20240307",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        })))
+        .expect(2) // Downgraded responses are not cached
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .model(Model::Claude35Sonnet20241022)
+        .model_fallback(vec![Model::Claude3Haiku20240307])
+        .max_retries(1)
+        .sleeper(Arc::new(RecordingSleeper::default()))
+        .response_cache(Arc::new(MemoryResponseCache::default()))
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .temperature(0.0)
+        .build();
+
+    for _ in 0..2 {
+        let (message, meta) = client
+            .execute_chat_with_response(request.clone())
+            .await
+            .unwrap();
+        assert_eq!(message.id, "msg_fallback");
+        assert_eq!(message.model, Model::Claude3Haiku20240307);
+        assert_eq!(meta.fallback_model, Some(Model::Claude3Haiku20240307));
+    }
+}
+
+#[tokio::test]
+async fn test_model_fallback_skips_models_the_request_is_invalid_for() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(
+            json!({"model": "claude-3-5-sonnet-20241022"}),
+        ))
+        .respond_with(ResponseTemplate::new(529).set_body_json(json!({
+            "type": "error",
+            "error": {"type": "overloaded_error", "message": "Overloaded"}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(
+            json!({"model": "claude-4-sonnet-20250514"}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_fallback",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Served by the second fallback"}],
+            "model": "claude-4-sonnet-20250514",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    // Haiku's 4096-token output limit cannot fit the thinking budget
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .model(Model::Claude35Sonnet20241022)
+        .max_tokens(8000)
+        .model_fallback(vec![
+            Model::Claude3Haiku20240307,
+            Model::Claude4Sonnet20250514,
+        ])
+        .max_retries(0)
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .thinking(5000)
+        .build();
+
+    let (message, meta) = client.execute_chat_with_response(request).await.unwrap();
+    assert_eq!(message.model, Model::Claude4Sonnet20250514);
+    assert_eq!(meta.fallback_model, Some(Model::Claude4Sonnet20250514));
+}
+
+#[tokio::test]
+async fn test_stream_errors_carry_request_id() {
+    let mock_server = MockServer::start().await;
+
+    let body = concat!(
+        r#"{"type":"message_start","message":{"id":"msg_err","role":"assistant","content":[],"#,
+        r#""model":"claude-3-5-sonnet-20241022","stop_reason":null,"stop_sequence":null,"#,
+        r#""usage":{"input_tokens":3,"output_tokens":0}}}"#,
+        r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+    );
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("request-id", "req_stream_123")
+                .set_body_string(body),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .stream_transport(StreamTransport::PollingChunks)
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    let stream = client.stream_chat(request).await.unwrap();
+    assert_eq!(stream.request_id(), Some("req_stream_123"));
+
+    let events: Vec<_> = stream.collect().await;
+    assert!(events[0].is_ok());
+    let error = events[1].as_ref().unwrap_err();
+    assert!(error.is_overloaded());
+    assert_eq!(error.request_id(), Some("req_stream_123"));
+}
+
+#[tokio::test]
+async fn test_retry_streams_restarts_after_mid_stream_overload() {
+    let mock_server = MockServer::start().await;
+
+    let failing = concat!(
+        r#"{"type":"message_start","message":{"id":"msg_first","role":"assistant","content":[],"#,
+        r#""model":"claude-3-5-sonnet-20241022","stop_reason":null,"stop_sequence":null,"#,
+        r#""usage":{"input_tokens":3,"output_tokens":0}}}"#,
+        r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+        r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Par"}}"#,
+        r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+    );
+    let complete = concat!(
+        r#"{"type":"message_start","message":{"id":"msg_second","role":"assistant","content":[],"#,
+        r#""model":"claude-3-5-sonnet-20241022","stop_reason":null,"stop_sequence":null,"#,
+        r#""usage":{"input_tokens":3,"output_tokens":0}}}"#,
+        r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+        r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Done"}}"#,
+        r#"{"type":"content_block_stop","index":0}"#,
+        r#"{"type":"message_stop"}"#,
+    );
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(failing))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(complete))
+        .mount(&mock_server)
+        .await;
+
+    let sleeper = Arc::new(RecordingSleeper::default());
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .stream_transport(StreamTransport::PollingChunks)
+        .retry_config(RetryConfig::default().with_overloaded_backoff(Duration::from_secs(3)))
+        .retry_streams(true)
+        .sleeper(sleeper.clone())
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    // The fresh message_start from the restart supersedes the partial message
+    let message = client
+        .stream_chat(request)
+        .await
+        .unwrap()
+        .accumulate()
+        .accumulate()
+        .await
+        .unwrap();
+    assert_eq!(message.id, "msg_second");
+    assert!(matches!(&message.content[0], ContentBlock::Text { text, .. } if text == "Done"));
+
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 2);
+    assert_eq!(
+        *sleeper.delays.lock().unwrap(),
+        vec![Duration::from_secs(3)]
+    );
+}
+
+#[tokio::test]
+async fn test_gzip_encoded_error_body_is_decoded() {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    let mock_server = MockServer::start().await;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(
+            json!({
+                "type": "error",
+                "error": {"type": "invalid_request_error", "message": "messages: field required"}
+            })
+            .to_string()
+            .as_bytes(),
+        )
+        .unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(400)
+                .insert_header("content-encoding", "gzip")
+                .insert_header("content-type", "application/json")
+                .set_body_bytes(compressed),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let client = create_mock_client(&mock_server).await;
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    // Both the regular and the streaming error paths see the decoded message
+    let error = client.execute_chat(request.clone()).await.unwrap_err();
+    assert!(error.to_string().contains("messages: field required"));
+
+    let error = client.stream_chat(request).await.err().unwrap();
+    assert!(error.to_string().contains("messages: field required"));
+}
+
+#[tokio::test]
+async fn test_execute_chat_candidates() {
+    let mock_server = MockServer::start().await;
+
+    let response_body = json!({
+        "id": "msg_candidate",
+        "type": "message",
+        "role": "assistant",
+        "content": [{ "type": "text", "text": "Whiskers" }],
+        "model": "claude-3-5-sonnet-20241022",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": { "input_tokens": 10, "output_tokens": 3 }
+    });
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&response_body))
+        .expect(6)
+        .mount(&mock_server)
+        .await;
+
+    // Deterministic requests would otherwise be served from the response cache
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .response_cache(Arc::new(MemoryResponseCache::default()))
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Suggest a name for a cat"))
+        .temperature(0.0)
+        .seed(10)
+        .candidate_count(3)
+        .build();
+
+    let candidates = client
+        .execute_chat_candidates(request.clone())
+        .await
+        .unwrap();
+    assert_eq!(candidates.len(), 3);
+    assert!(candidates
+        .iter()
+        .all(|candidate| candidate.id == "msg_candidate"));
+
+    // Asking again must not be answered from the cache
+    client.execute_chat_candidates(request).await.unwrap();
+
+    // Each candidate gets its own seed; candidate_count is client-side only
+    let received = mock_server.received_requests().await.unwrap();
+    let bodies: Vec<serde_json::Value> = received
+        .iter()
+        .map(|request| serde_json::from_slice(&request.body).unwrap())
+        .collect();
+    let mut seeds: Vec<u64> = bodies
+        .iter()
+        .map(|body| body["seed"].as_u64().unwrap())
+        .collect();
+    seeds.sort_unstable();
+    assert_eq!(seeds, vec![10, 10, 11, 11, 12, 12]);
+    assert!(bodies
+        .iter()
+        .all(|body| body.get("candidate_count").is_none()));
+}
+
+#[tokio::test]
+async fn test_execute_chat_candidates_without_seed() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_candidate",
+            "type": "message",
+            "role": "assistant",
+            "content": [{ "type": "text", "text": "Whiskers" }],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": { "input_tokens": 10, "output_tokens": 3 }
+        })))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_mock_client(&mock_server).await;
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Suggest a name for a cat"))
+        .candidate_count(2)
+        .build();
+
+    let candidates = client.execute_chat_candidates(request).await.unwrap();
+    assert_eq!(candidates.len(), 2);
+
+    // No seed is invented when the caller did not set one
+    let received = mock_server.received_requests().await.unwrap();
+    for request in &received {
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert!(body.get("seed").is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_precheck_tokens_rejects_oversized_request() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages/count_tokens"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "input_tokens": 199_500 })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .model(Model::Claude35Sonnet20241022)
+        .max_tokens(1000)
+        .precheck_tokens(true)
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("A very long document..."))
+        .build();
+
+    let error = client.execute_chat(request).await.unwrap_err();
+    assert!(matches!(error, Error::InvalidRequest(_)));
+    assert!(error.to_string().contains("199500 input tokens"));
+}
+
+#[tokio::test]
+async fn test_precheck_tokens_counts_with_overridden_model() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages/count_tokens"))
+        .and(body_partial_json(
+            json!({ "model": "claude-3-haiku-20240307" }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "input_tokens": 100 })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages/count_tokens"))
+        .and(body_partial_json(
+            json!({ "model": "claude-3-5-sonnet-20241022" }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "input_tokens": 200 })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_precheck",
+            "type": "message",
+            "role": "assistant",
+            "content": [{ "type": "text", "text": "Hi" }],
+            "model": "claude-3-haiku-20240307",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": { "input_tokens": 100, "output_tokens": 1 }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .model(Model::Claude35Sonnet20241022)
+        .max_tokens(1000)
+        .precheck_tokens(true)
+        .token_count_cache(16)
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Count me per model"))
+        .build();
+
+    client
+        .execute_chat_with_model(Model::Claude3Haiku20240307, request.clone())
+        .await
+        .unwrap();
+
+    // The Haiku count is cached under Haiku only
+    let count = client
+        .count_tokens(CountTokensRequest::from(request))
+        .await
+        .unwrap();
+    assert_eq!(count.input_tokens, 200);
+}
+
+#[tokio::test]
+async fn test_per_request_headers_override_defaults() {
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    let mock_server = MockServer::start().await;
+
+    let response_body = json!({
+        "id": "msg_headers",
+        "type": "message",
+        "role": "assistant",
+        "content": [{ "type": "text", "text": "Hi" }],
+        "model": "claude-3-5-sonnet-20241022",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": { "input_tokens": 5, "output_tokens": 1 }
+    });
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(header("x-tenant-id", "tenant-42"))
+        .and(header("x-app-name", "demo"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&response_body))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut defaults = HeaderMap::new();
+    defaults.insert("x-tenant-id", HeaderValue::from_static("default-tenant"));
+    defaults.insert("x-app-name", HeaderValue::from_static("demo"));
+    let http_client = reqwest::Client::builder()
+        .default_headers(defaults)
+        .build()
+        .unwrap();
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .http_client(http_client)
+        .build()
+        .unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-tenant-id", HeaderValue::from_static("tenant-42"));
+    let options = ChatOptions {
+        headers: Some(headers),
+        ..Default::default()
+    };
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    let response = client.execute_chat_with(options, request).await.unwrap();
+    assert_eq!(response.id, "msg_headers");
+
+    let received = mock_server.received_requests().await.unwrap();
+    let tenants: Vec<_> = received[0].headers.get_all("x-tenant-id").iter().collect();
+    assert_eq!(tenants, vec!["tenant-42"]);
+}
+
+#[tokio::test]
+async fn test_count_tokens_includes_default_system() {
+    let mock_server = MockServer::start().await;
+
+    let expected_system = json!({
+        "system": [
+            { "type": "text", "text": "You are terse." },
+            { "type": "text", "text": "Answer in French." }
+        ]
+    });
+    Mock::given(method("POST"))
+        .and(path("/v1/messages/count_tokens"))
+        .and(body_partial_json(&expected_system))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "input_tokens": 21 })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .default_system("You are terse.")
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .system("Answer in French.")
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    let count = client
+        .count_tokens(CountTokensRequest::from(request))
+        .await
+        .unwrap();
+    assert_eq!(count.input_tokens, 21);
+}
+
+/// Response cache backed by a map
+#[derive(Debug, Default)]
+struct MemoryResponseCache {
+    entries: Mutex<HashMap<String, Message>>,
+}
+
+impl ResponseCache for MemoryResponseCache {
+    fn get<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Option<Message>> + Send + 'a>> {
+        let message = self.entries.lock().unwrap().get(key).cloned();
+        Box::pin(async move { message })
+    }
+
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        message: Message,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), message);
+        Box::pin(async {})
+    }
+}
+
+#[tokio::test]
+async fn test_response_cache_serves_repeated_deterministic_request() {
+    let mock_server = MockServer::start().await;
+
+    let response_body = json!({
+        "id": "msg_cached",
+        "type": "message",
+        "role": "assistant",
+        "content": [{ "type": "text", "text": "Paris" }],
+        "model": "claude-3-5-sonnet-20241022",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": { "input_tokens": 12, "output_tokens": 1 }
+    });
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&response_body))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let cache = Arc::new(MemoryResponseCache::default());
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .response_cache(cache.clone())
+        .build()
+        .unwrap();
+
+    let deterministic = || {
+        client
+            .chat_builder()
+            .user_message(ContentBlock::text("Capital of France?"))
+            .temperature(0.0)
+            .build()
+    };
+
+    let first = client.execute_chat(deterministic()).await.unwrap();
+    let second = client.execute_chat(deterministic()).await.unwrap();
+    assert_eq!(first, second);
+    assert_eq!(cache.entries.lock().unwrap().len(), 1);
+
+    // Sampled requests bypass the cache and reach the API
+    let sampled = deterministic().with_temperature(0.7);
+    client.execute_chat(sampled).await.unwrap();
+    assert_eq!(cache.entries.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_replayed_tool_use_serializes_with_input_shape() {
+    let mock_server = MockServer::start().await;
+
+    let tool_use_response = json!({
+        "id": "msg_tool",
+        "type": "message",
+        "role": "assistant",
+        "content": [
+            { "type": "text", "text": "Let me calculate." },
+            {
+                "type": "tool_use",
+                "id": "toolu_1",
+                "name": "calculator",
+                "input": { "a": 15, "b": 27 }
+            }
+        ],
+        "model": "claude-3-5-sonnet-20241022",
+        "stop_reason": "tool_use",
+        "stop_sequence": null,
+        "usage": { "input_tokens": 20, "output_tokens": 10 }
+    });
+    let final_response = json!({
+        "id": "msg_final",
+        "type": "message",
+        "role": "assistant",
+        "content": [{ "type": "text", "text": "42" }],
+        "model": "claude-3-5-sonnet-20241022",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": { "input_tokens": 40, "output_tokens": 1 }
+    });
+
+    // Replay a saved conversation: the assistant turn is the parsed API response
+    let saved: Message = serde_json::from_value(tool_use_response).unwrap();
+    let conversation = vec![
+        MessageParam {
+            role: Role::User,
+            content: vec![ContentBlock::text("What is 15 + 27?")],
+        },
+        MessageParam::from(saved),
+        MessageParam {
+            role: Role::User,
+            content: vec![ContentBlock::tool_result("toolu_1", "42")],
+        },
+    ];
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&final_response))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_mock_client(&mock_server).await;
+    let mut request = client.chat_builder().build();
+    request.messages = conversation;
+    client.execute_chat(request).await.unwrap();
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(
+        body["messages"],
+        json!([
+            {
+                "role": "user",
+                "content": [{ "type": "text", "text": "What is 15 + 27?" }]
+            },
+            {
+                "role": "assistant",
+                "content": [
+                    { "type": "text", "text": "Let me calculate." },
+                    {
+                        "type": "tool_use",
+                        "id": "toolu_1",
+                        "name": "calculator",
+                        "input": { "a": 15, "b": 27 }
+                    }
+                ]
+            },
+            {
+                "role": "user",
+                "content": [{
+                    "type": "tool_result",
+                    "tool_use_id": "toolu_1",
+                    "content": [{ "type": "text", "text": "42" }]
+                }]
+            }
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_drain_waits_for_in_flight_requests() {
+    let mock_server = MockServer::start().await;
+
+    let response_body = json!({
+        "id": "msg_slow",
+        "type": "message",
+        "role": "assistant",
+        "content": [{ "type": "text", "text": "Done" }],
+        "model": "claude-3-5-sonnet-20241022",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": { "input_tokens": 5, "output_tokens": 1 }
+    });
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(&response_body)
+                .set_delay(Duration::from_millis(300)),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .build()
+        .unwrap();
+
+    // Nothing in flight: drain returns immediately
+    client.drain(Duration::ZERO).await.unwrap();
+
+    let request = || {
+        client
+            .chat_builder()
+            .user_message(ContentBlock::text("Hello"))
+            .build()
+    };
+
+    // A short timeout elapses while the slow request is still running
+    let slow = tokio::spawn({
+        let client = client.clone();
+        let request = request();
+        async move { client.execute_chat(request).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let error = client.drain(Duration::from_millis(20)).await.unwrap_err();
+    assert!(matches!(error, Error::Timeout { .. }));
+    slow.await.unwrap().unwrap();
+
+    // A generous timeout blocks until the request completes
+    let slow = tokio::spawn({
+        let client = client.clone();
+        let request = request();
+        async move { client.execute_chat(request).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!slow.is_finished());
+    client.drain(Duration::from_secs(5)).await.unwrap();
+    assert!(slow.is_finished());
+    assert_eq!(slow.await.unwrap().unwrap().id, "msg_slow");
+}
+
+#[tokio::test]
+async fn test_auto_max_tokens_uses_model_output_limit() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(json!({
+            "model": "claude-4-sonnet-20250514",
+            "max_tokens": 64000,
+            "stream": true
+        })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(include_str!("fixtures/message_stream.sse")),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .model(Model::Claude4Sonnet20250514)
+        .auto_max_tokens(true)
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+    let events: Vec<_> = client.stream_chat(request).await.unwrap().collect().await;
+    assert!(events.iter().all(|event| event.is_ok()));
+}
+
+#[tokio::test]
+async fn test_non_utf8_response_body() {
+    let mock_server = MockServer::start().await;
+
+    let mut body = b"{\"id\": \"msg_".to_vec();
+    body.extend_from_slice(&[0xff, 0xfe, 0x00, 0x9f]);
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/json"))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_mock_client(&mock_server).await;
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    let error = client.execute_chat(request).await.unwrap_err();
+    assert!(matches!(error, Error::InvalidResponse(_)));
+    assert!(error
+        .to_string()
+        .contains("Response body was not valid UTF-8 (16 bytes, invalid at byte 12)"));
+}
+
+#[tokio::test]
+async fn test_sse_stream_yields_real_events() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(include_str!("fixtures/message_stream.sse")),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let client = create_mock_client(&mock_server).await;
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    let events: Vec<StreamEvent> = client
+        .stream_chat(request.clone())
+        .await
+        .unwrap()
+        .map(|event| event.unwrap())
+        .collect()
+        .await;
+    let deltas: Vec<&str> = events.iter().filter_map(StreamEvent::text_delta).collect();
+    assert_eq!(deltas, vec!["Hello", ", world"]);
+    assert!(matches!(events.last(), Some(StreamEvent::MessageStop)));
+
+    let message = client
+        .stream_chat(request)
+        .await
+        .unwrap()
+        .accumulate()
+        .accumulate()
+        .await
+        .unwrap();
+    assert_eq!(message.id, "msg_fixture");
+    assert_eq!(message.stop_reason, Some(StopReason::EndTurn));
+    assert_eq!(message.usage.output_tokens, 6);
+}
+
+#[tokio::test]
+async fn test_redirects_are_not_followed_by_default() {
+    let mock_server = MockServer::start().await;
+
+    let response_body = json!({
+        "id": "msg_redirected",
+        "type": "message",
+        "role": "assistant",
+        "content": [{ "type": "text", "text": "Hi" }],
+        "model": "claude-3-5-sonnet-20241022",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": { "input_tokens": 5, "output_tokens": 1 }
+    });
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(307)
+                .insert_header("location", format!("{}/elsewhere", mock_server.uri())),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/elsewhere"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&response_body))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let request = || {
+        anthropic_rust::ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Hello"))
+            .build()
+    };
+
+    // The default policy surfaces the redirect instead of following it
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .max_retries(0)
+        .build()
+        .unwrap();
+    assert!(client.execute_chat(request()).await.is_err());
+
+    // Opting in follows the redirect
+    let following = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .redirect_policy(reqwest::redirect::Policy::limited(1))
+        .build()
+        .unwrap();
+    let message = following.execute_chat(request()).await.unwrap();
+    assert_eq!(message.id, "msg_redirected");
+}
+
+#[tokio::test]
+async fn test_batch_results_decodes_jsonl() {
+    let mock_server = MockServer::start().await;
+
+    let succeeded = json!({
+        "custom_id": "req-1",
+        "result": {
+            "type": "succeeded",
+            "message": {
+                "id": "msg_batch_1",
+                "type": "message",
+                "role": "assistant",
+                "content": [{ "type": "text", "text": "Hello" }],
+                "model": "claude-3-5-sonnet-20241022",
+                "stop_reason": "end_turn",
+                "stop_sequence": null,
+                "usage": { "input_tokens": 5, "output_tokens": 1 }
+            }
+        }
+    });
+    let errored = json!({
+        "custom_id": "req-2",
+        "result": {
+            "type": "errored",
+            "error": {
+                "type": "error",
+                "error": { "type": "invalid_request_error", "message": "max_tokens: field required" }
+            }
+        }
+    });
+
+    Mock::given(method("GET"))
+        .and(path("/v1/messages/batches/msgbatch_123/results"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "application/x-jsonl")
+                .set_body_string(format!("{}\n{}\n", succeeded, errored)),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_mock_client(&mock_server).await;
+    let results = client.batch_results("msgbatch_123").await.unwrap();
+
+    assert_eq!(results.len(), 2);
+    let (first_id, first) = &results[0];
+    assert_eq!(first_id, "req-1");
+    assert_eq!(first.as_ref().unwrap().id, "msg_batch_1");
+
+    let (second_id, second) = &results[1];
+    assert_eq!(second_id, "req-2");
+    match second {
+        Err(Error::InvalidRequest(message)) => {
+            assert_eq!(message, "max_tokens: field required")
+        }
+        other => panic!("Expected InvalidRequest, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_response_from_unknown_model_deserializes() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(
+            json!({ "model": "claude-sonnet-9-20300101" }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_new_model",
+            "type": "message",
+            "role": "assistant",
+            "content": [{ "type": "text", "text": "Hello from the future" }],
+            "model": "claude-sonnet-9-20300101",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": { "input_tokens": 5, "output_tokens": 4 }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_mock_client(&mock_server).await;
+    let model = Model::Other("claude-sonnet-9-20300101".to_string());
+    let response = client
+        .execute_chat_with_model(
+            model.clone(),
+            client
+                .chat_builder()
+                .user_message(ContentBlock::text("Hello"))
+                .build(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.id, "msg_new_model");
+    assert_eq!(response.model, model);
+}
+
+#[tokio::test]
+async fn test_error_on_empty_response_flag() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_empty",
+            "type": "message",
+            "role": "assistant",
+            "content": [],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": { "input_tokens": 10, "output_tokens": 0 }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let request = || {
+        anthropic_rust::ChatRequestBuilder::new()
+            .user_message(ContentBlock::text("Hello"))
+            .build()
+    };
+
+    // Disabled by default: the empty message is returned as-is
+    let client = create_mock_client(&mock_server).await;
+    let message = client.execute_chat(request()).await.unwrap();
+    assert!(message.content.is_empty());
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .model(Model::Claude35Sonnet20241022)
+        .error_on_empty_response(true)
+        .build()
+        .unwrap();
+    let error = client.execute_chat(request()).await.unwrap_err();
+    assert!(matches!(error, Error::InvalidResponse(_)));
+    assert!(error.to_string().contains("msg_empty"));
+}
+
+#[tokio::test]
+async fn test_execute_chat_with_response_returns_meta() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({
+                    "id": "msg_meta",
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [{ "type": "text", "text": "Hi" }],
+                    "model": "claude-3-5-sonnet-20241022",
+                    "stop_reason": "end_turn",
+                    "stop_sequence": null,
+                    "usage": { "input_tokens": 5, "output_tokens": 1 }
+                }))
+                .insert_header("request-id", "req_meta_123")
+                .insert_header("anthropic-ratelimit-requests-remaining", "49")
+                .insert_header("x-unrelated", "ignored"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let client = create_mock_client(&mock_server).await;
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+    let (message, meta) = client.execute_chat_with_response(request).await.unwrap();
+
+    assert_eq!(message.id, "msg_meta");
+    assert_eq!(meta.status, 200);
+    assert_eq!(meta.request_id.as_deref(), Some("req_meta_123"));
+    assert_eq!(
+        meta.rate_limit_header("anthropic-ratelimit-requests-remaining"),
+        Some("49")
+    );
+    assert!(meta.rate_limit_headers.get("x-unrelated").is_none());
+    assert_eq!(meta.rate_limit.unwrap().requests_remaining, Some(49));
+}
+
+#[tokio::test]
+async fn test_request_validation_rejects_bad_extra_field() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0) // Invalid bodies must never reach the API
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .validate_requests(true)
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .extra("temperature", json!("hot"))
+        .build();
+
+    match client.execute_chat(request).await.unwrap_err() {
+        Error::InvalidRequest(message) => assert!(message.contains("/temperature")),
+        other => panic!("Expected invalid request error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_remaining_output_budget() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages/count_tokens"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"input_tokens": 196_000})))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages/count_tokens"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"input_tokens": 120})))
+        .mount(&mock_server)
+        .await;
+
+    // Claude 3.5 Sonnet: 200k context window, 8,192 max output tokens
+    let client = create_mock_client(&mock_server).await;
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("A very long prompt"))
+        .build();
+
+    // Near the end of the context window the remaining space is the limit
+    assert_eq!(
+        client.remaining_output_budget(&request).await.unwrap(),
+        4_000
+    );
+
+    // With plenty of room the budget is clamped to the model's output limit
+    assert_eq!(
+        client.remaining_output_budget(&request).await.unwrap(),
+        8_192
+    );
+}
+
+#[tokio::test]
+async fn test_retry_on_custom_status() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "type": "error",
+            "error": {"type": "invalid_request_error", "message": "Flaky proxy"}
+        })))
+        .expect(3) // Initial attempt plus two retries
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .max_retries(2)
+        .retry_on_status(400)
+        .sleeper(Arc::new(RecordingSleeper::default()))
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    let error = client.execute_chat(request).await.unwrap_err();
+    assert!(matches!(error, Error::InvalidRequest(_)));
+}
+
+#[tokio::test]
+async fn test_default_retryable_statuses_unchanged() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "type": "error",
+            "error": {"type": "invalid_request_error", "message": "Bad request"}
+        })))
+        .expect(1) // 400 is not retried by default
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .max_retries(2)
+        .sleeper(Arc::new(RecordingSleeper::default()))
+        .build()
+        .unwrap();
+
+    let request = client
+        .chat_builder()
+        .user_message(ContentBlock::text("Hello"))
+        .build();
+
+    assert!(client.execute_chat(request).await.is_err());
+}
+
+#[tokio::test]
+async fn test_idempotency_key_from_id_generator() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(529).set_body_json(json!({
+            "type": "error",
+            "error": {"type": "overloaded_error", "message": "Overloaded"}
+        })))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_123",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hi"}],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 10, "output_tokens": 1}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = Client::builder()
+        .api_key("sk-ant-api03-test-key")
+        .base_url(mock_server.uri().as_str())
+        .unwrap()
+        .id_generator(Arc::new(SequentialIdGenerator::new("req")))
+        .sleeper(Arc::new(RecordingSleeper::default()))
+        .build()
+        .unwrap();
+
+    for _ in 0..2 {
+        let request = client
+            .chat_builder()
+            .user_message(ContentBlock::text("Hello"))
+            .build();
+        client.execute_chat(request).await.unwrap();
+    }
+
+    // The retry of the first request reuses its key
+    let keys: Vec<_> = mock_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            request.headers["idempotency-key"]
+                .to_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+    assert_eq!(keys, ["req-1", "req-1", "req-2"]);
+}